@@ -0,0 +1,473 @@
+// 工作区列映射规则模块
+// 不同来源的文件用不同的列名表示同一数据（如 "手机号"、"电话"、"Phone"），导入时按工作区的规则
+// 重命名、合并或删除列，并对取值做统一转换，使同一工作区内data_json的字段名保持一致
+use crate::models::entity::{column_profile_versions, column_profiles};
+use crate::row_validation::ValidationRules;
+use crate::text_normalize::fold_full_width;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Set, Statement, TransactionTrait,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 未指定格式时，日期转换依次尝试的格式
+const DEFAULT_DATE_FORMATS: [&str; 9] = [
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%Y.%m.%d",
+    "%Y年%m月%d日",
+    "%Y%m%d",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+];
+
+/// 工作区的列映射规则
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    /// 目标列：由一个或多个来源列重命名、合并而来
+    pub columns: Vec<ColumnRule>,
+    /// 导入时删除的列
+    pub drop: Vec<String>,
+    /// 数据行校验规则，在列映射之后按目标列名检查
+    pub validation: ValidationRules,
+    /// upsert导入默认使用的键列（列映射之后的列名），上传时未指定key_columns时使用
+    pub key_columns: Vec<String>,
+}
+
+/// 单个目标列的规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnRule {
+    /// 导入后的列名
+    pub target: String,
+    /// 来源列名（不区分大小写），为空时只对同名列应用转换
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// 同一行中多个来源列都有值时的合并方式
+    #[serde(default)]
+    pub merge: MergeStrategy,
+    /// merge为join时的分隔符
+    #[serde(default = "default_join_separator")]
+    pub separator: String,
+    /// 依次应用的取值转换，可以写成名称（如 "trim"）或带参数的对象（如 {"type": "phone", "country_code": "86"}）
+    #[serde(default, deserialize_with = "deserialize_transforms")]
+    pub transforms: Vec<ValueTransform>,
+}
+
+fn default_join_separator() -> String {
+    " ".to_string()
+}
+
+/// 多个来源列的合并方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// 取第一个有值的来源列
+    #[default]
+    First,
+    /// 用分隔符连接所有有值的来源列
+    Join,
+}
+
+/// 取值转换
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ValueTransform {
+    /// 去除首尾空白
+    Trim,
+    Upper,
+    Lower,
+    /// 电话号码规范化：全角转半角，去除空格、横线、括号等分隔符，国际前缀00改写为+；
+    /// 指定country_code时去掉该国家代码，只保留国内号码
+    Phone {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        country_code: Option<String>,
+    },
+    /// 将文本日期转换为ISO日期（2024-01-15）或日期时间（2024-01-15 13:30:00），无法识别时保持原值
+    Date {
+        /// chrono格式，依次尝试，为空时使用常见格式
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        formats: Vec<String>,
+    },
+}
+
+/// 转换列表同时接受名称和对象两种写法
+fn deserialize_transforms<'de, D>(deserializer: D) -> Result<Vec<ValueTransform>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|item| {
+            let item = match item {
+                Value::String(name) => serde_json::json!({ "type": name }),
+                other => other,
+            };
+            serde_json::from_value(item).map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+/// 列名比较，不区分大小写并忽略首尾空白
+pub fn same_column(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+impl ColumnRule {
+    /// 实际参与映射的来源列：未指定时为目标列本身，目标列同名的列总是作为第一个来源
+    fn effective_sources(&self) -> Vec<&str> {
+        let mut sources = vec![self.target.as_str()];
+        sources.extend(
+            self.sources
+                .iter()
+                .map(String::as_str)
+                .filter(|source| !same_column(source, &self.target)),
+        );
+        sources
+    }
+}
+
+impl ColumnMapping {
+    /// 校验规则，返回中文错误说明
+    pub fn validate(&self) -> Result<(), String> {
+        // 目标列本身也计入来源，因此一条规则的目标列不能是另一条规则的来源列
+        let mut sources: Vec<&str> = Vec::new();
+
+        for rule in &self.columns {
+            if rule.target.trim().is_empty() {
+                return Err("目标列名不能为空".to_string());
+            }
+            for source in rule.effective_sources() {
+                if source.trim().is_empty() {
+                    return Err(format!("目标列 {} 的来源列名不能为空", rule.target));
+                }
+                if self.drop.iter().any(|dropped| same_column(dropped, source)) {
+                    return Err(format!("列 {} 同时出现在删除列表中", source));
+                }
+                if sources.iter().any(|used| same_column(used, source)) {
+                    return Err(format!("列 {} 出现在多条规则中", source));
+                }
+                sources.push(source);
+            }
+            for transform in &rule.transforms {
+                if let ValueTransform::Date { formats } = transform {
+                    if formats.iter().any(|format| format.trim().is_empty()) {
+                        return Err(format!("目标列 {} 的日期格式不能为空", rule.target));
+                    }
+                }
+            }
+        }
+        if self.key_columns.iter().any(|column| column.trim().is_empty()) {
+            return Err("键列名不能为空".to_string());
+        }
+        self.validation.validate()
+    }
+
+    /// 对一行数据应用规则：data为显示文本，typed为原生类型值
+    pub fn apply(&self, data: &mut HashMap<String, Value>, typed: &mut HashMap<String, Value>) {
+        let mut mapped = Vec::with_capacity(self.columns.len());
+
+        for rule in &self.columns {
+            // 取出本行中存在的来源列，文件中没有任何来源列时不生成目标列
+            let mut values = Vec::new();
+            for source in rule.effective_sources() {
+                let keys: Vec<String> = data.keys().filter(|key| same_column(key, source)).cloned().collect();
+                for key in keys {
+                    let value = data.remove(&key).unwrap_or(Value::Null);
+                    values.push((value, typed.remove(&key)));
+                }
+            }
+            if values.is_empty() {
+                continue;
+            }
+
+            let mut filled = values.into_iter().filter(|(value, _)| !is_blank(value));
+            let (mut value, mut typed_value) = match rule.merge {
+                MergeStrategy::First => filled.next().unwrap_or((Value::Null, None)),
+                MergeStrategy::Join => {
+                    let parts: Vec<String> = filled.map(|(value, _)| value_text(&value)).collect();
+                    if parts.is_empty() {
+                        (Value::Null, None)
+                    } else {
+                        (Value::String(parts.join(&rule.separator)), None)
+                    }
+                }
+            };
+
+            for transform in &rule.transforms {
+                transform.apply(&mut value, &mut typed_value);
+            }
+            mapped.push((rule.target.clone(), value, typed_value));
+        }
+
+        data.retain(|key, _| !self.drop.iter().any(|dropped| same_column(dropped, key)));
+        typed.retain(|key, _| data.contains_key(key));
+
+        for (target, value, typed_value) in mapped {
+            if let Some(typed_value) = typed_value {
+                typed.insert(target.clone(), typed_value);
+            }
+            data.insert(target, value);
+        }
+    }
+
+    /// 按规则改写字段顺序：来源列替换为目标列（多个来源列时取第一次出现的位置），删除的列移除
+    pub fn map_field_order(&self, field_order: &[String]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::with_capacity(field_order.len());
+        for name in field_order.iter().filter_map(|field| self.map_field(field)) {
+            if !result.contains(&name) {
+                result.push(name);
+            }
+        }
+        result
+    }
+
+    /// 源文件中的列在应用规则后的列名，与apply一致：来源列映射为目标列，被删除的列返回None
+    pub fn map_field(&self, field: &str) -> Option<String> {
+        if let Some(rule) = self
+            .columns
+            .iter()
+            .find(|rule| rule.effective_sources().into_iter().any(|source| same_column(source, field)))
+        {
+            return Some(rule.target.clone());
+        }
+        if self.drop.iter().any(|dropped| same_column(dropped, field)) {
+            None
+        } else {
+            Some(field.to_string())
+        }
+    }
+}
+
+impl ValueTransform {
+    /// 对单个取值应用转换，空值保持不变
+    fn apply(&self, value: &mut Value, typed: &mut Option<Value>) {
+        let Value::String(text) = value else {
+            return;
+        };
+        match self {
+            ValueTransform::Trim => *text = text.trim().to_string(),
+            ValueTransform::Upper => *text = text.to_uppercase(),
+            ValueTransform::Lower => *text = text.to_lowercase(),
+            ValueTransform::Phone { country_code } => {
+                if let Some(phone) = normalize_phone(text, country_code.as_deref()) {
+                    *text = phone;
+                    // 电话号码按文本保存，不再作为数字参与范围筛选
+                    *typed = None;
+                }
+            }
+            ValueTransform::Date { formats } => {
+                // 已经是原生日期时间的单元格无需转换
+                let is_native_date = typed
+                    .as_ref()
+                    .and_then(|typed| typed.get("type"))
+                    .and_then(Value::as_str)
+                    .is_some_and(|kind| matches!(kind, "date" | "datetime"));
+                if is_native_date {
+                    return;
+                }
+                if let Some((display, native)) = parse_date_text(text.trim(), formats) {
+                    *text = display;
+                    *typed = Some(native);
+                }
+            }
+        }
+    }
+}
+
+fn is_blank(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 规范化电话号码，不包含数字时返回None
+fn normalize_phone(text: &str, country_code: Option<&str>) -> Option<String> {
+    let folded: String = text.chars().map(fold_full_width).collect();
+    let trimmed = folded.trim();
+    let digits: String = trimmed.chars().filter(char::is_ascii_digit).collect();
+    if digits.is_empty() {
+        return None;
+    }
+
+    let international = if trimmed.starts_with('+') {
+        Some(digits.clone())
+    } else if digits.len() > 2 && digits.starts_with("00") {
+        Some(digits[2..].to_string())
+    } else {
+        None
+    };
+    let Some(international) = international else {
+        return Some(digits);
+    };
+
+    match country_code.map(|code| code.trim().trim_start_matches('+')) {
+        Some(code) if !code.is_empty() && international.starts_with(code) => Some(international[code.len()..].to_string()),
+        _ => Some(format!("+{}", international)),
+    }
+}
+
+/// 按格式解析文本日期，返回显示文本与原生类型值
+fn parse_date_text(text: &str, formats: &[String]) -> Option<(String, Value)> {
+    let formats: Vec<&str> = if formats.is_empty() {
+        DEFAULT_DATE_FORMATS.to_vec()
+    } else {
+        formats.iter().map(String::as_str).collect()
+    };
+
+    for format in formats {
+        if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(text, format) {
+            return Some((
+                datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+                serde_json::json!({ "type": "datetime", "value": datetime.format("%Y-%m-%dT%H:%M:%S").to_string() }),
+            ));
+        }
+        if let Ok(date) = chrono::NaiveDate::parse_from_str(text, format) {
+            let iso = date.format("%Y-%m-%d").to_string();
+            return Some((iso.clone(), serde_json::json!({ "type": "date", "value": iso })));
+        }
+    }
+    None
+}
+
+/// 读取工作区当前生效的列映射规则及其版本号
+pub async fn load_mapping(db: &DatabaseConnection, workspace_id: i32) -> Result<Option<(i32, ColumnMapping)>, DbErr> {
+    let Some(profile) = find_profile(db, workspace_id).await? else {
+        return Ok(None);
+    };
+    let mapping = serde_json::from_value(profile.rules)
+        .map_err(|e| DbErr::Custom(format!("工作区 {} 的列映射规则无法解析: {}", workspace_id, e)))?;
+    Ok(Some((profile.version, mapping)))
+}
+
+/// 查询工作区当前的列映射规则
+pub async fn find_profile(db: &DatabaseConnection, workspace_id: i32) -> Result<Option<column_profiles::Model>, DbErr> {
+    column_profiles::Entity::find()
+        .filter(column_profiles::Column::WorkspaceId.eq(workspace_id))
+        .one(db)
+        .await
+}
+
+/// 按版本号从新到旧列出规则的历史版本
+pub async fn list_versions(db: &DatabaseConnection, workspace_id: i32) -> Result<Vec<column_profile_versions::Model>, DbErr> {
+    column_profile_versions::Entity::find()
+        .filter(column_profile_versions::Column::WorkspaceId.eq(workspace_id))
+        .order_by_desc(column_profile_versions::Column::Version)
+        .all(db)
+        .await
+}
+
+/// 保存工作区的列映射规则（新建或更新），同时记录新版本
+pub async fn save_profile(
+    db: &DatabaseConnection,
+    workspace_id: i32,
+    user_id: i32,
+    mapping: &ColumnMapping,
+) -> Result<column_profiles::Model, DbErr> {
+    let rules = serde_json::to_value(mapping).map_err(|e| DbErr::Custom(format!("序列化列映射规则失败: {}", e)))?;
+    let txn = db.begin().await?;
+    let version = next_version(&txn, workspace_id).await?;
+    let now = chrono::Utc::now();
+
+    column_profile_versions::ActiveModel {
+        id: Default::default(),
+        workspace_id: Set(workspace_id),
+        version: Set(version),
+        rules: Set(Some(rules.clone())),
+        created_by: Set(Some(user_id)),
+        created_at: Set(now),
+    }
+    .insert(&txn)
+    .await?;
+
+    let profile = match column_profiles::Entity::find()
+        .filter(column_profiles::Column::WorkspaceId.eq(workspace_id))
+        .one(&txn)
+        .await?
+    {
+        Some(existing) => {
+            let mut active: column_profiles::ActiveModel = existing.into();
+            active.version = Set(version);
+            active.rules = Set(rules);
+            active.updated_by = Set(Some(user_id));
+            active.updated_at = Set(now);
+            active.update(&txn).await?
+        }
+        None => {
+            column_profiles::ActiveModel {
+                id: Default::default(),
+                workspace_id: Set(workspace_id),
+                version: Set(version),
+                rules: Set(rules),
+                updated_by: Set(Some(user_id)),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+            .insert(&txn)
+            .await?
+        }
+    };
+
+    txn.commit().await?;
+    Ok(profile)
+}
+
+/// 删除工作区的列映射规则，历史版本中记录一条删除版本；没有规则时返回false
+pub async fn delete_profile(db: &DatabaseConnection, workspace_id: i32, user_id: i32) -> Result<bool, DbErr> {
+    let txn = db.begin().await?;
+    let deleted = column_profiles::Entity::delete_many()
+        .filter(column_profiles::Column::WorkspaceId.eq(workspace_id))
+        .exec(&txn)
+        .await?;
+    if deleted.rows_affected == 0 {
+        return Ok(false);
+    }
+
+    let version = next_version(&txn, workspace_id).await?;
+    column_profile_versions::ActiveModel {
+        id: Default::default(),
+        workspace_id: Set(workspace_id),
+        version: Set(version),
+        rules: Set(None),
+        created_by: Set(Some(user_id)),
+        created_at: Set(chrono::Utc::now()),
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+    Ok(true)
+}
+
+/// 锁定工作区记录并计算下一个版本号，避免并发保存得到相同的版本号
+async fn next_version(txn: &sea_orm::DatabaseTransaction, workspace_id: i32) -> Result<i32, DbErr> {
+    txn.execute(Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Postgres,
+        "SELECT id FROM workspaces WHERE id = $1 FOR UPDATE",
+        [workspace_id.into()],
+    ))
+    .await?;
+
+    let row = txn
+        .query_one(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Postgres,
+            "SELECT COALESCE(MAX(version), 0) + 1 AS version FROM column_profile_versions WHERE workspace_id = $1",
+            [workspace_id.into()],
+        ))
+        .await?
+        .ok_or_else(|| DbErr::Custom("计算列映射规则版本号失败".to_string()))?;
+    row.try_get("", "version")
+}
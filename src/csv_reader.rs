@@ -0,0 +1,195 @@
+// CSV/TSV文本表格读取模块
+// 自动识别文本编码、分隔符和引号字符，并转换为与Excel工作表一致的单元格区域
+use calamine::{Data, Range};
+use encoding_rs::{Encoding, GB18030, UTF_16BE, UTF_16LE, UTF_8};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// 候选分隔符：逗号、制表符、分号、竖线
+const DELIMITER_CANDIDATES: [u8; 4] = [b',', b'\t', b';', b'|'];
+/// 候选引号字符：双引号、单引号
+const QUOTE_CANDIDATES: [u8; 2] = [b'"', b'\''];
+/// 用于探测分隔符的最大采样记录数
+const SNIFF_RECORD_LIMIT: usize = 50;
+
+/// 文本表格的解析方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvDialect {
+    pub delimiter: u8,
+    pub quote: u8,
+}
+
+/// 判断文件是否按分隔文本（CSV/TSV）处理
+pub fn is_delimited_text_file(file_path: &str) -> bool {
+    Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_ascii_lowercase().as_str(), "csv" | "tsv"))
+        .unwrap_or(false)
+}
+
+/// 检测文本编码并解码为字符串（支持UTF-8、带BOM的UTF-8、UTF-16、GBK）
+pub fn decode_text(bytes: &[u8]) -> (String, &'static Encoding) {
+    // 优先根据BOM判断编码
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return (text.into_owned(), encoding);
+    }
+
+    // 无BOM的UTF-16：ASCII字符的高字节为0，根据0字节的位置判断字节序
+    if let Some(encoding) = guess_utf16_without_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(bytes);
+        return (text.into_owned(), encoding);
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), UTF_8);
+    }
+
+    // 非UTF-8文本按GB18030（GBK的超集）解码
+    let (text, _) = GB18030.decode_without_bom_handling(bytes);
+    (text.into_owned(), GB18030)
+}
+
+fn guess_utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096)];
+    if sample.len() < 4 || !sample.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+
+    // 超过一半的字符在同一侧出现0字节时才认定为UTF-16
+    if odd_zeros * 2 > pairs && even_zeros * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 2 > pairs && odd_zeros * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// 检测分隔符与引号字符，无法识别（如只有一列）时使用逗号和双引号
+pub fn detect_dialect(text: &str) -> CsvDialect {
+    sniff_dialect(text).unwrap_or(CsvDialect { delimiter: b',', quote: b'"' })
+}
+
+/// 探测分隔符与引号字符，没有方言能把多数记录解析为相同的两列以上时返回None（不是分隔文本，或只有一列）
+///
+/// 对每组候选方言解析前若干条记录，选择字段数一致的记录最多的组合，记录数相同时取列数多的。
+/// 先比较一致性：引号字符选错时带分隔符的字段会被拆开，列数变多但各行不再一致。
+pub fn sniff_dialect(text: &str) -> Option<CsvDialect> {
+    let mut best = None;
+    let mut best_score = (0usize, 0usize);
+
+    for &delimiter in &DELIMITER_CANDIDATES {
+        for &quote in &QUOTE_CANDIDATES {
+            let dialect = CsvDialect { delimiter, quote };
+            let score = score_dialect(text, dialect);
+            if score > best_score {
+                best_score = score;
+                best = Some(dialect);
+            }
+        }
+    }
+
+    best
+}
+
+/// 方言的得分：(字段数一致的记录数, 列数)，无法解析、少于两列或字段数一致的记录不到一半时为 (0, 0)
+fn score_dialect(text: &str, dialect: CsvDialect) -> (usize, usize) {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut counts: Vec<usize> = Vec::new();
+    for record in reader.records().take(SNIFF_RECORD_LIMIT) {
+        match record {
+            Ok(record) => counts.push(record.len()),
+            Err(_) => return (0, 0),
+        }
+    }
+
+    // 取出现次数最多的字段数作为该方言的列数
+    let mut frequency: std::collections::HashMap<usize, usize> = std::collections::HashMap::new();
+    for count in &counts {
+        *frequency.entry(*count).or_default() += 1;
+    }
+    let (columns, hits) = frequency
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)))
+        .unwrap_or((0, 0));
+
+    if columns < 2 || hits * 2 < counts.len() {
+        return (0, 0);
+    }
+    (hits, columns)
+}
+
+/// 读取CSV/TSV文件，返回与Excel工作表相同结构的单元格区域
+pub fn read_delimited_range(file_path: &str) -> Result<Range<Data>, Box<dyn std::error::Error>> {
+    let bytes = fs::read(file_path)?;
+    let (text, encoding) = decode_text(&bytes);
+
+    // 兼容Excel导出的 "sep=;" 首行声明
+    let (text, declared_delimiter) = strip_separator_hint(&text);
+    let mut dialect = detect_dialect(text);
+    if let Some(delimiter) = declared_delimiter {
+        dialect.delimiter = delimiter;
+    }
+    info!(
+        "文本表格解析: 文件={} 编码={} 分隔符={:?} 引号={:?}",
+        file_path,
+        encoding.name(),
+        dialect.delimiter as char,
+        dialect.quote as char
+    );
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut records: Vec<Vec<String>> = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        records.push(record.iter().map(ToOwned::to_owned).collect());
+    }
+
+    let height = records.len();
+    let width = records.iter().map(Vec::len).max().unwrap_or(0);
+    if height == 0 || width == 0 {
+        return Ok(Range::empty());
+    }
+
+    let mut range = Range::new((0, 0), ((height - 1) as u32, (width - 1) as u32));
+    for (row_idx, record) in records.into_iter().enumerate() {
+        for (col_idx, field) in record.into_iter().enumerate() {
+            if !field.is_empty() {
+                range.set_value((row_idx as u32, col_idx as u32), Data::String(field));
+            }
+        }
+    }
+
+    Ok(range)
+}
+
+fn strip_separator_hint(text: &str) -> (&str, Option<u8>) {
+    let first_line_end = text.find('\n').unwrap_or(text.len());
+    let first_line = text[..first_line_end].trim_end_matches('\r');
+    if let Some(sep) = first_line.strip_prefix("sep=") {
+        if sep.len() == 1 {
+            let rest = text.get(first_line_end + 1..).unwrap_or("");
+            return (rest, Some(sep.as_bytes()[0]));
+        }
+    }
+    (text, None)
+}
@@ -1,6 +1,6 @@
-use sea_orm::{Database, DatabaseConnection, DbErr, ConnectionTrait};
+use sea_orm::{Database, DatabaseConnection, DbErr, ConnectionTrait, TransactionTrait};
 use std::env;
-use tracing::info;
+use tracing::{info, warn};
 
 pub async fn connect_database() -> Result<DatabaseConnection, DbErr> {
     // 从环境变量获取数据库URL，必须是PostgreSQL
@@ -18,10 +18,36 @@ pub async fn connect_database() -> Result<DatabaseConnection, DbErr> {
     
     // 创建数据库表（如果不存在）
     create_tables_if_not_exists(&db).await?;
+
+    check_text_search_encoding(&db).await?;
     
     Ok(db)
 }
 
+/// 全文检索依赖数据库的字符分类，非UTF-8编码或字符分类下中文、阿拉伯文等词语会被忽略
+async fn check_text_search_encoding(db: &DatabaseConnection) -> Result<(), DbErr> {
+    use sea_orm::Statement;
+
+    let row = db
+        .query_one(Statement::from_string(
+            sea_orm::DatabaseBackend::Postgres,
+            "SELECT current_setting('server_encoding') AS encoding, current_setting('lc_ctype') AS ctype".to_string(),
+        ))
+        .await?;
+    if let Some(row) = row {
+        let encoding: String = row.try_get("", "encoding")?;
+        let ctype: String = row.try_get("", "ctype")?;
+        let ctype_lower = ctype.to_ascii_lowercase();
+        if encoding != "UTF8" || !(ctype_lower.contains("utf8") || ctype_lower.contains("utf-8")) {
+            warn!(
+                "数据库编码为 {}、LC_CTYPE 为 {}，全文检索将无法识别非ASCII文字，请使用UTF8编码及UTF-8字符分类创建数据库",
+                encoding, ctype
+            );
+        }
+    }
+    Ok(())
+}
+
 async fn create_tables_if_not_exists(db: &DatabaseConnection) -> Result<(), DbErr> {
     use sea_orm::Statement;
     
@@ -93,6 +119,7 @@ async fn create_tables_if_not_exists(db: &DatabaseConnection) -> Result<(), DbEr
             file_size BIGINT NOT NULL,
             file_hash TEXT NOT NULL,
             field_order JSONB,
+            import_metadata JSONB,
             created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
             updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
         )
@@ -114,6 +141,7 @@ async fn create_tables_if_not_exists(db: &DatabaseConnection) -> Result<(), DbEr
             row_number INTEGER NOT NULL,
             sheet_name TEXT NOT NULL DEFAULT 'Sheet1',
             data_json JSONB NOT NULL,
+            typed_json JSONB NOT NULL DEFAULT '{}',
             search_text TEXT NOT NULL
         )
         "#.to_string()
@@ -122,6 +150,128 @@ async fn create_tables_if_not_exists(db: &DatabaseConnection) -> Result<(), DbEr
     db.execute(create_excel_data_table).await?;
     info!("excel_data表检查完成");
 
+    // 创建import_jobs表
+    let create_import_jobs_table = Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        r#"
+        CREATE TABLE IF NOT EXISTS import_jobs (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+            file_path TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            file_hash TEXT,
+            options JSONB,
+            confirm_token TEXT,
+            status TEXT NOT NULL DEFAULT 'queued',
+            rows_processed BIGINT NOT NULL DEFAULT 0,
+            rows_inserted BIGINT NOT NULL DEFAULT 0,
+            rows_updated BIGINT NOT NULL DEFAULT 0,
+            rows_unchanged BIGINT NOT NULL DEFAULT 0,
+            rows_rejected BIGINT NOT NULL DEFAULT 0,
+            file_id INTEGER REFERENCES files(id) ON DELETE SET NULL,
+            current_sheet TEXT,
+            error TEXT,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+            started_at TIMESTAMP WITH TIME ZONE,
+            finished_at TIMESTAMP WITH TIME ZONE,
+            updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+        )
+        "#.to_string()
+    );
+
+    db.execute(create_import_jobs_table).await?;
+    info!("import_jobs表检查完成");
+
+    // 创建列映射规则表及其历史版本表
+    let create_column_profiles_table = Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        r#"
+        CREATE TABLE IF NOT EXISTS column_profiles (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL UNIQUE REFERENCES workspaces(id) ON DELETE CASCADE,
+            version INTEGER NOT NULL,
+            rules JSONB NOT NULL,
+            updated_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+            created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+        )
+        "#.to_string()
+    );
+
+    db.execute(create_column_profiles_table).await?;
+
+    let create_column_profile_versions_table = Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        r#"
+        CREATE TABLE IF NOT EXISTS column_profile_versions (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            version INTEGER NOT NULL,
+            rules JSONB,
+            created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+            created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+            UNIQUE (workspace_id, version)
+        )
+        "#.to_string()
+    );
+
+    db.execute(create_column_profile_versions_table).await?;
+    info!("column_profiles表检查完成");
+
+    // 创建导入拒绝记录表：未通过校验或无法写入的数据行，属于导入的文件；
+    // 严格模式下整个文件未导入时没有文件记录，属于导入任务
+    let create_import_rejections_table = Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        r#"
+        CREATE TABLE IF NOT EXISTS import_rejections (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE,
+            file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+            job_id INTEGER REFERENCES import_jobs(id) ON DELETE CASCADE,
+            sheet_name TEXT NOT NULL,
+            row_number INTEGER NOT NULL,
+            reasons JSONB NOT NULL,
+            data_json JSONB NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+            CHECK (file_id IS NOT NULL OR job_id IS NOT NULL)
+        )
+        "#.to_string()
+    );
+
+    db.execute(create_import_rejections_table).await?;
+    info!("import_rejections表检查完成");
+
+    // 创建导出任务表
+    let create_export_jobs_table = Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        r#"
+        CREATE TABLE IF NOT EXISTS export_jobs (
+            id SERIAL PRIMARY KEY,
+            created_by INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE,
+            format TEXT NOT NULL,
+            request JSONB NOT NULL,
+            status TEXT NOT NULL DEFAULT 'queued',
+            total_rows BIGINT NOT NULL DEFAULT 0,
+            file_path TEXT,
+            file_name TEXT,
+            file_size BIGINT,
+            error TEXT,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+            started_at TIMESTAMP WITH TIME ZONE,
+            finished_at TIMESTAMP WITH TIME ZONE,
+            expires_at TIMESTAMP WITH TIME ZONE,
+            updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+        )
+        "#.to_string()
+    );
+
+    db.execute(create_export_jobs_table).await?;
+    info!("export_jobs表检查完成");
+
     // 增量升级旧表结构
     let schema_upgrades = vec![
         "ALTER TABLE files ADD COLUMN IF NOT EXISTS workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE",
@@ -130,6 +280,10 @@ async fn create_tables_if_not_exists(db: &DatabaseConnection) -> Result<(), DbEr
         "ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS description TEXT",
         "ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS is_public BOOLEAN NOT NULL DEFAULT FALSE",
         "DROP INDEX IF EXISTS idx_excel_data_search_text",
+        "ALTER TABLE files ADD COLUMN IF NOT EXISTS import_metadata JSONB",
+        "ALTER TABLE excel_data ADD COLUMN IF NOT EXISTS typed_json JSONB NOT NULL DEFAULT '{}'",
+        // 全文检索向量由search_text自动生成，配置需与excel_processor_sea中的SEARCH_TEXT_CONFIG一致
+        "ALTER TABLE excel_data ADD COLUMN IF NOT EXISTS search_vector tsvector GENERATED ALWAYS AS (to_tsvector('simple', search_text)) STORED",
     ];
 
     for sql in schema_upgrades {
@@ -152,17 +306,179 @@ async fn create_tables_if_not_exists(db: &DatabaseConnection) -> Result<(), DbEr
         "CREATE INDEX IF NOT EXISTS idx_excel_data_workspace_id ON excel_data(workspace_id)",
         "CREATE INDEX IF NOT EXISTS idx_excel_data_workspace_import_time ON excel_data(workspace_id, import_time DESC)",
         "CREATE INDEX IF NOT EXISTS idx_excel_data_file_id ON excel_data(file_id)",
+        "CREATE INDEX IF NOT EXISTS idx_excel_data_file_sheet_row ON excel_data(file_id, sheet_name, row_number, id)",
         "CREATE INDEX IF NOT EXISTS idx_excel_data_import_time ON excel_data(import_time)",
         "CREATE INDEX IF NOT EXISTS idx_excel_data_data_json ON excel_data USING GIN (data_json)",
+        "CREATE INDEX IF NOT EXISTS idx_excel_data_search_vector ON excel_data USING GIN (search_vector)",
         "CREATE INDEX IF NOT EXISTS idx_files_file_path ON files(file_path)",
         "CREATE INDEX IF NOT EXISTS idx_files_file_hash ON files(file_hash)",
+        "CREATE INDEX IF NOT EXISTS idx_import_jobs_status ON import_jobs(status, id)",
+        "CREATE INDEX IF NOT EXISTS idx_import_jobs_workspace_id ON import_jobs(workspace_id)",
+        // 查找重复文件时按文件查询其导入任务
+        "CREATE INDEX IF NOT EXISTS idx_import_jobs_file_id ON import_jobs(file_id)",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_import_jobs_confirm_token ON import_jobs(confirm_token)",
+        "CREATE INDEX IF NOT EXISTS idx_import_rejections_file_id ON import_rejections(file_id, sheet_name, row_number)",
+        "CREATE INDEX IF NOT EXISTS idx_import_rejections_job_id ON import_rejections(job_id, sheet_name, row_number)",
+        "CREATE INDEX IF NOT EXISTS idx_export_jobs_status ON export_jobs(status, id)",
+        "CREATE INDEX IF NOT EXISTS idx_export_jobs_created_by ON export_jobs(created_by, id DESC)",
+        "CREATE INDEX IF NOT EXISTS idx_export_jobs_workspace_id ON export_jobs(workspace_id)",
     ];
     
     for index_sql in indexes {
         let statement = Statement::from_string(sea_orm::DatabaseBackend::Postgres, index_sql.to_string());
         db.execute(statement).await?;
     }
+
+    // 模糊搜索依赖pg_trgm扩展，无权限安装时仅影响模糊搜索
+    let create_trgm_extension = Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        "CREATE EXTENSION IF NOT EXISTS pg_trgm".to_string(),
+    );
+    match db.execute(create_trgm_extension).await {
+        Ok(_) => {
+            let trgm_index = Statement::from_string(
+                sea_orm::DatabaseBackend::Postgres,
+                "CREATE INDEX IF NOT EXISTS idx_excel_data_search_text_trgm ON excel_data USING GIN (search_text gin_trgm_ops)".to_string(),
+            );
+            db.execute(trgm_index).await?;
+        }
+        Err(e) => warn!("pg_trgm扩展启用失败，模糊搜索不可用: {}", e),
+    }
     
+    // normalize()与chr(1600)只能在UTF8编码的数据库中使用，其他编码下检索文本原样返回
+    // （导入时已在应用中规范化，其他编码下的全文检索问题见check_text_search_encoding）
+    let server_encoding = db
+        .query_one(Statement::from_string(
+            sea_orm::DatabaseBackend::Postgres,
+            "SELECT current_setting('server_encoding') AS encoding".to_string(),
+        ))
+        .await?
+        .map(|row| row.try_get::<String>("", "encoding"))
+        .transpose()?;
+    let normalize_search_function = if server_encoding.as_deref() == Some("UTF8") {
+        r#"
+        CREATE OR REPLACE FUNCTION excel_normalize_search(value TEXT) RETURNS TEXT
+        LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+            SELECT replace(normalize(value, NFKC), chr(1600), '')
+        $$
+        "#
+    } else {
+        r#"
+        CREATE OR REPLACE FUNCTION excel_normalize_search(value TEXT) RETURNS TEXT
+        LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+            SELECT value
+        $$
+        "#
+    };
+
+    // 搜索使用的函数：检索文本规范化（与text_normalize::normalize_for_search规则相同），
+    // 以及范围筛选使用的安全类型转换函数（单元格文本无法转换时返回NULL，不会中断整个查询）
+    let cast_functions = vec![
+        // 搜索词切分为词素后按前缀匹配并取交集，没有词素时返回NULL；配置与search_vector一致
+        r#"
+        CREATE OR REPLACE FUNCTION excel_prefix_tsquery(keyword TEXT) RETURNS tsquery
+        LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+            SELECT string_agg('''' || replace(replace(lexeme, '\', '\\'), '''', '''''') || ''':*', ' & ')::tsquery
+            FROM unnest(to_tsvector('simple', keyword))
+        $$
+        "#,
+        normalize_search_function,
+        r#"
+        CREATE OR REPLACE FUNCTION excel_try_numeric(value TEXT) RETURNS NUMERIC
+        LANGUAGE plpgsql IMMUTABLE AS $$
+        BEGIN
+            IF value !~ '^\s*[-+]?(\d+\.?\d*|\.\d+)([eE][-+]?\d+)?\s*$' THEN
+                RETURN NULL;
+            END IF;
+            RETURN value::numeric;
+        EXCEPTION WHEN others THEN
+            RETURN NULL;
+        END
+        $$
+        "#,
+        // ISO日期/时间直接转换；纯数字按Excel 1900日期系统的序列号处理（序列号1为1900-01-01）
+        r#"
+        CREATE OR REPLACE FUNCTION excel_try_timestamp(value TEXT) RETURNS TIMESTAMP
+        LANGUAGE plpgsql IMMUTABLE AS $$
+        DECLARE
+            serial NUMERIC;
+        BEGIN
+            IF value ~ '^\s*\d{4}-\d{1,2}-\d{1,2}([ T]\d{1,2}:\d{2}(:\d{2}(\.\d+)?)?)?\s*$' THEN
+                RETURN value::timestamp;
+            ELSIF value ~ '^\s*\d+(\.\d+)?\s*$' THEN
+                serial := value::numeric;
+                IF serial <= 0 OR serial >= 2958466 THEN
+                    RETURN NULL;
+                END IF;
+                -- Excel将1900年误当作闰年，序列号60及以前的日期基准需后移一天
+                IF serial < 61 THEN
+                    serial := serial + 1;
+                END IF;
+                RETURN TIMESTAMP '1899-12-30' + serial * INTERVAL '1 day';
+            END IF;
+            RETURN NULL;
+        EXCEPTION WHEN others THEN
+            RETURN NULL;
+        END
+        $$
+        "#,
+        // upsert键列与唯一列的查找：列名转小写、取值去掉首尾空格后的对象（与upsert_key::key_value的比较规则一致），
+        // 由idx_excel_data_key_fields索引；修改定义后需要重建该索引
+        r#"
+        CREATE OR REPLACE FUNCTION excel_key_fields(data JSONB) RETURNS JSONB
+        LANGUAGE sql IMMUTABLE PARALLEL SAFE AS $$
+            SELECT COALESCE(jsonb_object_agg(lower(trim(key)), trim(value)), '{}'::jsonb)
+            FROM jsonb_each_text(data)
+            WHERE value IS NOT NULL
+        $$
+        "#,
+    ];
+
+    for function_sql in cast_functions {
+        let statement = Statement::from_string(sea_orm::DatabaseBackend::Postgres, function_sql.to_string());
+        db.execute(statement).await?;
+    }
+    db.execute(Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        "CREATE INDEX IF NOT EXISTS idx_excel_data_key_fields ON excel_data USING GIN (excel_key_fields(data_json) jsonb_path_ops)"
+            .to_string(),
+    ))
+    .await?;
+
+    // 一次性数据迁移，执行后记录在schema_migrations中；迁移与记录在同一事务中提交
+    let data_migrations = vec![
+        // 引入检索文本规范化之前导入的数据，search_vector随search_text重新生成
+        (
+            "normalize_search_text",
+            "UPDATE excel_data SET search_text = excel_normalize_search(search_text) \
+             WHERE search_text IS DISTINCT FROM excel_normalize_search(search_text)",
+        ),
+    ];
+    db.execute(Statement::from_string(
+        sea_orm::DatabaseBackend::Postgres,
+        "CREATE TABLE IF NOT EXISTS schema_migrations (name TEXT PRIMARY KEY, applied_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW())"
+            .to_string(),
+    ))
+    .await?;
+    for (name, sql) in data_migrations {
+        let txn = db.begin().await?;
+        let claimed = txn
+            .execute(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                "INSERT INTO schema_migrations (name) VALUES ($1) ON CONFLICT (name) DO NOTHING",
+                [name.into()],
+            ))
+            .await?;
+        if claimed.rows_affected() == 0 {
+            continue;
+        }
+        let updated = txn
+            .execute(Statement::from_string(sea_orm::DatabaseBackend::Postgres, sql.to_string()))
+            .await?;
+        txn.commit().await?;
+        info!("数据迁移 {} 完成，更新 {} 行", name, updated.rows_affected());
+    }
+
     info!("数据库索引检查完成");
     info!("数据库表和索引初始化完成");
     
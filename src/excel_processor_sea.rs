@@ -1,14 +1,773 @@
 use crate::models::{ExcelData, SearchResponse, StatsResponse};
-use crate::models::entity::{excel_data, files, workspaces};
-use calamine::{open_workbook_auto, Data, Reader};
+use crate::models::entity::{excel_data, files, import_rejections, workspaces};
+use crate::column_profile::{self, ColumnMapping};
+use crate::csv_reader;
+use crate::row_validation::{self, RejectionOwner, RowRejection, RowValidator, RowViolation};
+use crate::search_export::{ExportError, ExportOutput, ExportSheet, ExportSink, SearchExporter};
+use crate::sheet_header;
+use crate::search_query::{ParsedQuery, QueryExpr, RangeBound, RangeFilter, RangeValue, SearchCriteria, SearchTerm};
+use crate::text_normalize::{contains_cjk, normalize_for_search};
+use crate::upsert_key::{self, UpsertAction};
 use md5;
+use calamine::{open_workbook_auto, Data, Dimensions, ExcelDateTime, Range, Reader, SheetType, SheetVisible, Sheets};
 use rust_xlsxwriter::{Workbook, Format};
-use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder, QuerySelect, PaginatorTrait, Set, TransactionTrait};
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbBackend, EntityTrait, QueryFilter, QueryOrder, QuerySelect, PaginatorTrait, Set, Statement, TransactionTrait};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io::Read;
 use std::path::Path;
-use tracing::{info, error};
+use tracing::{info, error, warn};
+
+/// 导入时需要移除的不可见字符：空字符、BOM、零宽空格、零宽非连接符、零宽连接符
+const INVISIBLE_CHARS: [char; 5] = ['\u{0000}', '\u{FEFF}', '\u{200B}', '\u{200C}', '\u{200D}'];
+
+/// 从文件中加载的工作簿
+struct LoadedWorkbook {
+    /// 文件中全部工作表的名称，包括未加载的
+    sheet_names: Vec<String>,
+    sheets: Vec<LoadedSheet>,
+    /// 按加载条件未加载的数据工作表及其可见性
+    unloaded: Vec<(String, SheetVisibility)>,
+}
+
+/// 从文件中加载的工作表
+struct LoadedSheet {
+    name: String,
+    visibility: SheetVisibility,
+    range: Range<Data>,
+    /// 合并单元格区域（工作表绝对坐标），格式不支持读取合并信息时为None
+    merged_regions: Option<Vec<Dimensions>>,
+}
+
+/// 多行表头允许的最大行数
+const MAX_HEADER_ROWS: usize = 10;
+
+/// 工作表的可见性
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SheetVisibility {
+    Visible,
+    Hidden,
+    /// 深度隐藏，只能通过VBA取消隐藏
+    VeryHidden,
+}
+
+impl From<SheetVisible> for SheetVisibility {
+    fn from(visible: SheetVisible) -> Self {
+        match visible {
+            SheetVisible::Visible => Self::Visible,
+            SheetVisible::Hidden => Self::Hidden,
+            SheetVisible::VeryHidden => Self::VeryHidden,
+        }
+    }
+}
+
+/// 工作表不被导入的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SheetSkipReason {
+    /// 指定了include_sheets且不在其中
+    NotIncluded,
+    /// 在exclude_sheets中
+    Excluded,
+    /// 隐藏或深度隐藏，且设置了skip_hidden_sheets
+    Hidden,
+}
+
+/// 工作表名称比较，与Excel一致不区分大小写
+fn same_sheet_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// 名称列表（工作表名、列名），既可以是逗号分隔的字符串（URL查询参数），也可以是字符串数组（已保存的JSON）
+fn deserialize_name_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NameList {
+        Joined(String),
+        List(Vec<String>),
+    }
+
+    let names = match NameList::deserialize(deserializer)? {
+        NameList::Joined(joined) => joined.split(',').map(str::to_string).collect(),
+        NameList::List(list) => list,
+    };
+    Ok(names
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+/// 导入方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// 作为新文件导入全部数据行
+    #[default]
+    Append,
+    /// 替换工作区中同名文件的上一版本：沿用其文件记录，删除旧数据后重新导入
+    Replace,
+    /// 按键列匹配工作区中已有的数据行：取值有变化的就地更新，没有匹配的作为新文件的数据插入
+    Upsert,
+}
+
+/// 单次上传的导入选项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    /// 表头所在行（Excel中的行号，从1开始），优先于自动检测
+    pub header_row: Option<usize>,
+    /// 表头行数，多行表头按 "上级 / 下级" 合并为列名
+    pub header_rows: usize,
+    /// 未指定表头行时自动检测表头（跳过表头上方的标题行）
+    pub auto_detect_header: bool,
+    /// 将合并单元格的值填充到合并区域覆盖的每个单元格（仅xlsx和xls支持读取合并信息）
+    pub fill_merged_cells: bool,
+    /// 只导入这些工作表（名称不区分大小写），为空时导入全部工作表
+    #[serde(deserialize_with = "deserialize_name_list")]
+    pub include_sheets: Vec<String>,
+    /// 不导入这些工作表，优先于include_sheets
+    #[serde(deserialize_with = "deserialize_name_list")]
+    pub exclude_sheets: Vec<String>,
+    /// 跳过隐藏和深度隐藏的工作表，include_sheets中明确列出的除外
+    pub skip_hidden_sheets: bool,
+    pub mode: ImportMode,
+    /// upsert模式匹配数据行的键列（列映射之后的列名），为空时使用列映射规则中的key_columns
+    #[serde(deserialize_with = "deserialize_name_list")]
+    pub key_columns: Vec<String>,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            header_row: None,
+            header_rows: 1,
+            auto_detect_header: false,
+            fill_merged_cells: false,
+            include_sheets: Vec::new(),
+            exclude_sheets: Vec::new(),
+            skip_hidden_sheets: false,
+            mode: ImportMode::Append,
+            key_columns: Vec::new(),
+        }
+    }
+}
+
+impl ImportOptions {
+    /// 校验选项取值，返回中文错误说明
+    pub fn validate(&self) -> Result<(), String> {
+        if self.header_row == Some(0) {
+            return Err("表头行号从1开始".to_string());
+        }
+        if !(1..=MAX_HEADER_ROWS).contains(&self.header_rows) {
+            return Err(format!("表头行数必须在1到{}之间", MAX_HEADER_ROWS));
+        }
+        Ok(())
+    }
+
+    /// 按工作表选择规则判断工作表是否导入，返回不导入的原因
+    pub fn sheet_skip_reason(&self, sheet_name: &str, visibility: SheetVisibility) -> Option<SheetSkipReason> {
+        let matches = |names: &[String]| names.iter().any(|name| same_sheet_name(name, sheet_name));
+
+        if matches(&self.exclude_sheets) {
+            return Some(SheetSkipReason::Excluded);
+        }
+        if self.include_sheets.is_empty() {
+            if self.skip_hidden_sheets && visibility != SheetVisibility::Visible {
+                return Some(SheetSkipReason::Hidden);
+            }
+            return None;
+        }
+        if matches(&self.include_sheets) {
+            None
+        } else {
+            Some(SheetSkipReason::NotIncluded)
+        }
+    }
+
+    /// upsert模式的键列：上传时指定的优先，否则使用列映射规则中的设置；其他模式返回空列表
+    pub fn upsert_key_columns(&self, mapping: Option<&ColumnMapping>) -> Vec<String> {
+        match self.mode {
+            ImportMode::Upsert if self.key_columns.is_empty() => {
+                mapping.map(|mapping| mapping.key_columns.clone()).unwrap_or_default()
+            }
+            ImportMode::Upsert => self.key_columns.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// include_sheets中在文件里找不到的工作表名称
+    fn missing_included_sheets<'a>(&'a self, sheet_names: &[&str]) -> Vec<&'a str> {
+        self.include_sheets
+            .iter()
+            .filter(|name| !sheet_names.iter().any(|sheet_name| same_sheet_name(name, sheet_name)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// 工作表预览：导入前查看文件中的工作表、数据行数与表头
+#[derive(Debug, Clone, Serialize)]
+pub struct SheetPreview {
+    pub name: String,
+    pub visibility: SheetVisibility,
+    /// 按当前导入选项是否会导入
+    pub selected: bool,
+    pub skip_reason: Option<SheetSkipReason>,
+    /// 表头下方的数据行数，试导入时不会导入的工作表不读取内容，为0
+    pub row_count: usize,
+    pub headers: Vec<String>,
+    /// 按当前导入选项无法读取时的错误说明，如表头行超出范围
+    pub error: Option<String>,
+}
+
+/// 文件预览结果
+#[derive(Debug, Clone, Serialize)]
+pub struct FilePreview {
+    pub sheets: Vec<SheetPreview>,
+    /// include_sheets中在文件里找不到的工作表名称
+    pub missing_sheets: Vec<String>,
+}
+
+/// 试导入时每个工作表最多列出的拒绝行与可疑字段数量，超出部分只计数
+const MAX_DRY_RUN_ISSUES: usize = 100;
+
+/// PostgreSQL单个tsvector中词条的最大总字节数，超过时to_tsvector报错，该行无法写入
+const TSVECTOR_MAX_BYTES: usize = 1_048_575;
+
+/// 试导入结果：按导入选项读取并清理数据，不写入数据库
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub sheets: Vec<SheetDryRun>,
+    /// include_sheets中在文件里找不到的工作表名称
+    pub missing_sheets: Vec<String>,
+    /// 以下统计只包含会被导入的工作表
+    pub total_rows: usize,
+    pub rejected_count: usize,
+    pub suspicious_count: usize,
+}
+
+/// 单个工作表的试导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SheetDryRun {
+    #[serde(flatten)]
+    pub sheet: SheetPreview,
+    pub columns: Vec<ColumnProfile>,
+    /// 清理后的前若干行数据
+    pub sample_rows: Vec<DryRunRow>,
+    pub rejected_rows: Vec<RejectedRow>,
+    pub rejected_count: usize,
+    pub suspicious_escapes: Vec<SuspiciousEscape>,
+    pub suspicious_count: usize,
+}
+
+/// 列的类型统计
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    /// 推断的列类型：单一类型时为该类型，日期与日期时间混合时为datetime，其他混合为mixed，没有数据时为empty
+    pub inferred_type: &'static str,
+    /// 非空单元格数量
+    pub filled: usize,
+    /// 各类型的单元格数量，文本单元格按内容识别数字和ISO日期
+    pub type_counts: BTreeMap<&'static str, usize>,
+}
+
+/// 试导入的数据行，row_number与导入后excel_data中的行号一致
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunRow {
+    pub row_number: usize,
+    pub data: HashMap<String, Value>,
+}
+
+/// 导入时会被拒绝的数据行：未通过工作区的校验规则或超过数据库存储限制
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedRow {
+    pub row_number: usize,
+    /// 各项原因的说明，以分号连接
+    pub reason: String,
+    pub reasons: Vec<RowViolation>,
+    pub data: HashMap<String, Value>,
+}
+
+/// 包含可疑Unicode转义序列的字段
+#[derive(Debug, Clone, Serialize)]
+pub struct SuspiciousEscape {
+    pub row_number: usize,
+    pub field: String,
+    pub value: String,
+}
+
+/// 由各类型的单元格数量推断列类型
+fn infer_column_type(type_counts: &BTreeMap<&'static str, usize>) -> &'static str {
+    let mut kinds = type_counts.keys().copied();
+    match (kinds.next(), kinds.next(), kinds.next()) {
+        (None, _, _) => "empty",
+        (Some(kind), None, _) => kind,
+        (Some("date"), Some("datetime"), None) => "datetime",
+        _ => "mixed",
+    }
+}
+
+/// 原生日期时间值的格式（ISO-8601，精确到秒）
+const ISO_DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// 日期时间单元格显示文本的格式
+const DISPLAY_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 读取到的一行数据
+#[derive(Debug, Clone, Default)]
+pub struct SheetRow {
+    /// 字段名 → 显示文本，写入data_json，用于搜索和展示
+    pub data: HashMap<String, Value>,
+    /// 字段名 → 原生类型值，写入typed_json，只包含数字、布尔、日期时间、时长和错误单元格
+    pub typed: HashMap<String, Value>,
+    /// 本行中由合并单元格填充的单元格
+    pub merged_fills: Vec<MergedFill>,
+}
+
+/// 清理、映射并校验后待写入的数据行
+struct PreparedRow {
+    /// 数据在工作表中的行号，部分行被拒绝时其余行的行号保持不变
+    row_number: usize,
+    row: SheetRow,
+    search_text: String,
+    violations: Vec<RowViolation>,
+}
+
+/// 读取并逐行处理后待导入的文件内容
+struct PreparedFile {
+    /// 应用列映射规则后的字段顺序
+    field_order: Vec<String>,
+    import_metadata: Value,
+    sheets: Vec<(String, Vec<PreparedRow>)>,
+    validator: Option<RowValidator>,
+}
+
+impl PreparedRow {
+    fn into_rejection(self, sheet_name: &str) -> RowRejection {
+        RowRejection {
+            sheet_name: sheet_name.to_string(),
+            row_number: self.row_number,
+            reasons: self.violations,
+            data: self.row.data,
+        }
+    }
+}
+
+/// 由合并单元格的值填充的单元格
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MergedFill {
+    pub field: String,
+    /// 被填充的单元格，如 A7
+    pub cell: String,
+    /// 值的来源，即合并区域左上角的单元格，如 A5
+    pub source: String,
+}
+
+/// 导入信息中最多记录的合并单元格填充区域数量
+const MAX_MERGED_FILL_RANGES: usize = 1000;
+
+/// 默认每插入多少行上报一次进度
+pub const DEFAULT_PROGRESS_INTERVAL: usize = 1000;
+
+/// search_vector使用的全文检索配置（与database_sea中生成列的定义保持一致）
+const SEARCH_TEXT_CONFIG: &str = "simple";
+
+/// 模糊搜索默认的词相似度阈值
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.5;
+
+/// 搜索模式，序列化为 {"mode": "fulltext"} 或 {"mode": "fuzzy", "threshold": 0.5}
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum SearchMode {
+    /// 全文检索（按词语前缀匹配）
+    FullText,
+    /// 模糊检索（子串及三元组相似度匹配），threshold为0到1之间的相似度阈值
+    Fuzzy { threshold: f64 },
+}
+
+/// 编译后的搜索条件，SQL中以d作为excel_data的别名
+struct SearchClause {
+    condition: String,
+    /// 排序得分表达式
+    rank: String,
+    values: Vec<sea_orm::Value>,
+    /// 模糊搜索的词相似度阈值
+    similarity_threshold: Option<f64>,
+}
+
+/// 搜索结果导出计划：匹配的数据按源文件和工作表分组，导出时逐组分页读取
+pub struct ExportPlan {
+    clause: SearchClause,
+    workspace_id: Option<i32>,
+    only_public_workspaces: bool,
+    pub sheets: Vec<ExportSheet>,
+}
+
+impl ExportPlan {
+    /// 导出开始前统计的匹配行数
+    pub fn total_rows(&self) -> u64 {
+        self.sheets.iter().map(|sheet| sheet.row_count).sum()
+    }
+}
+
+/// 导出时每次从数据库读取的行数
+const EXPORT_PAGE_SIZE: u64 = 1000;
+
+/// 计算文件哈希时每次读取的字节数
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 每条批量INSERT语句包含的行数（每行8个参数，需低于PostgreSQL的65535个参数上限）
+const INSERT_BATCH_SIZE: usize = 1000;
+
+/// upsert模式下每次查询已有数据行使用的键数
+const KEY_LOOKUP_BATCH_SIZE: usize = 1000;
+
+/// 单个文件的导入结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportSummary {
+    /// 数据所属的文件记录，未导入时为None
+    pub file_id: Option<i32>,
+    pub inserted_rows: usize,
+    /// upsert模式下就地更新的已有数据行
+    pub updated_rows: usize,
+    /// upsert模式下取值没有变化的行；replace模式下文件与导入设置都没有变化时为上一版本的全部行
+    pub unchanged_rows: usize,
+    /// replace模式下删除的上一版本数据行
+    pub replaced_rows: usize,
+    /// 未通过校验或无法写入、记录到import_rejections的行
+    pub rejected_rows: usize,
+}
+
+/// 严格模式下有不合格的行，整个文件未导入：没有创建或改动任何文件记录，拒绝的行由调用方按导入任务保存
+#[derive(Debug)]
+pub struct StrictModeRejection {
+    pub file_path: String,
+    pub rejections: Vec<RowRejection>,
+}
+
+impl std::fmt::Display for StrictModeRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "严格模式下有 {} 行未通过校验，文件未导入: {}", self.rejections.len(), self.file_path)
+    }
+}
+
+impl std::error::Error for StrictModeRejection {}
+
+/// replace模式下同名文件的上一版本
+enum PreviousVersion {
+    /// 工作区中没有同名文件
+    None,
+    /// 文件与导入设置都没有变化，无需重新导入
+    Unchanged { file_id: i32, rows: usize },
+    /// 沿用上一版本的文件记录，其数据将被替换
+    Adopted { file_id: i32, rows: usize, old_path: String },
+}
+
+/// 导入进度事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ImportEvent {
+    /// 开始导入工作表
+    SheetStarted { sheet: String, total_rows: usize },
+    /// 已插入若干行（file_rows为整个文件累计插入行数）
+    RowsInserted { sheet: String, sheet_rows: usize, file_rows: usize },
+    /// 工作表导入完成
+    SheetFinished { sheet: String, rows: usize },
+    /// 文件导入完成（total_rows为导入后文件对应的数据行数：插入、更新与未变化的行之和）
+    FileDone {
+        total_rows: usize,
+        #[serde(flatten)]
+        summary: ImportSummary,
+    },
+    /// 导入失败
+    Failed { error: String },
+}
+
+/// 导入进度的上报端
+#[derive(Clone)]
+pub struct ImportProgress {
+    sender: tokio::sync::mpsc::UnboundedSender<ImportEvent>,
+    /// 每插入多少行上报一次RowsInserted
+    interval: usize,
+}
+
+impl ImportProgress {
+    pub fn new(sender: tokio::sync::mpsc::UnboundedSender<ImportEvent>, interval: usize) -> Self {
+        Self {
+            sender,
+            interval: interval.max(1),
+        }
+    }
+}
+
+fn report_progress(progress: Option<&ImportProgress>, event: ImportEvent) {
+    if let Some(progress) = progress {
+        // 接收端已关闭时忽略，不影响导入本身
+        let _ = progress.sender.send(event);
+    }
+}
+
+/// Excel日期时间单元格的粒度
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateTimeKind {
+    Date,
+    Time,
+    DateTime,
+}
+
+impl DateTimeKind {
+    /// 在typed_json中使用的类型名
+    fn type_name(self) -> &'static str {
+        match self {
+            DateTimeKind::Date => "date",
+            DateTimeKind::Time => "time",
+            DateTimeKind::DateTime => "datetime",
+        }
+    }
+}
+
+/// 将Excel日期时间单元格转换为真实的日期时间
+///
+/// 单元格是否为日期、时长由calamine按工作簿的数字格式判断（时长由调用方按is_duration单独处理），
+/// 1900/1904日期系统由as_datetime换算。calamine只提供格式的类型而不提供格式字符串，因此粒度按序列号判断：
+/// 小于1（含0，即00:00）为时间；带时刻的为日期时间；整数序列号为日期，但同一列中有带时刻的值时
+/// （`column_has_time`）视为零点的日期时间。
+fn excel_datetime(dt: &ExcelDateTime, column_has_time: bool) -> Option<(DateTimeKind, chrono::NaiveDateTime)> {
+    let serial = dt.as_f64();
+    let datetime = dt.as_datetime()?;
+    let kind = if (0.0..1.0).contains(&serial) {
+        DateTimeKind::Time
+    } else if serial.fract() != 0.0 || column_has_time {
+        DateTimeKind::DateTime
+    } else {
+        DateTimeKind::Date
+    };
+    Some((kind, datetime))
+}
+
+/// 单元格是否为带时刻的日期时间（序列号不小于1且有小数部分），用于判断整列的日期粒度
+fn has_time_of_day(cell: &Data) -> bool {
+    match cell {
+        Data::DateTime(dt) if !dt.is_duration() => dt.as_f64() >= 1.0 && dt.as_f64().fract() != 0.0,
+        _ => false,
+    }
+}
+
+/// 将从0开始的行列坐标转换为A1形式的单元格引用
+fn cell_reference(row: u32, col: u32) -> String {
+    let mut letters = Vec::new();
+    let mut col = col + 1;
+    while col > 0 {
+        let remainder = (col - 1) % 26;
+        letters.push((b'A' + remainder as u8) as char);
+        col = (col - 1) / 26;
+    }
+    letters.iter().rev().collect::<String>() + &(row + 1).to_string()
+}
+
+/// 将时长格式化为 [h]:mm:ss，如 36:30:00
+fn format_clock_duration(duration: chrono::Duration) -> String {
+    let total_seconds = (duration.num_milliseconds() as f64 / 1000.0).round() as i64;
+    let sign = if total_seconds < 0 { "-" } else { "" };
+    let seconds = total_seconds.unsigned_abs();
+    format!("{}{}:{:02}:{:02}", sign, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+/// 将时长格式化为ISO-8601时长，如 PT36H30M0S
+fn format_iso_duration(duration: chrono::Duration) -> String {
+    let total_seconds = (duration.num_milliseconds() as f64 / 1000.0).round() as i64;
+    let sign = if total_seconds < 0 { "-" } else { "" };
+    let seconds = total_seconds.unsigned_abs();
+    format!("{}PT{}H{}M{}S", sign, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+/// 生成包含匹配的ILIKE模式，转义通配符
+fn like_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// 编译范围筛选条件：按字段名（不区分大小写）取出单元格的值，安全转换为数值或时间后判断是否在范围内，
+/// 无法转换的行视为不匹配。上下限合成一个numrange/tsrange，每个单元格只转换一次
+fn range_filter_condition(filter: &RangeFilter, values: &mut Vec<sea_orm::Value>) -> String {
+    values.push(filter.field.clone().into());
+    let key = values.len();
+
+    let is_number = matches!(
+        filter.lower.as_ref().or(filter.upper.as_ref()).map(|bound| &bound.value),
+        Some(RangeValue::Number(_))
+    );
+    let mut bound_param = |bound: &Option<RangeBound>| match bound {
+        Some(RangeBound { value: RangeValue::Number(number), .. }) => {
+            values.push(number.clone().into());
+            format!("${}::numeric", values.len())
+        }
+        Some(RangeBound { value: RangeValue::DateTime(datetime), .. }) => {
+            values.push(datetime.format("%Y-%m-%d %H:%M:%S").to_string().into());
+            format!("${}::timestamp", values.len())
+        }
+        None => "NULL".to_string(),
+    };
+    let lower = bound_param(&filter.lower);
+    let upper = bound_param(&filter.upper);
+    let bounds = format!(
+        "{}{}",
+        if filter.lower.as_ref().is_some_and(|bound| bound.inclusive) { "[" } else { "(" },
+        if filter.upper.as_ref().is_some_and(|bound| bound.inclusive) { "]" } else { ")" },
+    );
+    let (cast, range) = if is_number {
+        (typed_or_text_cast(RangeCast::Number), "numrange")
+    } else {
+        (typed_or_text_cast(RangeCast::Timestamp), "tsrange")
+    };
+
+    format!(
+        "EXISTS (SELECT 1 FROM jsonb_each_text(d.data_json) AS f(key, value) WHERE lower(f.key) = lower(${key}) AND {cast} <@ {range}({lower}, {upper}, '{bounds}'))"
+    )
+}
+
+/// 范围筛选的目标类型
+#[derive(Clone, Copy)]
+enum RangeCast {
+    Number,
+    Timestamp,
+}
+
+/// 生成安全转换表达式：原生值类型匹配时转换原生值，否则转换显示文本
+///
+/// 带异常处理的plpgsql转换函数开销较大，显示文本先用内联的正则排除明显不是数字/日期的值，
+/// 只对候选值调用转换函数；原生数值由导入时写入，格式确定，直接转换。
+fn typed_or_text_cast(target: RangeCast) -> String {
+    let (typed_kinds, typed_cast, text_candidate, cast_function) = match target {
+        RangeCast::Number => (
+            "'number'",
+            "(d.typed_json -> f.key ->> 'value')::numeric",
+            r"f.value ~ '^\s*[-+]?\.?\d'",
+            "excel_try_numeric",
+        ),
+        RangeCast::Timestamp => (
+            "'date', 'datetime'",
+            "excel_try_timestamp(d.typed_json -> f.key ->> 'value')",
+            r"f.value ~ '^\s*\d'",
+            "excel_try_timestamp",
+        ),
+    };
+    format!(
+        "(CASE WHEN d.typed_json -> f.key ->> 'type' IN ({typed_kinds}) THEN {typed_cast} \
+         WHEN {text_candidate} THEN {cast_function}(f.value) END)"
+    )
+}
+
+/// 将查询表达式编译为SQL条件，参数按出现顺序追加到values
+struct QueryCompiler {
+    mode: SearchMode,
+    values: Vec<sea_orm::Value>,
+    /// 非排除条件的相关度得分表达式
+    ranks: Vec<String>,
+}
+
+impl QueryCompiler {
+    fn push_value(&mut self, value: impl Into<sea_orm::Value>) -> usize {
+        self.values.push(value.into());
+        self.values.len()
+    }
+
+    fn compile(&mut self, expr: &QueryExpr, negated: bool) -> String {
+        match expr {
+            QueryExpr::Term(term) => match &term.field {
+                Some(field) => self.compile_field_term(field, term),
+                None => self.compile_text_term(term, negated),
+            },
+            QueryExpr::Not(inner) => format!("NOT ({})", self.compile(inner, !negated)),
+            QueryExpr::And(items) => self.compile_group(items, " AND ", negated),
+            QueryExpr::Or(items) => self.compile_group(items, " OR ", negated),
+        }
+    }
+
+    fn compile_group(&mut self, items: &[QueryExpr], operator: &str, negated: bool) -> String {
+        let parts: Vec<String> = items.iter().map(|item| self.compile(item, negated)).collect();
+        format!("({})", parts.join(operator))
+    }
+
+    /// 整行条件匹配search_text（查询词按导入时相同的规则规范化）
+    ///
+    /// 全文检索模式下词素按前缀匹配（短语按相邻顺序匹配），词素由数据库按与search_vector相同的配置切分；
+    /// 中日韩文字之间没有分隔，一段连续文字只切出一个词素，因此含中日韩文字的查询词同时按子串匹配。
+    /// 不含字母和数字、切分不出词素的查询词只按子串匹配。
+    fn compile_text_term(&mut self, term: &SearchTerm, negated: bool) -> String {
+        let value = normalize_for_search(&term.value);
+        let (condition, rank) = match self.mode {
+            SearchMode::FullText if !value.chars().any(char::is_alphanumeric) => {
+                let n = self.push_value(like_pattern(&value));
+                (format!("d.search_text ILIKE ${}", n), "1::real".to_string())
+            }
+            SearchMode::FullText => {
+                let substring = contains_cjk(&value).then(|| like_pattern(&value));
+                let n = self.push_value(value);
+                // 标量子查询只计算一次，过滤与排序共用
+                let query = if term.phrase {
+                    format!("(SELECT phraseto_tsquery('{}', ${}))", SEARCH_TEXT_CONFIG, n)
+                } else {
+                    format!("(SELECT excel_prefix_tsquery(${}))", n)
+                };
+                let mut condition = format!("d.search_vector @@ {}", query);
+                if let Some(pattern) = substring {
+                    let p = self.push_value(pattern);
+                    condition = format!("({} OR d.search_text ILIKE ${})", condition, p);
+                }
+                // 只有子串命中的行得分为0，排在词素命中的行之后
+                (condition, format!("ts_rank_cd(d.search_vector, {})", query))
+            }
+            SearchMode::Fuzzy { .. } => {
+                let pattern = self.push_value(like_pattern(&value));
+                let word = self.push_value(value);
+                (
+                    format!("(d.search_text ILIKE ${pattern} OR ${word} <% d.search_text)"),
+                    format!("CASE WHEN d.search_text ILIKE ${pattern} THEN 1.0 ELSE word_similarity(${word}, d.search_text) END"),
+                )
+            }
+        };
+        if !negated {
+            self.ranks.push(rank);
+        }
+        condition
+    }
+
+    /// 字段条件匹配data_json中的同名键（不区分大小写），值按包含关系匹配
+    ///
+    /// 字段值命中时整行的search_text必然也命中，因此先用search_text上的三元组索引
+    /// 缩小候选行，只对候选行展开data_json；该前置条件被EXISTS蕴含，取反时结果不变。
+    fn compile_field_term(&mut self, field: &str, term: &SearchTerm) -> String {
+        // 单元格取值经excel_normalize_search规范化后与规范化的搜索词比较，规则与search_text相同
+        let normalized = normalize_for_search(&term.value);
+        let key = self.push_value(field.to_string());
+        let pattern = self.push_value(like_pattern(&normalized));
+        let (row_condition, value_condition) = match self.mode {
+            SearchMode::Fuzzy { .. } => {
+                let word = self.push_value(normalized);
+                (
+                    format!("(d.search_text ILIKE ${pattern} OR ${word} <% d.search_text)"),
+                    format!("(excel_normalize_search(f.value) ILIKE ${pattern} OR ${word} <% excel_normalize_search(f.value))"),
+                )
+            }
+            SearchMode::FullText => (
+                format!("d.search_text ILIKE ${pattern}"),
+                format!("excel_normalize_search(f.value) ILIKE ${pattern}"),
+            ),
+        };
+        format!(
+            "({row_condition} AND EXISTS (SELECT 1 FROM jsonb_each_text(d.data_json) AS f(key, value) WHERE lower(f.key) = lower(${key}) AND {value_condition}))"
+        )
+    }
+}
 
 pub struct ExcelProcessor {
     db: sea_orm::DatabaseConnection,
@@ -19,7 +778,8 @@ impl ExcelProcessor {
         Self { db }
     }
 
-    fn cell_to_text(cell: &Data) -> String {
+    /// 单元格的显示文本，`column_has_time` 见excel_datetime
+    fn cell_to_text(cell: &Data, column_has_time: bool) -> String {
         match cell {
             Data::Empty => String::new(),
             Data::String(s) => s.clone(),
@@ -37,13 +797,53 @@ impl ExcelProcessor {
             }
             Data::Int(i) => i.to_string(),
             Data::Bool(b) => b.to_string(),
-            Data::DateTime(dt) => dt.to_string(),
+            Data::DateTime(dt) if dt.is_duration() => match dt.as_duration() {
+                Some(duration) => format_clock_duration(duration),
+                None => dt.to_string(),
+            },
+            Data::DateTime(dt) => match excel_datetime(dt, column_has_time) {
+                Some((DateTimeKind::Date, datetime)) => datetime.format("%Y-%m-%d").to_string(),
+                Some((DateTimeKind::Time, datetime)) => datetime.format("%H:%M:%S").to_string(),
+                Some((DateTimeKind::DateTime, datetime)) => datetime.format(DISPLAY_DATETIME_FORMAT).to_string(),
+                // 超出可表示范围的序列号保留原值
+                None => dt.to_string(),
+            },
             Data::DateTimeIso(s) => s.clone(),
             Data::DurationIso(s) => s.clone(),
             Data::Error(e) => format!("{:?}", e),
         }
     }
 
+    /// 非文本单元格的原生类型值，文本和空单元格返回None（其内容与显示文本相同）
+    ///
+    /// Excel日期时间及时长单元格额外保存原始序列号（serial），换算规则变化时不丢失信息。
+    fn cell_typed_value(cell: &Data, column_has_time: bool) -> Option<Value> {
+        let (kind, value) = match cell {
+            Data::Empty | Data::String(_) => return None,
+            Data::Float(f) => ("number", Value::Number(serde_json::Number::from_f64(*f)?)),
+            Data::Int(i) => ("number", Value::from(*i)),
+            Data::Bool(b) => ("bool", Value::Bool(*b)),
+            Data::DateTime(dt) => {
+                let (kind, value) = if dt.is_duration() {
+                    ("duration", format_iso_duration(dt.as_duration()?))
+                } else {
+                    let (kind, datetime) = excel_datetime(dt, column_has_time)?;
+                    let format = match kind {
+                        DateTimeKind::Date => "%Y-%m-%d",
+                        DateTimeKind::Time => "%H:%M:%S",
+                        DateTimeKind::DateTime => ISO_DATETIME_FORMAT,
+                    };
+                    (kind.type_name(), datetime.format(format).to_string())
+                };
+                return Some(serde_json::json!({ "type": kind, "value": value, "serial": dt.as_f64() }));
+            }
+            Data::DateTimeIso(s) => ("datetime", Value::String(s.clone())),
+            Data::DurationIso(s) => ("duration", Value::String(s.clone())),
+            Data::Error(e) => ("error", Value::String(e.to_string())),
+        };
+        Some(serde_json::json!({ "type": kind, "value": value }))
+    }
+
     /// 检测行数据中可疑的Unicode转义序列
     fn find_suspicious_escapes(row: &HashMap<String, Value>) -> Vec<(String, String)> {
         let mut suspicious_fields = Vec::new();
@@ -57,34 +857,147 @@ impl ExcelProcessor {
         suspicious_fields
     }
 
-    /// 清理行数据中的问题字符（包括字段名和字段值）
-    fn clean_row_data(row: &mut HashMap<String, Value>) {
+    /// 单元格的值类型，用于试导入时推断列类型；空单元格返回None
+    fn value_kind(value: &Value, typed: Option<&Value>) -> Option<&'static str> {
+        let text = match value {
+            Value::String(s) if !s.trim().is_empty() => s.trim(),
+            _ => return None,
+        };
+        if let Some(kind) = typed.and_then(|typed| typed.get("type")).and_then(Value::as_str) {
+            return Some(match kind {
+                "number" => "number",
+                "bool" => "bool",
+                "date" => "date",
+                "time" => "time",
+                "datetime" => "datetime",
+                "duration" => "duration",
+                "error" => "error",
+                _ => "text",
+            });
+        }
+
+        // 没有原生类型的文本（如CSV）按内容识别，与范围筛选的安全转换一致
+        if text.parse::<f64>().is_ok_and(f64::is_finite) {
+            Some("number")
+        } else if chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok() {
+            Some("date")
+        } else if chrono::NaiveDateTime::parse_from_str(text, ISO_DATETIME_FORMAT).is_ok()
+            || chrono::NaiveDateTime::parse_from_str(text, DISPLAY_DATETIME_FORMAT).is_ok()
+        {
+            Some("datetime")
+        } else {
+            Some("text")
+        }
+    }
+
+    /// 构建行的搜索文本（规范化后写入，搜索时对查询词做同样的处理）
+    fn build_search_text(data: &HashMap<String, Value>) -> String {
+        let search_parts: Vec<String> = data
+            .values()
+            .map(|v| {
+                match v {
+                    Value::String(s) => s.clone(),
+                    Value::Bool(b) => b.to_string(),
+                    _ => String::new(),
+                }
+            })
+            .collect();
+        normalize_for_search(&search_parts.join(" "))
+    }
+
+    /// 数据行无法写入数据库的原因
+    ///
+    /// search_vector由search_text生成，不重复的词条总长度超过tsvector上限时整行写入失败；
+    /// 这里按simple配置近似切分词条（按非字母数字字符分隔）。
+    fn row_rejection_reason(search_text: &str) -> Option<String> {
+        if search_text.len() <= TSVECTOR_MAX_BYTES {
+            return None;
+        }
+        let mut lexemes = HashSet::new();
+        let lexeme_bytes: usize = search_text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty() && lexemes.insert(*word))
+            .map(str::len)
+            .sum();
+        (lexeme_bytes > TSVECTOR_MAX_BYTES).then(|| {
+            format!(
+                "全文检索词条总长度 {} 字节，超过PostgreSQL tsvector上限 {} 字节",
+                lexeme_bytes, TSVECTOR_MAX_BYTES
+            )
+        })
+    }
+
+    /// 清理数据行并应用工作区的列映射规则，再检查校验规则和数据库存储限制
+    fn prepare_row(
+        sheet_name: &str,
+        row_number: usize,
+        mut row: SheetRow,
+        mapping: Option<&ColumnMapping>,
+        validator: Option<&mut RowValidator>,
+    ) -> PreparedRow {
+        Self::clean_row_data(&mut row);
+        if let Some(mapping) = mapping {
+            mapping.apply(&mut row.data, &mut row.typed);
+        }
+
+        let mut violations = match validator {
+            Some(validator) => {
+                validator.check_row(&row.data, &row.typed, &format!("工作表 {} 第 {} 行", sheet_name, row_number))
+            }
+            None => Vec::new(),
+        };
+        let search_text = Self::build_search_text(&row.data);
+        if let Some(reason) = Self::row_rejection_reason(&search_text) {
+            violations.push(RowViolation::new("", "storage", reason));
+        }
+
+        PreparedRow {
+            row_number,
+            row,
+            search_text,
+            violations,
+        }
+    }
+
+    /// 检查upsert模式的键列，键为空或在文件内重复的行追加违规记录；已经被拒绝的行不会导入，不参与检查
+    fn check_upsert_keys<'a>(
+        key_columns: &[String],
+        sheets: impl Iterator<Item = (&'a str, &'a mut Vec<PreparedRow>)>,
+    ) {
+        if key_columns.is_empty() {
+            return;
+        }
+        let rows = sheets.flat_map(|(sheet_name, rows)| {
+            rows.iter_mut()
+                .filter(|prepared| prepared.violations.is_empty())
+                .map(move |prepared| (sheet_name, prepared.row_number, &prepared.row.data, &mut prepared.violations))
+        });
+        upsert_key::check_keys(key_columns, rows);
+    }
+
+    /// 清理字段名 - 只移除真正有问题的控制字符，保留Unicode字符
+    fn clean_field_name(key: &str) -> String {
+        key.replace(INVISIBLE_CHARS, "") // 移除空字符、BOM及零宽字符
+            .chars()
+            .filter(|c| !c.is_control() || c.is_whitespace()) // 只过滤控制字符，保留空白字符和所有可见字符
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    /// 清理行数据中的问题字符（包括字段名和字段值），原生类型值的字段名按相同规则清理
+    fn clean_row_data(row: &mut SheetRow) {
         // 首先清理字段名，需要重新构建HashMap
         let mut cleaned_row = HashMap::new();
         
-        for (key, value) in row.drain() {
-            // 清理字段名 - 只移除真正有问题的控制字符，保留Unicode字符
-            let cleaned_key = key
-                .replace('\u{0000}', "") // 移除空字符
-                .replace('\u{FEFF}', "") // 移除BOM字符
-                .replace('\u{200B}', "") // 移除零宽空格
-                .replace('\u{200C}', "") // 移除零宽非连接符
-                .replace('\u{200D}', "") // 移除零宽连接符
-                .chars()
-                .filter(|c| !c.is_control() || c.is_whitespace()) // 只过滤控制字符，保留空白字符和所有可见字符
-                .collect::<String>()
-                .trim()
-                .to_string();
+        for (key, value) in row.data.drain() {
+            let cleaned_key = Self::clean_field_name(&key);
             
             // 清理字段值
             let cleaned_value = match value {
                 Value::String(s) => {
                     let cleaned_str = s
-                        .replace('\u{0000}', "") // 移除空字符
-                        .replace('\u{FEFF}', "") // 移除BOM字符
-                        .replace('\u{200B}', "") // 移除零宽空格
-                        .replace('\u{200C}', "") // 移除零宽非连接符
-                        .replace('\u{200D}', "") // 移除零宽连接符
+                        .replace(INVISIBLE_CHARS, "") // 移除空字符、BOM及零宽字符
                         .chars()
                         .filter(|c| !c.is_control() || c.is_whitespace()) // 只过滤控制字符，保留空白字符和所有可见字符
                         .collect::<String>()
@@ -102,23 +1015,40 @@ impl ExcelProcessor {
         }
         
         // 将清理后的数据放回原HashMap
-        *row = cleaned_row;
+        row.data = cleaned_row;
+        row.typed = row
+            .typed
+            .drain()
+            .map(|(key, value)| (Self::clean_field_name(&key), value))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
     }
 
-    /// 生成文件哈希值
+    /// 生成文件哈希值（分块读取，避免将整个文件载入内存）
     async fn generate_file_hash(&self, file_path: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let content = fs::read(file_path)?;
-        let digest = md5::compute(content);
-        Ok(format!("{:x}", digest))
+        let mut file = fs::File::open(file_path)?;
+        let mut context = md5::Context::new();
+        let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            context.consume(&buffer[..read]);
+        }
+        Ok(format!("{:x}", context.compute()))
     }
 
     /// 获取或创建文件元数据
-    async fn get_or_create_file_metadata(
+    #[allow(clippy::too_many_arguments)]
+    async fn get_or_create_file_metadata<C: ConnectionTrait>(
         &self,
+        db: &C,
         file_path: &str,
         workspace_id: Option<i32>,
         uploaded_by: Option<i32>,
         original_file_name: Option<&str>,
+        known_hash: Option<&str>,
     ) -> Result<i32, sea_orm::DbErr> {
         // 获取文件信息
         let metadata = match fs::metadata(file_path) {
@@ -127,36 +1057,22 @@ impl ExcelProcessor {
         };
 
         let file_size = metadata.len() as i64;
-        let file_hash = match self.generate_file_hash(file_path).await {
-            Ok(hash) => hash,
-            Err(e) => return Err(sea_orm::DbErr::Custom(format!("文件哈希生成失败: {}", e))),
+        let file_hash = match known_hash {
+            Some(hash) => hash.to_string(),
+            None => match self.generate_file_hash(file_path).await {
+                Ok(hash) => hash,
+                Err(e) => return Err(sea_orm::DbErr::Custom(format!("文件哈希生成失败: {}", e))),
+            },
         };
 
-        let file_name = original_file_name
-            .map(str::trim)
-            .filter(|name| !name.is_empty())
-            .map(ToOwned::to_owned)
-            .unwrap_or_else(|| {
-                Path::new(file_path)
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string()
-            });
+        let file_name = Self::display_file_name(file_path, original_file_name);
 
         let now = chrono::Utc::now();
 
         // 尝试获取现有的文件元数据
-        let mut file_filter = Condition::all().add(files::Column::FilePath.eq(file_path));
-        file_filter = if let Some(wid) = workspace_id {
-            file_filter.add(files::Column::WorkspaceId.eq(wid))
-        } else {
-            file_filter.add(files::Column::WorkspaceId.is_null())
-        };
-
-        let existing_file: Option<files::Model> = files::Entity::find()
-            .filter(file_filter)
-            .one(&self.db)
+        let existing_file = files::Entity::find()
+            .filter(Self::file_record_condition(file_path, workspace_id))
+            .one(db)
             .await?;
 
         match existing_file {
@@ -172,10 +1088,11 @@ impl ExcelProcessor {
                     file_size: Set(file_size),
                     file_hash: Set(file_hash),
                     field_order: Set(file_model.field_order),
+                    import_metadata: Set(file_model.import_metadata),
                     created_at: Set(file_model.created_at),
                     updated_at: Set(now),
                 };
-                updated_file.update(&self.db).await?;
+                updated_file.update(db).await?;
                 
                 Ok(file_model.id)
             }
@@ -190,10 +1107,11 @@ impl ExcelProcessor {
                     file_size: Set(file_size),
                     file_hash: Set(file_hash),
                     field_order: Set(None),
+                    import_metadata: Set(None),
                     created_at: Set(now),
                     updated_at: Set(now),
                 };
-                let inserted = new_file.insert(&self.db).await?;
+                let inserted = new_file.insert(db).await?;
                 Ok(inserted.id)
             }
         }
@@ -209,17 +1127,7 @@ impl ExcelProcessor {
         let current_hash = self.generate_file_hash(file_path).await?;
         
         // 查询数据库中的文件记录
-        let mut file_filter = Condition::all().add(files::Column::FilePath.eq(file_path));
-        file_filter = if let Some(wid) = workspace_id {
-            file_filter.add(files::Column::WorkspaceId.eq(wid))
-        } else {
-            file_filter.add(files::Column::WorkspaceId.is_null())
-        };
-
-        let existing_file: Option<files::Model> = files::Entity::find()
-            .filter(file_filter)
-            .one(&self.db)
-            .await?;
+        let existing_file = self.find_file_record(file_path, workspace_id).await?;
 
         match existing_file {
             Some(file_model) => {
@@ -233,83 +1141,190 @@ impl ExcelProcessor {
         }
     }
 
+    /// 按文件路径与所属工作区查找文件记录
+    async fn find_file_record(&self, file_path: &str, workspace_id: Option<i32>) -> Result<Option<files::Model>, sea_orm::DbErr> {
+        files::Entity::find()
+            .filter(Self::file_record_condition(file_path, workspace_id))
+            .one(&self.db)
+            .await
+    }
+
+    fn file_record_condition(file_path: &str, workspace_id: Option<i32>) -> Condition {
+        let file_filter = Condition::all().add(files::Column::FilePath.eq(file_path));
+        if let Some(wid) = workspace_id {
+            file_filter.add(files::Column::WorkspaceId.eq(wid))
+        } else {
+            file_filter.add(files::Column::WorkspaceId.is_null())
+        }
+    }
+
     /// 删除指定文件的数据
-    async fn delete_file_data(&self, file_id: i32) -> Result<(), sea_orm::DbErr> {
+    async fn delete_file_data<C: ConnectionTrait>(db: &C, file_id: i32) -> Result<(), sea_orm::DbErr> {
         // 删除关联的Excel数据
         excel_data::Entity::delete_many()
             .filter(excel_data::Column::FileId.eq(file_id))
-            .exec(&self.db)
+            .exec(db)
             .await?;
+        // 上一次导入的拒绝记录
+        row_validation::delete_rejections(db, file_id).await?;
         Ok(())
     }
 
-    /// 读取Excel文件内容
-    async fn read_excel_file(
-        &self,
+    /// 加载文件中可读取的工作表区域（Excel工作簿或CSV/TSV文本）及合并单元格信息
+    ///
+    /// `should_load` 按工作表名称和可见性决定是否读取该工作表，未读取的工作表只记录名称。
+    fn load_sheet_ranges(
         file_path: &str,
-    ) -> Result<(Vec<(String, Vec<HashMap<String, Value>>)>, Vec<String>), Box<dyn std::error::Error>> {
-        let mut workbook = open_workbook_auto(file_path)?;
-        let sheet_names = workbook.sheet_names().to_owned();
-
-        if sheet_names.is_empty() {
-            return Ok((vec![], vec![]));
+        should_load: impl Fn(&str, SheetVisibility) -> bool,
+    ) -> Result<LoadedWorkbook, Box<dyn std::error::Error>> {
+        // CSV/TSV文件作为单个工作表处理，不存在合并单元格，也不参与工作表选择
+        if csv_reader::is_delimited_text_file(file_path) {
+            let range = csv_reader::read_delimited_range(file_path)?;
+            return Ok(LoadedWorkbook {
+                sheet_names: vec!["Sheet1".to_string()],
+                sheets: vec![LoadedSheet {
+                    name: "Sheet1".to_string(),
+                    visibility: SheetVisibility::Visible,
+                    range,
+                    merged_regions: Some(Vec::new()),
+                }],
+                unloaded: Vec::new(),
+            });
         }
 
-        let mut all_sheets_data = Vec::new();
-        let mut all_headers = Vec::new();
+        let mut workbook = open_workbook_auto(file_path)?;
+        let metadata: Vec<(String, SheetType, SheetVisibility)> = workbook
+            .sheets_metadata()
+            .iter()
+            .map(|sheet| (sheet.name.clone(), sheet.typ, sheet.visible.into()))
+            .collect();
 
-        // 遍历所有工作表
-        for sheet_name in &sheet_names {
-            let range = match workbook.worksheet_range(sheet_name) {
-                Ok(range) => range,
+        let mut sheets = Vec::new();
+        let mut unloaded = Vec::new();
+        for (sheet_name, sheet_type, visibility) in &metadata {
+            // 图表、对话框、宏表不包含单元格数据
+            if *sheet_type != SheetType::WorkSheet {
+                info!("跳过非数据工作表: {} ({:?})", sheet_name, sheet_type);
+                continue;
+            }
+            if !should_load(sheet_name, *visibility) {
+                unloaded.push((sheet_name.clone(), *visibility));
+                continue;
+            }
+            match workbook.worksheet_range(sheet_name) {
+                Ok(range) => {
+                    let merged_regions = Self::load_merged_regions(&mut workbook, sheet_name);
+                    sheets.push(LoadedSheet {
+                        name: sheet_name.clone(),
+                        visibility: *visibility,
+                        range,
+                        merged_regions,
+                    });
+                }
                 Err(_) => {
                     info!("跳过无法读取的工作表: {}", sheet_name);
-                    continue;
                 }
-            };
+            }
+        }
 
-            let mut rows_data = Vec::new();
+        Ok(LoadedWorkbook {
+            sheet_names: metadata.into_iter().map(|(name, _, _)| name).collect(),
+            sheets,
+            unloaded,
+        })
+    }
 
-            if range.rows().count() == 0 {
-                info!("工作表 {} 为空，跳过", sheet_name);
-                continue;
-            }
+    /// 读取工作表的合并单元格区域，目前只有xlsx和xls支持
+    fn load_merged_regions(
+        workbook: &mut Sheets<std::io::BufReader<fs::File>>,
+        sheet_name: &str,
+    ) -> Option<Vec<Dimensions>> {
+        match workbook {
+            Sheets::Xlsx(xlsx) => match xlsx.worksheet_merge_cells(sheet_name)? {
+                Ok(regions) => Some(regions),
+                Err(e) => {
+                    warn!("读取工作表 {} 的合并单元格失败: {}", sheet_name, e);
+                    None
+                }
+            },
+            Sheets::Xls(xls) => xls.worksheet_merge_cells(sheet_name),
+            Sheets::Xlsb(_) | Sheets::Ods(_) => None,
+        }
+    }
 
-            // 获取列标题
-            let headers: Vec<String> = range
-                .rows()
-                .next()
-                .unwrap()
-                .iter()
-                .map(|cell| {
-                    match Self::cell_to_text(cell) {
-                        s if s.is_empty() => "EMPTY".to_string(),
-                        s => s,
-                    }
-                })
-                .collect();
+    /// 数据区域内被合并覆盖的单元格到合并区域左上角的映射（均为相对于数据区域的坐标）
+    ///
+    /// 只处理左上角位于表头之下的合并区域，表头中的合并由表头解析处理。
+    fn merged_cell_sources(
+        sheet: &LoadedSheet,
+        origin: (u32, u32),
+        header_end: usize,
+    ) -> HashMap<(usize, usize), (usize, usize)> {
+        let mut sources = HashMap::new();
+        let Some(regions) = &sheet.merged_regions else {
+            info!("工作表 {} 的文件格式不支持读取合并单元格，跳过合并单元格填充", sheet.name);
+            return sources;
+        };
 
-            // 处理数据行
-            for (_row_idx, row) in range.rows().enumerate().skip(1) {
-                let mut row_data = HashMap::new();
-
-                for (col_idx, cell) in row.iter().enumerate() {
-                    if col_idx < headers.len() {
-                        let cell_str = Self::cell_to_text(cell);
-                        let value = if cell_str.trim().is_empty() {
-                            Value::Null
-                        } else {
-                            // 所有字段统一按文本存储，避免数字/时间被自动类型改写
-                            Value::String(cell_str)
-                        };
-                        row_data.insert(headers[col_idx].clone(), value);
+        let data_top = origin.0 + header_end as u32;
+        for region in regions {
+            let (top, left) = region.start;
+            let (bottom, right) = region.end;
+            if top < data_top || left < origin.1 {
+                continue;
+            }
+            let source = ((top - origin.0) as usize, (left - origin.1) as usize);
+            for row in top..=bottom {
+                for col in left..=right {
+                    if (row, col) != (top, left) {
+                        sources.insert(((row - origin.0) as usize, (col - origin.1) as usize), source);
                     }
                 }
+            }
+        }
+        sources
+    }
 
-                if !row_data.is_empty() {
-                    rows_data.push(row_data);
+    /// 读取Excel文件内容（使用默认导入选项：首行为表头）
+    #[allow(clippy::type_complexity)]
+    pub fn read_excel_file(
+        file_path: &str,
+    ) -> Result<(Vec<(String, Vec<SheetRow>)>, Vec<String>), Box<dyn std::error::Error>> {
+        Self::read_excel_file_with_options(file_path, &ImportOptions::default())
+    }
+
+    /// 按导入选项读取Excel文件内容；为同步操作，在异步环境中应通过spawn_blocking调用
+    #[allow(clippy::type_complexity)]
+    pub fn read_excel_file_with_options(
+        file_path: &str,
+        options: &ImportOptions,
+    ) -> Result<(Vec<(String, Vec<SheetRow>)>, Vec<String>), Box<dyn std::error::Error>> {
+        let workbook = Self::load_sheet_ranges(file_path, |sheet_name, visibility| {
+            match options.sheet_skip_reason(sheet_name, visibility) {
+                Some(reason) => {
+                    info!("按导入选项跳过工作表 {} ({:?})", sheet_name, reason);
+                    false
                 }
+                None => true,
             }
+        })?;
+
+        let sheet_names: Vec<&str> = workbook.sheet_names.iter().map(String::as_str).collect();
+        let missing = options.missing_included_sheets(&sheet_names);
+        if !missing.is_empty() {
+            warn!("文件中不存在指定导入的工作表: {}", missing.join(", "));
+        }
+        if workbook.sheets.is_empty() && !options.include_sheets.is_empty() {
+            return Err(format!("文件中没有符合选择条件的工作表: {}", options.include_sheets.join(", ")).into());
+        }
+
+        let mut all_sheets_data = Vec::new();
+        let mut all_headers = Vec::new();
+
+        // 遍历所有工作表
+        for sheet in &workbook.sheets {
+            let sheet_name = &sheet.name;
+            let (headers, rows_data) = Self::parse_sheet(sheet, options)?;
 
             if !rows_data.is_empty() {
                 info!("工作表 {} 读取完成，共 {} 行数据", sheet_name, rows_data.len());
@@ -329,60 +1344,424 @@ impl ExcelProcessor {
         Ok((all_sheets_data, all_headers))
     }
 
-    /// 插入Excel数据到数据库
+    /// 预览文件中的工作表：列出全部工作表的可见性、是否会被导入、数据行数与表头，不写入数据库
+    ///
+    /// 只解析表头，数据行数按工作表数据区域的行数计算，不逐行读取数据。
+    /// 读取为同步操作，在异步环境中应通过spawn_blocking调用。
+    pub fn preview_excel_file(file_path: &str, options: &ImportOptions) -> Result<FilePreview, Box<dyn std::error::Error>> {
+        let (sheets, missing_sheets) = Self::inspect_sheets(file_path, options, true, |sheet| {
+            let rows: Vec<&[Data]> = sheet.range.rows().collect();
+            if rows.is_empty() {
+                return Ok((Vec::new(), 0, ()));
+            }
+            let (headers, header_end) = Self::sheet_headers(sheet, &rows, options)?;
+            // 表头下方的每一行都会作为数据行导入（没有列标题时不导入任何行）
+            let row_count = if headers.is_empty() { 0 } else { rows.len() - header_end };
+            Ok((headers, row_count, ()))
+        })?;
+        Ok(FilePreview {
+            sheets: sheets.into_iter().map(|(sheet, _)| sheet).collect(),
+            missing_sheets,
+        })
+    }
+
+    /// 试导入：按导入选项读取、清理并应用列映射规则，统计列类型、列出前若干行、会被拒绝的行与可疑转义，不写入数据库
+    ///
+    /// 会被拒绝的行与导入时相同：校验规则、upsert模式的键列及数据库的tsvector大小限制；
+    /// 唯一性规则只检查文件内（所有会被导入的工作表之间）的重复，不查询工作区中已有的数据。
+    /// 按导入选项不会导入的工作表只列出名称与跳过原因，不读取内容。
+    /// 读取与解析为同步操作，在异步环境中应通过spawn_blocking调用。
+    pub fn dry_run_excel_file(
+        file_path: &str,
+        options: &ImportOptions,
+        mapping: Option<&ColumnMapping>,
+        sample_rows: usize,
+    ) -> Result<DryRunReport, Box<dyn std::error::Error>> {
+        let (sheets, missing_sheets) = Self::inspect_sheets(file_path, options, false, |sheet| {
+            let (headers, rows) = Self::parse_sheet(sheet, options)?;
+            Ok((headers, rows.len(), rows))
+        })?;
+        let mut validator = mapping.map(|mapping| RowValidator::new(&mapping.validation)).transpose()?;
+        let key_columns = options.upsert_key_columns(mapping);
+
+        let mut report = DryRunReport {
+            sheets: Vec::with_capacity(sheets.len()),
+            missing_sheets,
+            total_rows: 0,
+            rejected_count: 0,
+            suspicious_count: 0,
+        };
+
+        // 先清理并检查全部数据行，键列的重复需要跨工作表检查
+        let mut prepared_sheets = Vec::with_capacity(sheets.len());
+        for (mut preview, rows) in sheets {
+            if let Some(mapping) = mapping {
+                preview.headers = mapping.map_field_order(&preview.headers);
+            }
+            let mut suspicious_escapes = Vec::new();
+            let mut suspicious_count = 0;
+            let prepared: Vec<PreparedRow> = rows
+                .into_iter()
+                .enumerate()
+                .map(|(index, row)| {
+                    let row_number = index + 1;
+                    // 可疑转义在清理前检测，清理会移除其中的空字符
+                    for (field, value) in Self::find_suspicious_escapes(&row.data) {
+                        suspicious_count += 1;
+                        if suspicious_escapes.len() < MAX_DRY_RUN_ISSUES {
+                            suspicious_escapes.push(SuspiciousEscape { row_number, field, value });
+                        }
+                    }
+                    Self::prepare_row(&preview.name, row_number, row, mapping, validator.as_mut())
+                })
+                .collect();
+            prepared_sheets.push((preview, prepared, suspicious_escapes, suspicious_count));
+        }
+        Self::check_upsert_keys(
+            &key_columns,
+            prepared_sheets.iter_mut().map(|(preview, rows, _, _)| (preview.name.as_str(), rows)),
+        );
+
+        for (preview, rows, suspicious_escapes, suspicious_count) in prepared_sheets {
+            let mut sheet = SheetDryRun {
+                columns: Vec::new(),
+                sample_rows: Vec::new(),
+                rejected_rows: Vec::new(),
+                rejected_count: 0,
+                suspicious_escapes,
+                suspicious_count,
+                sheet: preview,
+            };
+            let mut type_counts: Vec<BTreeMap<&'static str, usize>> = vec![BTreeMap::new(); sheet.sheet.headers.len()];
+
+            for prepared in rows {
+                let row = prepared.row;
+                for (header, counts) in sheet.sheet.headers.iter().zip(type_counts.iter_mut()) {
+                    if let Some(kind) = row.data.get(header).and_then(|value| Self::value_kind(value, row.typed.get(header))) {
+                        *counts.entry(kind).or_default() += 1;
+                    }
+                }
+
+                if !prepared.violations.is_empty() {
+                    sheet.rejected_count += 1;
+                    if sheet.rejected_rows.len() < MAX_DRY_RUN_ISSUES {
+                        let reason = prepared
+                            .violations
+                            .iter()
+                            .map(|violation| violation.message.as_str())
+                            .collect::<Vec<_>>()
+                            .join("; ");
+                        sheet.rejected_rows.push(RejectedRow {
+                            row_number: prepared.row_number,
+                            reason,
+                            reasons: prepared.violations,
+                            data: row.data,
+                        });
+                    }
+                } else if sheet.sample_rows.len() < sample_rows {
+                    sheet.sample_rows.push(DryRunRow { row_number: prepared.row_number, data: row.data });
+                }
+            }
+
+            sheet.columns = sheet
+                .sheet
+                .headers
+                .iter()
+                .zip(type_counts)
+                .map(|(name, type_counts)| ColumnProfile {
+                    name: name.clone(),
+                    inferred_type: infer_column_type(&type_counts),
+                    filled: type_counts.values().sum(),
+                    type_counts,
+                })
+                .collect();
+
+            if sheet.sheet.selected {
+                report.total_rows += sheet.sheet.row_count;
+                report.rejected_count += sheet.rejected_count;
+                report.suspicious_count += sheet.suspicious_count;
+            }
+            report.sheets.push(sheet);
+        }
+
+        Ok(report)
+    }
+
+    /// 加载文件中的工作表并用 `read` 读取每个工作表的列标题、数据行数与内容，同时返回include_sheets中找不到的名称
+    ///
+    /// `load_skipped` 为false时按导入选项不会导入的工作表不加载，只按文件中的顺序列出名称与跳过原因。
+    #[allow(clippy::type_complexity)]
+    fn inspect_sheets<T: Default>(
+        file_path: &str,
+        options: &ImportOptions,
+        load_skipped: bool,
+        read: impl Fn(&LoadedSheet) -> Result<(Vec<String>, usize, T), String>,
+    ) -> Result<(Vec<(SheetPreview, T)>, Vec<String>), Box<dyn std::error::Error>> {
+        let workbook = Self::load_sheet_ranges(file_path, |sheet_name, visibility| {
+            load_skipped || options.sheet_skip_reason(sheet_name, visibility).is_none()
+        })?;
+
+        let mut sheets: Vec<(SheetPreview, T)> = workbook
+            .sheets
+            .iter()
+            .map(|sheet| {
+                let skip_reason = options.sheet_skip_reason(&sheet.name, sheet.visibility);
+                let (headers, row_count, content, error) = match read(sheet) {
+                    Ok((headers, row_count, content)) => (headers, row_count, content, None),
+                    Err(e) => (Vec::new(), 0, T::default(), Some(e)),
+                };
+                let preview = SheetPreview {
+                    name: sheet.name.clone(),
+                    visibility: sheet.visibility,
+                    selected: skip_reason.is_none(),
+                    skip_reason,
+                    row_count,
+                    headers,
+                    error,
+                };
+                (preview, content)
+            })
+            .collect();
+        for (name, visibility) in &workbook.unloaded {
+            let preview = SheetPreview {
+                name: name.clone(),
+                visibility: *visibility,
+                selected: false,
+                skip_reason: options.sheet_skip_reason(name, *visibility),
+                row_count: 0,
+                headers: Vec::new(),
+                error: None,
+            };
+            sheets.push((preview, T::default()));
+        }
+        if !workbook.unloaded.is_empty() {
+            sheets.sort_by_key(|(preview, _)| workbook.sheet_names.iter().position(|name| *name == preview.name));
+        }
+
+        let sheet_names: Vec<&str> = workbook.sheet_names.iter().map(String::as_str).collect();
+        let missing_sheets = options
+            .missing_included_sheets(&sheet_names)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+
+        Ok((sheets, missing_sheets))
+    }
+
+    /// 按导入选项确定工作表的列标题，同时返回表头之后第一行的位置（相对于数据区域首行），`rows` 不能为空
+    fn sheet_headers(sheet: &LoadedSheet, rows: &[&[Data]], options: &ImportOptions) -> Result<(Vec<String>, usize), String> {
+        let sheet_name = &sheet.name;
+        let origin = sheet.range.start().unwrap_or((0, 0));
+        // 确定表头所在的行（相对于数据区域首行）
+        let header_start = match options.header_row {
+            Some(header_row) => (header_row - 1)
+                .checked_sub(origin.0 as usize)
+                .filter(|index| *index < rows.len())
+                .ok_or_else(|| format!("第 {} 行不在工作表 {} 的数据范围内", header_row, sheet_name))?,
+            None if options.auto_detect_header => sheet_header::detect_header_row(rows),
+            None => 0,
+        };
+        let header_end = (header_start + options.header_rows).min(rows.len());
+
+        // 获取列标题：合并多行表头，并为空白、重复的列名生成唯一名称
+        let mut header_texts: Vec<Vec<String>> = rows[header_start..header_end]
+            .iter()
+            .map(|row| row.iter().map(|cell| Self::clean_field_name(&Self::cell_to_text(cell, false))).collect())
+            .collect();
+        sheet_header::fill_merged_header_cells(
+            &mut header_texts,
+            (origin.0 + header_start as u32, origin.1),
+            sheet.merged_regions.as_deref(),
+        );
+        let headers = sheet_header::disambiguate_headers(sheet_header::combine_header_rows(&header_texts));
+        if header_start > 0 {
+            info!("工作表 {} 使用第 {} 行作为表头", sheet_name, origin.0 as usize + header_start + 1);
+        }
+
+        Ok((headers, header_end))
+    }
+
+    /// 按导入选项解析单个工作表，返回列标题与数据行
+    fn parse_sheet(sheet: &LoadedSheet, options: &ImportOptions) -> Result<(Vec<String>, Vec<SheetRow>), String> {
+        let sheet_name = &sheet.name;
+        let mut rows_data = Vec::new();
+
+        let rows: Vec<&[Data]> = sheet.range.rows().collect();
+        if rows.is_empty() {
+            info!("工作表 {} 为空，跳过", sheet_name);
+            return Ok((Vec::new(), rows_data));
+        }
+
+        let origin = sheet.range.start().unwrap_or((0, 0));
+        let (headers, header_end) = Self::sheet_headers(sheet, &rows, options)?;
+
+        let merge_sources = if options.fill_merged_cells {
+            Self::merged_cell_sources(sheet, origin, header_end)
+        } else {
+            HashMap::new()
+        };
+
+        // 数据区域中出现过带时刻的日期时间的列，该列零点的值按日期时间而不是日期处理
+        let mut time_columns = vec![false; headers.len()];
+        for row in rows.iter().skip(header_end) {
+            for (col_idx, cell) in row.iter().enumerate().take(headers.len()) {
+                time_columns[col_idx] |= has_time_of_day(cell);
+            }
+        }
+
+        // 处理数据行
+        for (row_idx, row) in rows.iter().enumerate().skip(header_end) {
+            let mut row_data = SheetRow::default();
+
+            for (col_idx, cell) in row.iter().enumerate() {
+                if col_idx < headers.len() {
+                    // 合并区域中除左上角外的空白单元格取左上角的值
+                    let cell = match merge_sources.get(&(row_idx, col_idx)) {
+                        Some(&(source_row, source_col)) if matches!(cell, Data::Empty) => {
+                            match rows[source_row].get(source_col) {
+                                Some(source) if !matches!(source, Data::Empty) => {
+                                    row_data.merged_fills.push(MergedFill {
+                                        field: headers[col_idx].clone(),
+                                        cell: cell_reference(origin.0 + row_idx as u32, origin.1 + col_idx as u32),
+                                        source: cell_reference(origin.0 + source_row as u32, origin.1 + source_col as u32),
+                                    });
+                                    source
+                                }
+                                _ => cell,
+                            }
+                        }
+                        _ => cell,
+                    };
+                    let cell_str = Self::cell_to_text(cell, time_columns[col_idx]);
+                    let value = if cell_str.trim().is_empty() {
+                        Value::Null
+                    } else {
+                        // 显示文本统一按文本存储，避免数字/时间被自动类型改写；原生类型另存于typed
+                        Value::String(cell_str)
+                    };
+                    row_data.data.insert(headers[col_idx].clone(), value);
+                    if let Some(typed) = Self::cell_typed_value(cell, time_columns[col_idx]) {
+                        row_data.typed.insert(headers[col_idx].clone(), typed);
+                    }
+                }
+            }
+
+            if !row_data.data.is_empty() {
+                rows_data.push(row_data);
+            }
+        }
+
+        Ok((headers, rows_data))
+    }
+
+    /// 在文件的导入事务中插入Excel数据，返回写入时被数据库拒绝的行
+    ///
+    /// 严格模式下出现无法写入的行时返回错误，由调用方回滚整个文件；否则跳过这些行，其余行照常写入。
+    #[allow(clippy::too_many_arguments)]
     async fn insert_excel_data(
-        &self,
+        txn: &sea_orm::DatabaseTransaction,
         workspace_id: Option<i32>,
         file_id: i32,
         file_path: &str,
         sheet_name: &str,
-        rows_data: Vec<HashMap<String, Value>>,
-    ) -> Result<bool, sea_orm::DbErr> {
+        rows_data: Vec<PreparedRow>,
+        rows_before: usize,
+        strict: bool,
+        progress: Option<&ImportProgress>,
+    ) -> Result<Vec<RowRejection>, sea_orm::DbErr> {
         if rows_data.is_empty() {
-            return Ok(true);
+            info!("文件ID {} 没有数据", file_id);
+            return Ok(Vec::new());
         }
 
         let now = chrono::Utc::now();
-        let mut records = Vec::new();
+        let records: Vec<_> = rows_data
+            .into_iter()
+            .map(|prepared| {
+                let record = excel_data::ActiveModel {
+                    id: Default::default(),
+                    workspace_id: Set(workspace_id),
+                    file_id: Set(file_id),
+                    import_time: Set(now),
+                    row_number: Set(prepared.row_number as i32),
+                    data_json: Set(serde_json::to_value(&prepared.row.data).unwrap_or_default()),
+                    typed_json: Set(serde_json::to_value(&prepared.row.typed).unwrap_or_default()),
+                    search_text: Set(prepared.search_text),
+                    sheet_name: Set(sheet_name.to_string()),
+                };
+                (prepared.row_number, prepared.row.data, record)
+            })
+            .collect();
 
-        for (index, mut row_data) in rows_data.into_iter().enumerate() {
-            // 清理数据中的问题字符
-            Self::clean_row_data(&mut row_data);
-            
-            // 构建搜索文本
-            let search_parts: Vec<String> = row_data
-                .values()
-                .map(|v| {
-                    match v {
-                        Value::String(s) => s.clone(),
-                        Value::Bool(b) => b.to_string(),
-                        _ => String::new(),
+        // 每批使用一个保存点插入；某一批失败时回滚该批并逐条重试，定位出错的数据行
+        let total_records = records.len();
+        let mut inserted = 0usize;
+        let mut processed = 0usize;
+        let mut rejections = Vec::new();
+
+        let mut records = records.into_iter().peekable();
+        while records.peek().is_some() {
+            let batch: Vec<_> = records.by_ref().take(INSERT_BATCH_SIZE).collect();
+            let batch_len = batch.len();
+
+            let savepoint = txn.begin().await?;
+            let batch_result = excel_data::Entity::insert_many(batch.iter().map(|(_, _, record)| record.clone()))
+                .exec(&savepoint)
+                .await;
+            match batch_result {
+                Ok(_) => {
+                    savepoint.commit().await?;
+                    inserted += batch_len;
+                }
+                Err(batch_err) => {
+                    savepoint.rollback().await?;
+                    warn!(
+                        "批量插入失败，逐条重试定位问题行: 文件={} 工作表={} 起始行号={} 错误={}",
+                        file_path, sheet_name, batch[0].0, batch_err
+                    );
+                    let failed = Self::diagnose_failed_batch(txn, file_path, sheet_name, batch, strict).await?;
+                    if failed.is_empty() {
+                        // 逐条插入全部成功说明批量失败与具体数据行无关，按原错误返回
+                        return Err(batch_err);
                     }
-                })
-                .collect();
-            let search_text = search_parts.join(" ");
-
-            let record = excel_data::ActiveModel {
-                id: Default::default(),
-                workspace_id: Set(workspace_id),
-                file_id: Set(file_id),
-                import_time: Set(now),
-                row_number: Set((index + 1) as i32),
-                data_json: Set(serde_json::to_value(row_data.clone()).unwrap_or_default()),
-                search_text: Set(search_text),
-                sheet_name: Set(sheet_name.to_string()),
-            };
+                    inserted += batch_len - failed.len();
+                    rejections.extend(failed);
+                }
+            }
 
-            records.push(((index + 1), row_data.clone(), record));
+            let before = processed;
+            processed += batch_len;
+            let crossed_interval = progress.is_some_and(|p| processed / p.interval > before / p.interval);
+            if crossed_interval || processed == total_records {
+                report_progress(progress, ImportEvent::RowsInserted {
+                    sheet: sheet_name.to_string(),
+                    sheet_rows: inserted,
+                    file_rows: rows_before + inserted,
+                });
+            }
         }
 
-        // 使用事务逐条插入，并在失败时打印详细上下文
-        if !records.is_empty() {
-            let txn = self.db.begin().await?;
-            let total_records = records.len();
+        info!("成功导入文件ID {}，工作表 {}，共 {} 条记录", file_id, sheet_name, inserted);
+        Ok(rejections)
+    }
 
-            for (row_no, row_data, record) in records {
-                if let Err(e) = record.insert(&txn).await {
+    /// 逐条插入批量失败的数据行，每行使用单独的保存点
+    ///
+    /// 失败的行打印详细上下文；严格模式下遇到第一条失败的行时返回错误，否则记为拒绝行继续插入其余行。
+    async fn diagnose_failed_batch(
+        txn: &sea_orm::DatabaseTransaction,
+        file_path: &str,
+        sheet_name: &str,
+        batch: Vec<(usize, HashMap<String, Value>, excel_data::ActiveModel)>,
+        strict: bool,
+    ) -> Result<Vec<RowRejection>, sea_orm::DbErr> {
+        let mut rejections = Vec::new();
+        for (row_no, row_data, record) in batch {
+            let savepoint = txn.begin().await?;
+            match record.insert(&savepoint).await {
+                Ok(_) => savepoint.commit().await?,
+                Err(e) => {
+                    savepoint.rollback().await?;
                     let suspicious_fields = Self::find_suspicious_escapes(&row_data);
                     let raw_json = serde_json::to_string(&row_data).unwrap_or_default();
                     error!(
@@ -394,22 +1773,109 @@ impl ExcelProcessor {
                         suspicious_fields,
                         raw_json
                     );
-                    // 出现错误直接返回，让上层日志保持"工作表 X 数据导入失败"
-                    return Err(e);
+                    if strict {
+                        // 直接返回，让上层日志保持"工作表 X 数据导入失败"
+                        return Err(e);
+                    }
+                    rejections.push(RowRejection {
+                        sheet_name: sheet_name.to_string(),
+                        row_number: row_no,
+                        reasons: vec![RowViolation::new("", "database", format!("写入数据库失败: {}", e))],
+                        data: row_data,
+                    });
                 }
             }
+        }
+        Ok(rejections)
+    }
+
+    /// 在工作区的其他文件中检查唯一列的取值，重复的行追加违规记录
+    async fn check_workspace_uniqueness(
+        &self,
+        workspace_id: i32,
+        file_id: Option<i32>,
+        validator: &RowValidator,
+        sheets: &mut [(String, Vec<PreparedRow>)],
+    ) -> Result<(), sea_orm::DbErr> {
+        for column in validator.unique_columns() {
+            let values: Vec<String> = sheets
+                .iter()
+                .flat_map(|(_, rows)| rows.iter())
+                .filter_map(|prepared| row_validation::unique_value(&prepared.row.data, column))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            let existing = row_validation::find_existing_values(&self.db, workspace_id, file_id, column, &values).await?;
+            if existing.is_empty() {
+                continue;
+            }
+
+            for prepared in sheets.iter_mut().flat_map(|(_, rows)| rows.iter_mut()) {
+                if let Some(value) = row_validation::unique_value(&prepared.row.data, column) {
+                    if existing.contains(&value) {
+                        prepared.violations.push(RowViolation::new(
+                            column,
+                            "unique",
+                            format!("{} 的值 {} 已存在于工作区的其他文件中", column, value),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取文件，清理数据行、应用列映射规则并按校验规则检查
+    ///
+    /// 读取与解析为同步操作，在异步环境中应通过spawn_blocking调用；读取失败时不改动数据库中已有的数据。
+    fn prepare_file(
+        file_path: &str,
+        options: &ImportOptions,
+        column_profile: Option<&(i32, ColumnMapping)>,
+    ) -> Result<PreparedFile, String> {
+        let (all_sheets_data, field_order) = Self::read_excel_file_with_options(file_path, options)
+            .map_err(|e| format!("文件读取失败 {}: {}", file_path, e))?;
+        let total_rows: usize = all_sheets_data.iter().map(|(_, rows)| rows.len()).sum();
+        info!("文件读取成功 {}: 共 {} 个工作表，{} 行数据", file_path, all_sheets_data.len(), total_rows);
+
+        let mapping = column_profile.map(|(_, mapping)| mapping);
+        let field_order = match column_profile {
+            Some((version, mapping)) => {
+                info!("文件 {} 按列映射规则版本 {} 导入", file_path, version);
+                mapping.map_field_order(&field_order)
+            }
+            None => field_order,
+        };
 
-            txn.commit().await?;
+        // 记录本次导入使用的选项及由合并单元格填充的单元格
+        let import_metadata = Self::build_import_metadata(&all_sheets_data, options, column_profile);
 
-            info!("成功导入文件ID {}，工作表 {}，共 {} 条记录", file_id, sheet_name, total_records);
-            Ok(true)
-        } else {
-            info!("文件ID {} 没有数据", file_id);
-            Ok(true)
-        }
+        let mut validator = mapping
+            .map(|mapping| RowValidator::new(&mapping.validation))
+            .transpose()
+            .map_err(|e| format!("工作区校验规则无效 {}: {}", file_path, e))?;
+        let sheets = all_sheets_data
+            .into_iter()
+            .map(|(sheet_name, rows)| {
+                let prepared = rows
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, row)| Self::prepare_row(&sheet_name, index + 1, row, mapping, validator.as_mut()))
+                    .collect();
+                (sheet_name, prepared)
+            })
+            .collect();
+
+        Ok(PreparedFile {
+            field_order,
+            import_metadata,
+            sheets,
+            validator,
+        })
     }
 
     /// 处理单个文件（用于多线程调用）
+    #[allow(clippy::too_many_arguments)]
     async fn process_single_file(
         &self,
         file_path: &str,
@@ -417,7 +1883,10 @@ impl ExcelProcessor {
         workspace_id: Option<i32>,
         uploaded_by: Option<i32>,
         original_file_name: Option<&str>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        known_hash: Option<&str>,
+        options: &ImportOptions,
+        progress: Option<&ImportProgress>,
+    ) -> Result<ImportSummary, Box<dyn std::error::Error + Send + Sync>> {
         info!("开始处理文件: {}", file_path);
         
         // 如果不是强制重新导入，检查文件是否需要更新
@@ -425,7 +1894,7 @@ impl ExcelProcessor {
             match self.is_file_changed(file_path, workspace_id).await {
                 Ok(false) => {
                     info!("文件未发生变化，跳过处理: {}", file_path);
-                    return Ok(());
+                    return Ok(ImportSummary::default());
                 },
                 Ok(true) => {
                     info!("文件已发生变化，需要重新处理: {}", file_path);
@@ -436,50 +1905,186 @@ impl ExcelProcessor {
                 }
             }
         }
+
+        // 工作区的列映射规则，字段顺序按规则改写，数据行在写入前逐行应用
+        let column_profile = match workspace_id {
+            Some(workspace_id) => column_profile::load_mapping(&self.db, workspace_id)
+                .await
+                .map_err(|e| format!("读取列映射规则失败 {}: {}", file_path, e))?,
+            None => None,
+        };
+        let mapping = column_profile.as_ref().map(|(_, mapping)| mapping);
+        let column_profile_version = column_profile.as_ref().map(|(version, _)| *version);
+
+        let key_columns = options.upsert_key_columns(mapping);
+        if options.mode == ImportMode::Upsert {
+            if workspace_id.is_none() {
+                return Err("upsert模式只支持导入到工作区".into());
+            }
+            if key_columns.is_empty() {
+                return Err(format!("upsert模式需要指定键列（key_columns）: {}", file_path).into());
+            }
+        }
+
+        let mut summary = ImportSummary::default();
+
+        // replace模式：沿用同名文件上一版本的文件记录，文件与导入设置都没有变化时无需重新导入
+        let mut replaced = None;
+        if let (ImportMode::Replace, Some(workspace_id)) = (options.mode, workspace_id) {
+            let file_name = Self::display_file_name(file_path, original_file_name);
+            let previous = self
+                .replace_previous_version(workspace_id, file_path, &file_name, known_hash, options, column_profile_version)
+                .await
+                .map_err(|e| format!("查找同名文件的上一版本失败 {}: {}", file_path, e))?;
+            match previous {
+                PreviousVersion::None => info!("工作区中没有同名文件 {}，作为新文件导入", file_name),
+                PreviousVersion::Unchanged { file_id, rows } => {
+                    info!("文件 {} 与上一版本（文件ID {}）相同，跳过导入", file_path, file_id);
+                    // 新上传的文件与已有文件相同，不再保留
+                    if let Err(e) = fs::remove_file(file_path) {
+                        warn!("删除重复上传的文件失败 {}: {}", file_path, e);
+                    }
+                    summary.file_id = Some(file_id);
+                    summary.unchanged_rows = rows;
+                    report_progress(progress, ImportEvent::FileDone {
+                        total_rows: rows,
+                        summary: summary.clone(),
+                    });
+                    return Ok(summary);
+                }
+                PreviousVersion::Adopted { file_id, rows, old_path } => {
+                    info!("文件 {} 替换上一版本（文件ID {}，{} 行）", file_path, file_id, rows);
+                    summary.replaced_rows = rows;
+                    replaced = Some((file_id, old_path));
+                }
+            }
+        }
         
-        // 获取或创建文件元数据
-        let file_id = match self
-            .get_or_create_file_metadata(file_path, workspace_id, uploaded_by, original_file_name)
+        // 读取与逐行处理为同步操作，在阻塞线程中执行，大文件不会长时间占用异步运行时的工作线程
+        let prepared = {
+            let (file_path, options, column_profile) = (file_path.to_string(), options.clone(), column_profile.clone());
+            tokio::task::spawn_blocking(move || Self::prepare_file(&file_path, &options, column_profile.as_ref()))
+        };
+        let PreparedFile {
+            field_order,
+            import_metadata,
+            mut sheets,
+            validator,
+        } = prepared
             .await
-        {
-            Ok(id) => {
-                info!("文件元数据处理成功，文件ID: {}", id);
-                id
-            },
-            Err(e) => {
-                return Err(format!("处理文件元数据失败 {}: {}", file_path, e).into());
-            }
+            .map_err(|e| format!("文件读取任务失败 {}: {}", file_path, e))??;
+        let strict = mapping.is_some_and(|mapping| mapping.validation.strict);
+
+        // 唯一列排除的文件：replace模式下被替换的上一版本，或同一路径已有的文件记录
+        let existing_file_id = match &replaced {
+            Some((file_id, _)) => Some(*file_id),
+            None => self
+                .find_file_record(file_path, workspace_id)
+                .await
+                .map_err(|e| format!("查询文件记录失败 {}: {}", file_path, e))?
+                .map(|file| file.id),
         };
 
-        // 删除现有数据
-        if let Err(e) = self.delete_file_data(file_id).await {
-            return Err(format!("删除现有数据失败 {}: {}", file_path, e).into());
+        if let (Some(workspace_id), Some(validator)) = (workspace_id, &validator) {
+            self.check_workspace_uniqueness(workspace_id, existing_file_id, validator, &mut sheets)
+                .await
+                .map_err(|e| format!("检查唯一列失败 {}: {}", file_path, e))?;
         }
-        
+        Self::check_upsert_keys(&key_columns, sheets.iter_mut().map(|(sheet_name, rows)| (sheet_name.as_str(), rows)));
+
+        let sheet_order: Vec<String> = sheets.iter().map(|(sheet_name, _)| sheet_name.clone()).collect();
+        let mut rejections = Vec::new();
+        let sheets: Vec<(String, Vec<PreparedRow>)> = sheets
+            .into_iter()
+            .map(|(sheet_name, rows)| {
+                let (valid, invalid): (Vec<_>, Vec<_>) = rows.into_iter().partition(|prepared| prepared.violations.is_empty());
+                rejections.extend(invalid.into_iter().map(|prepared| prepared.into_rejection(&sheet_name)));
+                (sheet_name, valid)
+            })
+            .collect();
+
+        // 严格模式下只要有不合格的行，整个文件都不导入；此时还没有创建或改动文件记录，
+        // 上一版本的数据与拒绝记录保持不变，拒绝的行随错误返回给调用方
+        if strict && !rejections.is_empty() {
+            return Err(Box::new(StrictModeRejection {
+                file_path: file_path.to_string(),
+                rejections,
+            }));
+        }
+
+        // 创建文件记录、替换旧数据、更新已有行与插入新行在同一个事务中完成，任何一步失败时文件保持导入前的状态
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| format!("开始导入事务失败 {}: {}", file_path, e))?;
+
+        // replace模式沿用上一版本的文件记录，改为指向新上传的文件
+        if let Some((file_id, _)) = &replaced {
+            files::ActiveModel {
+                id: Set(*file_id),
+                file_path: Set(file_path.to_string()),
+                updated_at: Set(chrono::Utc::now()),
+                ..Default::default()
+            }
+            .update(&txn)
+            .await
+            .map_err(|e| format!("更新文件记录失败 {}: {}", file_path, e))?;
+        }
+        let file_id = self
+            .get_or_create_file_metadata(&txn, file_path, workspace_id, uploaded_by, original_file_name, known_hash)
+            .await
+            .map_err(|e| format!("处理文件元数据失败 {}: {}", file_path, e))?;
+        info!("文件元数据处理成功，文件ID: {}", file_id);
+
+        // 删除现有数据
+        Self::delete_file_data(&txn, file_id)
+            .await
+            .map_err(|e| format!("删除现有数据失败 {}: {}", file_path, e))?;
         info!("已删除文件 {} 的现有数据", file_path);
 
-        // 读取Excel文件
-        let (all_sheets_data, field_order) = match self.read_excel_file(file_path).await {
-            Ok((data, headers)) => {
-                let total_rows: usize = data.iter().map(|(_, rows)| rows.len()).sum();
-                info!("文件读取成功 {}: 共 {} 个工作表，{} 行数据", file_path, data.len(), total_rows);
-                (data, headers)
-            },
-            Err(e) => {
-                return Err(format!("文件读取失败 {}: {}", file_path, e).into());
+        // 更新文件的字段顺序与导入信息
+        Self::update_file_field_order(&txn, file_id, &field_order)
+            .await
+            .map_err(|e| format!("更新文件字段顺序失败 {}: {}", file_path, e))?;
+        Self::update_file_import_metadata(&txn, file_id, import_metadata)
+            .await
+            .map_err(|e| format!("更新文件导入信息失败 {}: {}", file_path, e))?;
+
+        // upsert模式：先更新工作区中按键列匹配到的已有数据行，其余的行作为本文件的数据插入
+        let sheets = match workspace_id.filter(|_| !key_columns.is_empty()) {
+            Some(workspace_id) => {
+                let (sheets, updated_rows, unchanged_rows) = Self::upsert_existing_rows(&txn, workspace_id, file_id, &key_columns, sheets)
+                    .await
+                    .map_err(|e| format!("更新已有数据行失败 {}: {}", file_path, e))?;
+                info!("文件 {} 更新已有数据 {} 行，{} 行没有变化", file_path, updated_rows, unchanged_rows);
+                summary.updated_rows = updated_rows;
+                summary.unchanged_rows = unchanged_rows;
+                sheets
             }
+            None => sheets,
         };
 
-        // 更新文件的字段顺序信息
-        if let Err(e) = self.update_file_field_order(file_id, &field_order).await {
-            error!("更新文件字段顺序失败 {}: {}", file_path, e);
-        }
-
         // 插入每个工作表的数据
-        for (sheet_name, rows_data) in all_sheets_data {
-            match self.insert_excel_data(workspace_id, file_id, file_path, &sheet_name, rows_data).await {
-                Ok(_) => {
+        let mut file_rows = 0usize;
+        for (sheet_name, rows_data) in sheets {
+            let sheet_rows = rows_data.len();
+            report_progress(progress, ImportEvent::SheetStarted {
+                sheet: sheet_name.clone(),
+                total_rows: sheet_rows,
+            });
+            match Self::insert_excel_data(&txn, workspace_id, file_id, file_path, &sheet_name, rows_data, file_rows, strict, progress)
+                .await
+            {
+                Ok(failed) => {
                     info!("工作表 {} 数据导入成功", sheet_name);
+                    let inserted = sheet_rows - failed.len();
+                    file_rows += inserted;
+                    rejections.extend(failed);
+                    report_progress(progress, ImportEvent::SheetFinished {
+                        sheet: sheet_name,
+                        rows: inserted,
+                    });
                 },
                 Err(e) => {
                     error!("工作表 {} 数据导入失败: {}", sheet_name, e);
@@ -488,12 +2093,249 @@ impl ExcelProcessor {
             }
         }
 
+        // 拒绝记录按工作表顺序和行号保存
+        if !rejections.is_empty() {
+            rejections.sort_by_key(|rejection| {
+                (
+                    sheet_order.iter().position(|sheet_name| *sheet_name == rejection.sheet_name),
+                    rejection.row_number,
+                )
+            });
+            warn!("文件 {} 有 {} 行未导入，已记录到拒绝列表", file_path, rejections.len());
+            row_validation::save_rejections(&txn, workspace_id, RejectionOwner::File(file_id), &rejections)
+                .await
+                .map_err(|e| format!("保存拒绝记录失败 {}: {}", file_path, e))?;
+        }
+
+        txn.commit()
+            .await
+            .map_err(|e| format!("提交导入事务失败 {}: {}", file_path, e))?;
+
+        // 新版本提交后再删除被替换的旧文件
+        if let Some((_, old_path)) = replaced.filter(|(_, old_path)| old_path != file_path) {
+            if let Err(e) = fs::remove_file(&old_path) {
+                warn!("删除被替换的文件失败 {}: {}", old_path, e);
+            }
+        }
+
+        summary.file_id = Some(file_id);
+        summary.inserted_rows = file_rows;
+        summary.rejected_rows = rejections.len();
         info!("文件数据导入成功: {}", file_path);
+        report_progress(progress, ImportEvent::FileDone {
+            total_rows: summary.inserted_rows + summary.updated_rows + summary.unchanged_rows,
+            summary: summary.clone(),
+        });
+        Ok(summary)
+    }
+
+    /// 导入后显示的文件名：优先使用上传时的原始文件名
+    fn display_file_name(file_path: &str, original_file_name: Option<&str>) -> String {
+        original_file_name
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(ToOwned::to_owned)
+            .unwrap_or_else(|| {
+                Path::new(file_path)
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            })
+    }
+
+    /// replace模式：查找工作区中同名文件的上一版本（最近更新的一个）
+    ///
+    /// 文件哈希（与is_file_changed相同的判断）、导入选项和列映射规则版本都相同时视为未变化；
+    /// 否则之后的导入沿用其文件ID，在导入事务中把文件记录改为指向新上传的文件并替换其数据，
+    /// 新版本导入失败时上一版本保持不变。
+    async fn replace_previous_version(
+        &self,
+        workspace_id: i32,
+        file_path: &str,
+        file_name: &str,
+        known_hash: Option<&str>,
+        options: &ImportOptions,
+        column_profile_version: Option<i32>,
+    ) -> Result<PreviousVersion, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(previous) = files::Entity::find()
+            .filter(files::Column::WorkspaceId.eq(workspace_id))
+            .filter(files::Column::FileName.eq(file_name))
+            .filter(files::Column::FilePath.ne(file_path))
+            .order_by_desc(files::Column::UpdatedAt)
+            .one(&self.db)
+            .await?
+        else {
+            return Ok(PreviousVersion::None);
+        };
+
+        let rows = excel_data::Entity::find()
+            .filter(excel_data::Column::FileId.eq(previous.id))
+            .count(&self.db)
+            .await? as usize;
+
+        let file_hash = match known_hash {
+            Some(hash) => hash.to_string(),
+            None => self
+                .generate_file_hash(file_path)
+                .await
+                .map_err(|e| format!("文件哈希生成失败: {}", e))?,
+        };
+        // 导入方式本身不影响导入的数据，比较时忽略
+        let import_settings = |options: Option<&Value>, version: Option<&Value>| {
+            let mut options = options.cloned().unwrap_or(Value::Null);
+            if let Some(options) = options.as_object_mut() {
+                options.remove("mode");
+                options.remove("key_columns");
+            }
+            (options, version.cloned().unwrap_or(Value::Null))
+        };
+        let previous_settings = import_settings(
+            previous.import_metadata.as_ref().and_then(|metadata| metadata.get("options")),
+            previous.import_metadata.as_ref().and_then(|metadata| metadata.get("column_profile_version")),
+        );
+        let current_settings = import_settings(
+            Some(&serde_json::to_value(options).unwrap_or_default()),
+            Some(&serde_json::json!(column_profile_version)),
+        );
+        if previous.file_hash == file_hash && previous_settings == current_settings {
+            return Ok(PreviousVersion::Unchanged { file_id: previous.id, rows });
+        }
+
+        Ok(PreviousVersion::Adopted {
+            file_id: previous.id,
+            rows,
+            old_path: previous.file_path,
+        })
+    }
+
+    /// upsert模式：按键列匹配工作区中已有的数据行，取值有变化的就地更新（保留原来的文件与行号），
+    /// 返回需要插入的行以及更新、未变化的行数
+    #[allow(clippy::type_complexity)]
+    async fn upsert_existing_rows(
+        txn: &sea_orm::DatabaseTransaction,
+        workspace_id: i32,
+        file_id: i32,
+        key_columns: &[String],
+        sheets: Vec<(String, Vec<PreparedRow>)>,
+    ) -> Result<(Vec<(String, Vec<PreparedRow>)>, usize, usize), sea_orm::DbErr> {
+        let existing = Self::find_rows_by_key(txn, workspace_id, file_id, key_columns, &sheets).await?;
+
+        let mut updates = Vec::new();
+        let mut updated_rows = 0usize;
+        let mut unchanged_rows = 0usize;
+        let sheets = sheets
+            .into_iter()
+            .map(|(sheet_name, rows)| {
+                let mut to_insert = Vec::with_capacity(rows.len());
+                for prepared in rows {
+                    let key = upsert_key::row_key(&prepared.row.data, key_columns);
+                    let data_json = serde_json::to_value(&prepared.row.data).unwrap_or_default();
+                    let typed_json = serde_json::to_value(&prepared.row.typed).unwrap_or_default();
+                    let changed = match upsert_key::match_existing(key.as_ref(), &data_json, &typed_json, &existing) {
+                        UpsertAction::Insert => {
+                            to_insert.push(prepared);
+                            continue;
+                        }
+                        UpsertAction::Unchanged => {
+                            unchanged_rows += 1;
+                            continue;
+                        }
+                        UpsertAction::Update(changed) => changed,
+                    };
+                    updated_rows += 1;
+                    for id in changed {
+                        updates.push(serde_json::json!({
+                            "id": id,
+                            "data_json": data_json,
+                            "typed_json": typed_json,
+                            "search_text": prepared.search_text,
+                        }));
+                    }
+                }
+                (sheet_name, to_insert)
+            })
+            .collect();
+
+        Self::update_existing_rows(txn, &updates).await?;
+        Ok((sheets, updated_rows, unchanged_rows))
+    }
+
+    /// 查询工作区其他文件中键值与本次导入的行相同的数据行，按键分组
+    ///
+    /// 每个键作为 {键列: 取值} 对象，通过excel_key_fields表达式索引按包含关系查找，每批KEY_LOOKUP_BATCH_SIZE个键；
+    /// 查询在导入事务中执行，索引之外的开销与匹配到的行数成正比。
+    async fn find_rows_by_key(
+        txn: &sea_orm::DatabaseTransaction,
+        workspace_id: i32,
+        file_id: i32,
+        key_columns: &[String],
+        sheets: &[(String, Vec<PreparedRow>)],
+    ) -> Result<HashMap<Vec<String>, Vec<excel_data::Model>>, sea_orm::DbErr> {
+        let keys: HashSet<Vec<String>> = sheets
+            .iter()
+            .flat_map(|(_, rows)| rows.iter())
+            .filter_map(|prepared| upsert_key::row_key(&prepared.row.data, key_columns))
+            .collect();
+        let probes: Vec<Value> = keys
+            .iter()
+            .map(|key| Value::Object(key_columns.iter().cloned().zip(key.iter().cloned().map(Value::String)).collect()))
+            .collect();
+
+        let mut existing: HashMap<Vec<String>, Vec<excel_data::Model>> = HashMap::new();
+        for batch in probes.chunks(KEY_LOOKUP_BATCH_SIZE) {
+            let rows = excel_data::Entity::find()
+                .from_raw_sql(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    r#"
+                    SELECT d.* FROM jsonb_array_elements($3::jsonb) AS probe(fields)
+                    JOIN excel_data d ON excel_key_fields(d.data_json) @> excel_key_fields(probe.fields)
+                    WHERE d.workspace_id = $1 AND d.file_id <> $2
+                    ORDER BY d.id
+                    "#,
+                    [workspace_id.into(), file_id.into(), Value::Array(batch.to_vec()).into()],
+                ))
+                .all(txn)
+                .await?;
+
+            // 数据库中的比较与key_value的规则一致，这里再按完整的键分组
+            for row in rows {
+                let data: HashMap<String, Value> = serde_json::from_value(row.data_json.clone()).unwrap_or_default();
+                if let Some(key) = upsert_key::row_key(&data, key_columns).filter(|key| keys.contains(key)) {
+                    existing.entry(key).or_default().push(row);
+                }
+            }
+        }
+        Ok(existing)
+    }
+
+    /// 在文件的导入事务中批量更新已有数据行的内容
+    async fn update_existing_rows(txn: &sea_orm::DatabaseTransaction, updates: &[Value]) -> Result<(), sea_orm::DbErr> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let now = chrono::Utc::now();
+        for batch in updates.chunks(INSERT_BATCH_SIZE) {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"
+                UPDATE excel_data AS target
+                SET data_json = source.data_json,
+                    typed_json = source.typed_json,
+                    search_text = source.search_text,
+                    import_time = $2
+                FROM jsonb_to_recordset($1::jsonb) AS source(id INTEGER, data_json JSONB, typed_json JSONB, search_text TEXT)
+                WHERE target.id = source.id
+                "#,
+                [Value::Array(batch.to_vec()).into(), now.into()],
+            ))
+            .await?;
+        }
         Ok(())
     }
 
     /// 更新文件的字段顺序信息
-    async fn update_file_field_order(&self, file_id: i32, field_order: &[String]) -> Result<(), sea_orm::DbErr> {
+    async fn update_file_field_order<C: ConnectionTrait>(db: &C, file_id: i32, field_order: &[String]) -> Result<(), sea_orm::DbErr> {
         use sea_orm::{ActiveModelTrait, EntityTrait, Set};
         
         // 将字段顺序转换为JSON
@@ -502,34 +2344,119 @@ impl ExcelProcessor {
         
         // 查找文件记录
         let file_model = files::Entity::find_by_id(file_id)
-            .one(&self.db)
+            .one(db)
             .await?;
         
         if let Some(file) = file_model {
             let mut file_active: files::ActiveModel = file.into();
             file_active.field_order = Set(Some(field_order_json));
             file_active.updated_at = Set(chrono::Utc::now());
-            file_active.update(&self.db).await?;
+            file_active.update(db).await?;
         }
         
         Ok(())
     }
 
+    /// 生成文件的导入信息
+    ///
+    /// 合并单元格的填充按合并区域的每一列汇总为一条记录（行号与excel_data中的行号一致），
+    /// 最多记录MAX_MERGED_FILL_RANGES条，merged_fill_cells为被填充的单元格总数；
+    /// field为应用列映射规则后的列名，被规则删除的列不记录。
+    pub fn build_import_metadata(
+        sheets: &[(String, Vec<SheetRow>)],
+        options: &ImportOptions,
+        column_profile: Option<&(i32, ColumnMapping)>,
+    ) -> Value {
+        let mut ranges: Vec<Value> = Vec::new();
+        // (工作表, 来源单元格, 列名) 到ranges中位置的映射
+        let mut range_index: HashMap<(&str, &str, String), usize> = HashMap::new();
+        let mut filled_cells = 0usize;
+        let mut truncated = false;
+
+        for (sheet_name, rows) in sheets {
+            for (index, row) in rows.iter().enumerate() {
+                let row_number = index + 1;
+                for fill in &row.merged_fills {
+                    let field = match column_profile {
+                        Some((_, mapping)) => match mapping.map_field(&fill.field) {
+                            Some(field) => field,
+                            None => continue,
+                        },
+                        None => fill.field.clone(),
+                    };
+                    filled_cells += 1;
+
+                    let key = (sheet_name.as_str(), fill.source.as_str(), field);
+                    if let Some(&position) = range_index.get(&key) {
+                        let range = &mut ranges[position];
+                        range["last_row"] = row_number.into();
+                        range["cells"] = (range["cells"].as_u64().unwrap_or(0) + 1).into();
+                    } else if ranges.len() < MAX_MERGED_FILL_RANGES {
+                        ranges.push(serde_json::json!({
+                            "sheet": sheet_name,
+                            "field": key.2,
+                            "source": fill.source,
+                            "first_row": row_number,
+                            "last_row": row_number,
+                            "cells": 1,
+                        }));
+                        range_index.insert(key, ranges.len() - 1);
+                    } else {
+                        truncated = true;
+                    }
+                }
+            }
+        }
+
+        serde_json::json!({
+            "options": options,
+            "column_profile_version": column_profile.map(|(version, _)| *version),
+            "merged_fills": ranges,
+            "merged_fill_cells": filled_cells,
+            "merged_fills_truncated": truncated,
+        })
+    }
+
+    /// 更新文件的导入信息
+    async fn update_file_import_metadata<C: ConnectionTrait>(db: &C, file_id: i32, import_metadata: Value) -> Result<(), sea_orm::DbErr> {
+        files::ActiveModel {
+            id: Set(file_id),
+            import_metadata: Set(Some(import_metadata)),
+            updated_at: Set(chrono::Utc::now()),
+            ..Default::default()
+        }
+        .update(db)
+        .await?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn import_uploaded_file(
         &self,
         workspace_id: i32,
         file_path: &str,
-        uploaded_by: i32,
+        uploaded_by: Option<i32>,
         original_file_name: &str,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.process_single_file(
-            file_path,
-            true,
-            Some(workspace_id),
-            Some(uploaded_by),
-            Some(original_file_name),
-        )
-        .await
+        file_hash: Option<&str>,
+        options: &ImportOptions,
+        progress: Option<&ImportProgress>,
+    ) -> Result<ImportSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let result = self
+            .process_single_file(
+                file_path,
+                true,
+                Some(workspace_id),
+                uploaded_by,
+                Some(original_file_name),
+                file_hash,
+                options,
+                progress,
+            )
+            .await;
+        if let Err(e) = &result {
+            report_progress(progress, ImportEvent::Failed { error: e.to_string() });
+        }
+        result
     }
 
     async fn get_public_workspace_ids(&self) -> Result<Vec<i32>, sea_orm::DbErr> {
@@ -542,91 +2469,200 @@ impl ExcelProcessor {
             .await
     }
 
-    async fn get_scored_results(
-        &self,
-        query_text: &str,
-        workspace_id: Option<i32>,
-        only_public_workspaces: bool,
-    ) -> Result<Vec<((excel_data::Model, Option<files::Model>), i32)>, sea_orm::DbErr> {
-        let keywords: Vec<&str> = query_text
-            .trim()
-            .split_whitespace()
-            .filter(|k| !k.is_empty())
-            .collect();
+    /// 编译搜索条件：查询语句与范围筛选之间为AND关系
+    fn build_search_clause(criteria: &SearchCriteria, mode: SearchMode) -> SearchClause {
+        let mut clause = match &criteria.query {
+            Some(query) => Self::build_query_clause(query, mode),
+            None => SearchClause {
+                condition: "TRUE".to_string(),
+                rank: "0::real".to_string(),
+                values: Vec::new(),
+                similarity_threshold: None,
+            },
+        };
+
+        for filter in &criteria.filters {
+            let condition = range_filter_condition(filter, &mut clause.values);
+            clause.condition = format!("{} AND {}", clause.condition, condition);
+        }
+        clause
+    }
+
+    /// 根据搜索模式编译查询语句，查询词与search_text使用相同的规范化规则
+    ///
+    /// 未使用查询语法时多个关键词之间取并集并按相关度排序；
+    /// 使用了字段限定、短语或逻辑运算符时按查询表达式编译为SQL条件。
+    /// 词素切分在数据库中完成，整个搜索只需一次查询。
+    fn build_query_clause(query: &ParsedQuery, mode: SearchMode) -> SearchClause {
+        let plain_query;
+        let expr = match query.plain_keywords() {
+            Some(keywords) => {
+                plain_query = QueryExpr::Or(
+                    keywords
+                        .into_iter()
+                        .map(|keyword| QueryExpr::Term(SearchTerm { field: None, value: keyword.to_string(), phrase: false }))
+                        .collect(),
+                );
+                &plain_query
+            }
+            None => &query.expr,
+        };
+
+        let mut compiler = QueryCompiler {
+            mode,
+            values: Vec::new(),
+            ranks: Vec::new(),
+        };
+        let condition = compiler.compile(expr, false);
+        // 没有可排序的条件时使用常量表达式（ORDER BY中的裸数字会被当作列序号）
+        let rank = if compiler.ranks.is_empty() {
+            "0::real".to_string()
+        } else {
+            compiler.ranks.join(" + ")
+        };
 
-        if keywords.is_empty() {
-            return Ok(vec![]);
+        SearchClause {
+            condition,
+            rank,
+            values: compiler.values,
+            similarity_threshold: match mode {
+                SearchMode::Fuzzy { threshold } => Some(threshold),
+                SearchMode::FullText => None,
+            },
         }
+    }
 
-        let mut condition = Condition::any();
-        for keyword in &keywords {
-            condition = condition.add(excel_data::Column::SearchText.contains(*keyword));
+    /// 在当前事务内设置模糊搜索的相似度阈值（<%运算符读取该配置）
+    async fn apply_similarity_threshold(
+        txn: &sea_orm::DatabaseTransaction,
+        clause: &SearchClause,
+    ) -> Result<(), sea_orm::DbErr> {
+        if let Some(threshold) = clause.similarity_threshold {
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)",
+                [threshold.to_string().into()],
+            ))
+            .await?;
         }
+        Ok(())
+    }
 
+    /// 生成搜索范围条件，所需的参数追加到values末尾
+    fn search_scope_condition(
+        workspace_id: Option<i32>,
+        only_public_workspaces: bool,
+        values: &mut Vec<sea_orm::Value>,
+    ) -> String {
         if let Some(wid) = workspace_id {
-            condition = Condition::all()
-                .add(condition)
-                .add(excel_data::Column::WorkspaceId.eq(wid));
+            values.push(wid.into());
+            format!("d.workspace_id = ${}", values.len())
         } else if only_public_workspaces {
-            let public_workspace_ids = self.get_public_workspace_ids().await?;
-            if public_workspace_ids.is_empty() {
-                return Ok(vec![]);
-            }
-            condition = Condition::all()
-                .add(condition)
-                .add(excel_data::Column::WorkspaceId.is_in(public_workspace_ids));
+            "d.workspace_id IN (SELECT id FROM workspaces WHERE is_public = TRUE)".to_string()
+        } else {
+            "TRUE".to_string()
         }
+    }
 
-        let all_results: Vec<(excel_data::Model, Option<files::Model>)> = excel_data::Entity::find()
-            .find_also_related(files::Entity)
-            .filter(condition)
-            .all(&self.db)
+    /// 在数据库中完成检索、排序与分页
+    async fn query_ranked_results(
+        &self,
+        clause: &SearchClause,
+        workspace_id: Option<i32>,
+        only_public_workspaces: bool,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<(excel_data::Model, Option<files::Model>)>, sea_orm::DbErr> {
+        let mut values = clause.values.clone();
+        let scope = Self::search_scope_condition(workspace_id, only_public_workspaces, &mut values);
+        values.push((limit as i64).into());
+        values.push((offset as i64).into());
+        let sql = format!(
+            r#"
+            SELECT d.id, d.workspace_id, d.file_id, d.import_time, d.row_number,
+                   d.sheet_name, d.data_json, d.typed_json, d.search_text
+            FROM excel_data d
+            WHERE {condition} AND {scope}
+            ORDER BY {rank} DESC, d.import_time DESC, d.id DESC
+            LIMIT ${limit} OFFSET ${offset}
+            "#,
+            condition = clause.condition,
+            scope = scope,
+            rank = clause.rank,
+            limit = values.len() - 1,
+            offset = values.len(),
+        );
+
+        let txn = self.db.begin().await?;
+        Self::apply_similarity_threshold(&txn, clause).await?;
+        let rows = excel_data::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(DbBackend::Postgres, &sql, values))
+            .all(&txn)
             .await?;
+        txn.commit().await?;
 
-        let mut scored_results: Vec<((excel_data::Model, Option<files::Model>), i32)> = all_results
+        // 只为当前页涉及的文件加载元数据
+        let file_ids: std::collections::HashSet<i32> = rows.iter().map(|row| row.file_id).collect();
+        let file_models: HashMap<i32, files::Model> = files::Entity::find()
+            .filter(files::Column::Id.is_in(file_ids))
+            .all(&self.db)
+            .await?
             .into_iter()
-            .map(|result| {
-                let search_text = &result.0.search_text;
-                let mut score = 0;
-                for keyword in &keywords {
-                    if search_text.contains(*keyword) {
-                        score += 1;
-                    }
-                }
-                if search_text.contains(query_text) {
-                    score += keywords.len() as i32;
-                }
-                (result, score)
-            })
+            .map(|file| (file.id, file))
             .collect();
 
-        scored_results.sort_by(|a, b| match b.1.cmp(&a.1) {
-            std::cmp::Ordering::Equal => b.0.0.import_time.cmp(&a.0.0.import_time),
-            other => other,
-        });
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let file = file_models.get(&row.file_id).cloned();
+                (row, file)
+            })
+            .collect())
+    }
 
-        Ok(scored_results)
+    /// 统计匹配总数
+    async fn count_search_matches(
+        &self,
+        clause: &SearchClause,
+        workspace_id: Option<i32>,
+        only_public_workspaces: bool,
+    ) -> Result<i64, sea_orm::DbErr> {
+        let mut values = clause.values.clone();
+        let scope = Self::search_scope_condition(workspace_id, only_public_workspaces, &mut values);
+        let sql = format!(
+            "SELECT COUNT(*) AS total FROM excel_data d WHERE {} AND {}",
+            clause.condition, scope
+        );
+
+        let txn = self.db.begin().await?;
+        Self::apply_similarity_threshold(&txn, clause).await?;
+        let row = txn
+            .query_one(Statement::from_sql_and_values(DbBackend::Postgres, &sql, values))
+            .await?;
+        txn.commit().await?;
+        match row {
+            Some(row) => row.try_get("", "total"),
+            None => Ok(0),
+        }
     }
 
     async fn search_with_scope(
         &self,
-        query_text: &str,
+        criteria: &SearchCriteria,
+        mode: SearchMode,
         limit: u64,
         offset: u64,
         workspace_id: Option<i32>,
         only_public_workspaces: bool,
     ) -> Result<SearchResponse, sea_orm::DbErr> {
-        let scored_results = self
-            .get_scored_results(query_text, workspace_id, only_public_workspaces)
-            .await?;
-        let total = scored_results.len() as i64;
+        let clause = Self::build_search_clause(criteria, mode);
 
-        let paginated_results: Vec<(excel_data::Model, Option<files::Model>)> = scored_results
-            .into_iter()
-            .skip(offset as usize)
-            .take(limit as usize)
-            .map(|(result, _score)| result)
-            .collect();
+        let total = self
+            .count_search_matches(&clause, workspace_id, only_public_workspaces)
+            .await?;
+        let paginated_results = self
+            .query_ranked_results(&clause, workspace_id, only_public_workspaces, limit, offset)
+            .await?;
 
         let converted_results: Vec<ExcelData> = paginated_results
             .into_iter()
@@ -637,6 +2673,7 @@ impl ExcelProcessor {
                 import_time: excel_model.import_time,
                 row_number: excel_model.row_number,
                 data_json: excel_model.data_json.to_string(),
+                typed_json: excel_model.typed_json.to_string(),
                 search_text: excel_model.search_text,
                 sheet_name: excel_model.sheet_name,
                 file_name: file_model.as_ref().map(|f| f.file_name.clone()),
@@ -655,21 +2692,23 @@ impl ExcelProcessor {
     pub async fn search_workspace_data(
         &self,
         workspace_id: i32,
-        query_text: &str,
+        criteria: &SearchCriteria,
+        mode: SearchMode,
         limit: u64,
         offset: u64,
     ) -> Result<SearchResponse, sea_orm::DbErr> {
-        self.search_with_scope(query_text, limit, offset, Some(workspace_id), false)
+        self.search_with_scope(criteria, mode, limit, offset, Some(workspace_id), false)
             .await
     }
 
     pub async fn search_public_data(
         &self,
-        query_text: &str,
+        criteria: &SearchCriteria,
+        mode: SearchMode,
         limit: u64,
         offset: u64,
     ) -> Result<SearchResponse, sea_orm::DbErr> {
-        self.search_with_scope(query_text, limit, offset, None, true).await
+        self.search_with_scope(criteria, mode, limit, offset, None, true).await
     }
 
     pub async fn get_workspace_statistics(&self, workspace_id: i32) -> Result<StatsResponse, sea_orm::DbErr> {
@@ -733,38 +2772,231 @@ impl ExcelProcessor {
         })
     }
 
-    pub async fn export_workspace_search_results(
+    /// 统计工作区内的匹配数据并按源文件和工作表分组，没有匹配的数据时返回None
+    pub async fn plan_workspace_export(
         &self,
         workspace_id: i32,
-        query_text: &str,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        self.export_search_results_with_scope(query_text, Some(workspace_id), false).await
+        criteria: &SearchCriteria,
+        mode: SearchMode,
+    ) -> Result<Option<ExportPlan>, sea_orm::DbErr> {
+        self.plan_export_with_scope(criteria, mode, Some(workspace_id), false).await
     }
 
-    pub async fn export_public_search_results(&self, query_text: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        self.export_search_results_with_scope(query_text, None, true).await
+    /// 统计公开工作区内的匹配数据并按源文件和工作表分组，没有匹配的数据时返回None
+    pub async fn plan_public_export(
+        &self,
+        criteria: &SearchCriteria,
+        mode: SearchMode,
+    ) -> Result<Option<ExportPlan>, sea_orm::DbErr> {
+        self.plan_export_with_scope(criteria, mode, None, true).await
     }
 
-    async fn export_search_results_with_scope(
+    /// 只查询分组、行数和出现过的字段，数据行在导出时分页读取；
+    /// 分组按其中排名最高的数据排列，与搜索结果的顺序一致
+    async fn plan_export_with_scope(
         &self,
-        query_text: &str,
+        criteria: &SearchCriteria,
+        mode: SearchMode,
         workspace_id: Option<i32>,
         only_public_workspaces: bool,
-    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let scored_results = self
-            .get_scored_results(query_text, workspace_id, only_public_workspaces)
+    ) -> Result<Option<ExportPlan>, sea_orm::DbErr> {
+        let clause = Self::build_search_clause(criteria, mode);
+        let mut values = clause.values.clone();
+        let scope = Self::search_scope_condition(workspace_id, only_public_workspaces, &mut values);
+        let sql = format!(
+            r#"
+            SELECT d.file_id, d.sheet_name, COUNT(DISTINCT d.id) AS row_count,
+                   COALESCE(jsonb_agg(DISTINCT field.key) FILTER (WHERE field.key IS NOT NULL), '[]'::jsonb) AS fields
+            FROM excel_data d
+            LEFT JOIN LATERAL jsonb_object_keys(d.data_json) AS field(key) ON TRUE
+            WHERE {condition} AND {scope}
+            GROUP BY d.file_id, d.sheet_name
+            ORDER BY MAX({rank}) DESC, MAX(d.import_time) DESC, MAX(d.id) DESC
+            "#,
+            condition = clause.condition,
+            scope = scope,
+            rank = clause.rank,
+        );
+
+        let txn = self.db.begin().await?;
+        Self::apply_similarity_threshold(&txn, &clause).await?;
+        let groups = txn
+            .query_all(Statement::from_sql_and_values(DbBackend::Postgres, &sql, values))
             .await?;
+        txn.commit().await?;
 
-        if scored_results.is_empty() {
-            return Err("没有找到匹配的数据".into());
+        let mut file_ids = Vec::new();
+        for group in &groups {
+            file_ids.push(group.try_get::<i32>("", "file_id")?);
         }
+        let file_models: HashMap<i32, files::Model> = files::Entity::find()
+            .filter(files::Column::Id.is_in(file_ids))
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|file| (file.id, file))
+            .collect();
+
+        let mut sheets = Vec::with_capacity(groups.len());
+        for group in &groups {
+            let file_id: i32 = group.try_get("", "file_id")?;
+            let Some(file) = file_models.get(&file_id) else {
+                continue;
+            };
+            let fields: Value = group.try_get("", "fields")?;
+            let fields: Vec<String> = serde_json::from_value(fields).unwrap_or_default();
+            let row_count: i64 = group.try_get("", "row_count")?;
+            sheets.push(ExportSheet::new(
+                file.clone(),
+                group.try_get("", "sheet_name")?,
+                fields,
+                row_count as u64,
+            ));
+        }
+
+        if sheets.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ExportPlan {
+            clause,
+            workspace_id,
+            only_public_workspaces,
+            sheets,
+        }))
+    }
+
+    /// 按导出计划逐组分页读取数据并写入导出器，每页生成的内容立即写入sink；
+    /// 需要整个文件写完才能输出的格式（xlsx）返回生成的临时文件
+    ///
+    /// 编码每页数据与最后生成文件（xlsx需要压缩整个工作簿）都是同步操作，导出器交给阻塞线程执行后再取回。
+    pub async fn write_export<S: ExportSink + Send>(
+        &self,
+        plan: &ExportPlan,
+        mut exporter: Box<dyn SearchExporter>,
+        sink: &mut S,
+    ) -> Result<Option<std::fs::File>, ExportError> {
+        for (sheet_index, sheet) in plan.sheets.iter().enumerate() {
+            let mut after = None;
+            loop {
+                let rows = self.query_export_page(plan, sheet, after).await?;
+                let Some(last) = rows.last() else {
+                    break;
+                };
+                after = Some((last.row_number, last.id));
+                let full_page = rows.len() as u64 >= EXPORT_PAGE_SIZE;
+                let (returned, chunk) = tokio::task::spawn_blocking(move || {
+                    let chunk = exporter.write_rows(sheet_index, &rows).map(|_| exporter.take_output());
+                    (exporter, chunk)
+                })
+                .await
+                .map_err(|e| format!("导出任务异常退出: {}", e))?;
+                exporter = returned;
+                let chunk = chunk?;
+                if !chunk.is_empty() {
+                    sink.write_chunk(chunk).await?;
+                }
+                if !full_page {
+                    break;
+                }
+            }
+        }
+
+        let output = tokio::task::spawn_blocking(move || exporter.finish())
+            .await
+            .map_err(|e| format!("导出任务异常退出: {}", e))??;
+        match output {
+            ExportOutput::Bytes(rest) => {
+                if !rest.is_empty() {
+                    sink.write_chunk(rest).await?;
+                }
+                Ok(None)
+            }
+            ExportOutput::File(file) => Ok(Some(file)),
+        }
+    }
+
+    /// 读取一个分组中 (行号, ID) 在after之后的一页匹配数据
+    ///
+    /// 按 (file_id, sheet_name, row_number, id) 索引的顺序扫描该分组，读满一页即停止，不对整个分组的匹配结果排序。
+    async fn query_export_page(
+        &self,
+        plan: &ExportPlan,
+        sheet: &ExportSheet,
+        after: Option<(i32, i32)>,
+    ) -> Result<Vec<excel_data::Model>, sea_orm::DbErr> {
+        let clause = &plan.clause;
+        let mut values = clause.values.clone();
+        let scope = Self::search_scope_condition(plan.workspace_id, plan.only_public_workspaces, &mut values);
+        values.push(sheet.file.id.into());
+        values.push(sheet.sheet_name.clone().into());
+        let mut sql = format!(
+            r#"
+            SELECT d.id, d.workspace_id, d.file_id, d.import_time, d.row_number,
+                   d.sheet_name, d.data_json, d.typed_json, d.search_text
+            FROM excel_data d
+            WHERE {condition} AND {scope} AND d.file_id = ${file} AND d.sheet_name = ${sheet}
+            "#,
+            condition = clause.condition,
+            scope = scope,
+            file = values.len() - 1,
+            sheet = values.len(),
+        );
+        if let Some((row_number, id)) = after {
+            values.push(row_number.into());
+            values.push(id.into());
+            sql.push_str(&format!(" AND (d.row_number, d.id) > (${}, ${})", values.len() - 1, values.len()));
+        }
+        values.push((EXPORT_PAGE_SIZE as i64).into());
+        sql.push_str(&format!(" ORDER BY d.row_number, d.id LIMIT ${}", values.len()));
+
+        let txn = self.db.begin().await?;
+        Self::apply_similarity_threshold(&txn, clause).await?;
+        let rows = excel_data::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(DbBackend::Postgres, &sql, values))
+            .all(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(rows)
+    }
+
+    /// 导出文件被拒绝的行：每个工作表一个sheet，列为行号、文件的字段顺序和错误原因，未通过规则的单元格标红
+    pub async fn export_file_rejections(&self, file: &files::Model) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let field_order: Vec<String> = file
+            .field_order
+            .clone()
+            .and_then(|order| serde_json::from_value(order).ok())
+            .unwrap_or_default();
+        self.export_rejections(RejectionOwner::File(file.id), &field_order).await
+    }
 
-        let mut grouped_data: HashMap<String, Vec<(excel_data::Model, files::Model)>> = HashMap::new();
+    /// 导出严格模式下整个文件未导入的任务被拒绝的行，没有文件记录，列按名称排列
+    pub async fn export_job_rejections(&self, job_id: i32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.export_rejections(RejectionOwner::Job(job_id), &[]).await
+    }
+
+    async fn export_rejections(
+        &self,
+        owner: RejectionOwner,
+        field_order: &[String],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let rejections = row_validation::list_rejections(&self.db, owner, None, 0).await?;
+        if rejections.is_empty() {
+            return Err("没有被拒绝的数据行".into());
+        }
+        Ok(Self::build_rejection_workbook(field_order, &rejections)?)
+    }
 
-        for ((excel_model, file_model_opt), _score) in scored_results {
-            if let Some(file_model) = file_model_opt {
-                let file_name = file_model.file_name.clone();
-                grouped_data.entry(file_name).or_insert_with(Vec::new).push((excel_model, file_model));
+    /// 生成带错误原因列的拒绝行工作簿
+    pub fn build_rejection_workbook(
+        field_order: &[String],
+        rejections: &[import_rejections::Model],
+    ) -> Result<Vec<u8>, rust_xlsxwriter::XlsxError> {
+        // 按工作表分组，保持拒绝记录中工作表首次出现的顺序
+        let mut sheets: Vec<(&str, Vec<&import_rejections::Model>)> = Vec::new();
+        for rejection in rejections {
+            match sheets.iter_mut().find(|(name, _)| *name == rejection.sheet_name) {
+                Some((_, rows)) => rows.push(rejection),
+                None => sheets.push((&rejection.sheet_name, vec![rejection])),
             }
         }
 
@@ -776,96 +3008,112 @@ impl ExcelProcessor {
             .set_border(rust_xlsxwriter::FormatBorder::Thin);
         let data_format = Format::new()
             .set_border(rust_xlsxwriter::FormatBorder::Thin);
+        let invalid_format = Format::new()
+            .set_background_color("#FFC7CE")
+            .set_font_color("#9C0006")
+            .set_border(rust_xlsxwriter::FormatBorder::Thin);
+        let error_format = Format::new()
+            .set_font_color("#C00000")
+            .set_border(rust_xlsxwriter::FormatBorder::Thin);
 
-        for (file_name, file_data) in grouped_data.iter() {
-            let sheet_name = self.sanitize_sheet_name(file_name);
-            let worksheet = workbook.add_worksheet().set_name(&sheet_name)?;
-
-            if file_data.is_empty() {
-                continue;
-            }
-
-            let mut all_columns = std::collections::BTreeSet::new();
-            all_columns.insert("行号".to_string());
-            all_columns.insert("导入时间".to_string());
-            for (excel_model, _) in file_data {
-                if let Ok(data_obj) = serde_json::from_value::<HashMap<String, Value>>(excel_model.data_json.clone()) {
-                    for key in data_obj.keys() {
-                        all_columns.insert(key.clone());
-                    }
-                }
-            }
+        let mut used_names: Vec<String> = Vec::new();
+        for (sheet_name, rows) in sheets {
+            let name = Self::unique_sheet_name(sheet_name, &mut used_names);
+            let worksheet = workbook.add_worksheet().set_name(&name)?;
 
-            let columns: Vec<String> = all_columns.into_iter().collect();
+            // 文件的字段顺序之外，拒绝行中出现的其他列按名称追加在后面
+            let data_rows: Vec<HashMap<String, Value>> = rows
+                .iter()
+                .map(|rejection| serde_json::from_value(rejection.data_json.clone()).unwrap_or_default())
+                .collect();
+            let mut columns: Vec<String> = field_order.to_vec();
+            let extra: std::collections::BTreeSet<&String> = data_rows
+                .iter()
+                .flat_map(|data| data.keys())
+                .filter(|key| !field_order.contains(key))
+                .collect();
+            columns.extend(extra.into_iter().cloned());
 
+            worksheet.write_string_with_format(0, 0, "行号", &header_format)?;
             for (col_idx, column_name) in columns.iter().enumerate() {
-                worksheet.write_string_with_format(0, col_idx as u16, column_name, &header_format)?;
+                worksheet.write_string_with_format(0, (col_idx + 1) as u16, column_name, &header_format)?;
             }
+            let error_col = (columns.len() + 1) as u16;
+            worksheet.write_string_with_format(0, error_col, "错误原因", &header_format)?;
 
-            for (row_idx, (excel_model, _)) in file_data.iter().enumerate() {
+            for (row_idx, (rejection, data)) in rows.iter().zip(&data_rows).enumerate() {
                 let row = (row_idx + 1) as u32;
+                let reasons: Vec<RowViolation> = serde_json::from_value(rejection.reasons.clone()).unwrap_or_default();
+
+                worksheet.write_number_with_format(row, 0, rejection.row_number as f64, &data_format)?;
                 for (col_idx, column_name) in columns.iter().enumerate() {
-                    let col = col_idx as u16;
-                    let cell_value = match column_name.as_str() {
-                        "行号" => excel_model.row_number.to_string(),
-                        "导入时间" => excel_model.import_time.format("%Y-%m-%d %H:%M:%S").to_string(),
-                        _ => {
-                            if let Ok(data_obj) = serde_json::from_value::<HashMap<String, Value>>(excel_model.data_json.clone()) {
-                                match data_obj.get(column_name) {
-                                    Some(Value::String(s)) => s.clone(),
-                                    Some(Value::Number(n)) => {
-                                        if let Some(f) = n.as_f64() {
-                                            if f.fract() == 0.0 {
-                                                format!("{:.0}", f)
-                                            } else {
-                                                n.to_string()
-                                            }
-                                        } else {
-                                            n.to_string()
-                                        }
-                                    }
-                                    Some(Value::Bool(b)) => b.to_string(),
-                                    Some(Value::Null) => String::new(),
-                                    Some(v) => v.to_string(),
-                                    None => String::new(),
-                                }
-                            } else {
-                                String::new()
-                            }
-                        }
+                    let invalid = reasons
+                        .iter()
+                        .any(|reason| reason.column.trim().to_lowercase() == column_name.trim().to_lowercase());
+                    let cell_value = match data.get(column_name) {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(v) => v.to_string(),
                     };
-                    worksheet.write_string_with_format(row, col, &cell_value, &data_format)?;
+                    let format = if invalid { &invalid_format } else { &data_format };
+                    worksheet.write_string_with_format(row, (col_idx + 1) as u16, &cell_value, format)?;
                 }
+                let message = reasons
+                    .iter()
+                    .map(|reason| reason.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                worksheet.write_string_with_format(row, error_col, &message, &error_format)?;
             }
 
-            for col_idx in 0..columns.len() {
-                worksheet.set_column_width(col_idx as u16, 15.0)?;
+            worksheet.set_column_width(0, 8.0)?;
+            for col_idx in 1..error_col {
+                worksheet.set_column_width(col_idx, 15.0)?;
             }
+            worksheet.set_column_width(error_col, 50.0)?;
+            worksheet.set_freeze_panes(1, 0)?;
         }
 
-        let buffer = workbook.save_to_buffer()?;
-        Ok(buffer)
+        workbook.save_to_buffer()
     }
 
     /// 清理工作表名称，确保符合Excel规范
-    fn sanitize_sheet_name(&self, name: &str) -> String {
+    fn sanitize_sheet_name(name: &str) -> String {
         // Excel工作表名称限制：
-        // - 最大31个字符
+        // - 最大31个字符（按字符计，不是字节）
         // - 不能包含: \ / ? * [ ] :
-        let mut sanitized = name
+        // - 不能以单引号开头或结尾，不能为保留名称History
+        let filtered = name
             .chars()
             .filter(|c| !['\\', '/', '?', '*', '[', ']', ':'].contains(c))
             .collect::<String>();
-            
-        if sanitized.len() > 31 {
-            sanitized.truncate(28);
+        let mut sanitized = filtered.trim().trim_matches('\'').trim().to_string();
+
+        if sanitized.chars().count() > 31 {
+            sanitized = sanitized.chars().take(28).collect::<String>();
             sanitized.push_str("...");
         }
-        
+
         if sanitized.is_empty() {
             sanitized = "Sheet1".to_string();
+        } else if sanitized.eq_ignore_ascii_case("History") {
+            sanitized.push('_');
         }
-        
+
         sanitized
     }
+
+    /// 清理工作表名称，与已使用的名称（不区分大小写）重复时追加序号，并记录到已使用列表
+    pub(crate) fn unique_sheet_name(name: &str, used_names: &mut Vec<String>) -> String {
+        let base_name = Self::sanitize_sheet_name(name);
+        let mut unique = base_name.clone();
+        let mut suffix = 2;
+        while used_names.iter().any(|used| used.to_lowercase() == unique.to_lowercase()) {
+            let tag = format!(" ({})", suffix);
+            unique = format!("{}{}", base_name.chars().take(31 - tag.chars().count()).collect::<String>(), tag);
+            suffix += 1;
+        }
+        used_names.push(unique.clone());
+        unique
+    }
 }
@@ -0,0 +1,496 @@
+// 后台导出任务队列
+// 耗时较长的导出提交为任务，由后台工作线程从export_jobs表中领取执行，导出文件写入导出目录，
+// 在保留期内可以下载，过期后由定时清理删除文件并将任务标记为expired
+use crate::excel_processor_sea::{ExcelProcessor, SearchMode};
+use crate::import_jobs::{
+    spawn_heartbeat, JOB_LEASE_TIMEOUT, JOB_STATUS_COMPLETED, JOB_STATUS_FAILED, JOB_STATUS_QUEUED, JOB_STATUS_RUNNING,
+};
+use crate::models::entity::{export_jobs, workspaces};
+use crate::search_export::{ExportColumns, ExportContext, ExportFormat, ExportInfo};
+use crate::search_query::{parse_range_filters, parse_search_query, SearchCriteria};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, Statement,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+/// 导出文件已过期并被删除
+pub const JOB_STATUS_EXPIRED: &str = "expired";
+
+/// 导出文件默认保留时间（小时）
+pub const DEFAULT_EXPORT_RETENTION_HOURS: i64 = 24;
+
+/// 任务最多执行次数（含重启后的恢复执行）
+const MAX_ATTEMPTS: i32 = 3;
+/// 没有新任务通知时的轮询间隔
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// 清理过期导出文件的间隔
+const EXPIRED_CLEANUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 导出任务的搜索条件，与同步导出接口的查询参数一致
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportRequest {
+    /// 查询语句（q参数）
+    pub query: String,
+    /// 原样保存的 filter[字段] 参数
+    pub filters: Vec<(String, String)>,
+    pub search: SearchMode,
+    /// 写入导出文件的查询说明与范围
+    pub context: ExportContext,
+    /// 选择的列与提交时按请求者语言确定的系统列表头
+    #[serde(default)]
+    pub columns: ExportColumns,
+}
+
+impl ExportRequest {
+    /// 重新解析搜索条件，提交时已校验过，这里只在数据被改动时才会失败
+    pub fn criteria(&self) -> Result<SearchCriteria, String> {
+        let query = if self.query.trim().is_empty() {
+            None
+        } else {
+            Some(parse_search_query(&self.query).map_err(|e| format!("查询语法错误: {}", e))?)
+        };
+        let criteria = SearchCriteria {
+            query,
+            filters: parse_range_filters(&self.filters)?,
+        };
+        if criteria.is_empty() {
+            return Err("查询参数不能为空".to_string());
+        }
+        Ok(criteria)
+    }
+}
+
+/// 导出任务的下载状态
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadState<'a> {
+    /// 可以下载
+    Ready { file_path: &'a str, file_name: &'a str },
+    /// 任务尚未完成或执行失败，附带说明
+    NotReady(String),
+    /// 导出文件已过期或已被清理
+    Expired,
+}
+
+/// 按任务状态与过期时间判断导出文件能否下载
+pub fn download_state(job: &export_jobs::Model, now: chrono::DateTime<chrono::Utc>) -> DownloadState<'_> {
+    match job.status.as_str() {
+        JOB_STATUS_COMPLETED => {}
+        JOB_STATUS_FAILED => return DownloadState::NotReady(format!("导出任务失败: {}", job.error.as_deref().unwrap_or_default())),
+        JOB_STATUS_EXPIRED => return DownloadState::Expired,
+        _ => return DownloadState::NotReady("导出任务尚未完成".to_string()),
+    }
+    if job.expires_at.is_none_or(|at| at <= now) {
+        return DownloadState::Expired;
+    }
+    match (job.file_path.as_deref(), job.file_name.as_deref()) {
+        (Some(file_path), Some(file_name)) => DownloadState::Ready { file_path, file_name },
+        _ => DownloadState::Expired,
+    }
+}
+
+/// 执行完成的导出结果
+struct ExportArtifact {
+    file_path: String,
+    file_name: String,
+    file_size: u64,
+}
+
+#[derive(Clone)]
+pub struct ExportJobQueue {
+    db: DatabaseConnection,
+    export_dir: String,
+    retention: chrono::Duration,
+    notify: Arc<Notify>,
+}
+
+impl ExportJobQueue {
+    pub fn new(db: DatabaseConnection, export_dir: String, retention_hours: i64) -> Self {
+        Self {
+            db,
+            export_dir,
+            retention: chrono::Duration::hours(retention_hours),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// 查询任务当前状态
+    pub async fn find_job(&self, job_id: i32) -> Result<Option<export_jobs::Model>, DbErr> {
+        export_jobs::Entity::find_by_id(job_id).one(&self.db).await
+    }
+
+    /// 用户最近提交的导出任务，最新的在前
+    pub async fn list_recent(&self, user_id: i32, limit: u64) -> Result<Vec<export_jobs::Model>, DbErr> {
+        export_jobs::Entity::find()
+            .filter(export_jobs::Column::CreatedBy.eq(user_id))
+            .order_by_desc(export_jobs::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await
+    }
+
+    /// 登记新的导出任务并唤醒一个工作线程
+    pub async fn enqueue(
+        &self,
+        created_by: i32,
+        workspace_id: Option<i32>,
+        format: ExportFormat,
+        request: &ExportRequest,
+    ) -> Result<export_jobs::Model, DbErr> {
+        let request = serde_json::to_value(request).map_err(|e| DbErr::Custom(format!("序列化导出条件失败: {}", e)))?;
+        let now = chrono::Utc::now();
+        let job = export_jobs::ActiveModel {
+            id: Default::default(),
+            created_by: Set(created_by),
+            workspace_id: Set(workspace_id),
+            format: Set(format.extension().to_string()),
+            request: Set(request),
+            status: Set(JOB_STATUS_QUEUED.to_string()),
+            total_rows: Set(0),
+            file_path: Set(None),
+            file_name: Set(None),
+            file_size: Set(None),
+            error: Set(None),
+            attempts: Set(0),
+            created_at: Set(now),
+            started_at: Set(None),
+            finished_at: Set(None),
+            expires_at: Set(None),
+            updated_at: Set(now),
+        }
+        .insert(&self.db)
+        .await?;
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    /// 处理中断的任务：心跳超过租约时间的执行中任务，未超过重试次数的重新排队，否则标记为失败
+    ///
+    /// 多个实例共用同一数据库时，其他实例仍在执行的任务会持续刷新心跳，不会被重新排队。
+    pub async fn recover_interrupted_jobs(&self) -> Result<(), DbErr> {
+        let lease_secs = JOB_LEASE_TIMEOUT.as_secs_f64();
+        let failed = self
+            .db
+            .execute(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE export_jobs
+                SET status = $1, error = $2, finished_at = NOW(), updated_at = NOW()
+                WHERE status = $3 AND attempts >= $4 AND updated_at < NOW() - make_interval(secs => $5)
+                "#,
+                [
+                    JOB_STATUS_FAILED.into(),
+                    "任务执行中断，且已达到最大重试次数".into(),
+                    JOB_STATUS_RUNNING.into(),
+                    MAX_ATTEMPTS.into(),
+                    lease_secs.into(),
+                ],
+            ))
+            .await?;
+
+        let requeued = self
+            .db
+            .execute(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE export_jobs
+                SET status = $1, updated_at = NOW()
+                WHERE status = $2 AND updated_at < NOW() - make_interval(secs => $3)
+                "#,
+                [JOB_STATUS_QUEUED.into(), JOB_STATUS_RUNNING.into(), lease_secs.into()],
+            ))
+            .await?;
+
+        if requeued.rows_affected() > 0 || failed.rows_affected() > 0 {
+            info!(
+                "中断的导出任务处理完成: 重新排队 {} 个，标记失败 {} 个",
+                requeued.rows_affected(),
+                failed.rows_affected()
+            );
+        }
+        Ok(())
+    }
+
+    /// 删除超过保留期的导出文件，任务记录保留并标记为expired
+    pub async fn remove_expired_exports(&self) -> Result<(), DbErr> {
+        let rows = self
+            .db
+            .query_all(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                WITH expired AS (
+                    SELECT id, file_path FROM export_jobs
+                    WHERE status = $2 AND expires_at <= NOW()
+                    FOR UPDATE
+                )
+                UPDATE export_jobs
+                SET status = $1, file_path = NULL, updated_at = NOW()
+                FROM expired
+                WHERE export_jobs.id = expired.id
+                RETURNING expired.file_path
+                "#,
+                [JOB_STATUS_EXPIRED.into(), JOB_STATUS_COMPLETED.into()],
+            ))
+            .await?;
+
+        for row in &rows {
+            let file_path: Option<String> = row.try_get("", "file_path")?;
+            if let Some(file_path) = file_path {
+                remove_artifact(&file_path).await;
+            }
+        }
+        if !rows.is_empty() {
+            info!("已清理 {} 个过期的导出文件", rows.len());
+        }
+        Ok(())
+    }
+
+    /// 删除工作区的全部导出文件，删除工作区前调用（任务记录随工作区级联删除）
+    pub async fn remove_workspace_exports(&self, workspace_id: i32) -> Result<(), DbErr> {
+        let jobs = export_jobs::Entity::find()
+            .filter(export_jobs::Column::WorkspaceId.eq(workspace_id))
+            .filter(export_jobs::Column::FilePath.is_not_null())
+            .all(&self.db)
+            .await?;
+        for file_path in jobs.into_iter().filter_map(|job| job.file_path) {
+            remove_artifact(&file_path).await;
+        }
+        Ok(())
+    }
+
+    /// 启动指定数量的后台工作线程、中断任务的定时恢复及过期文件的定时清理
+    pub fn start_workers(&self, count: usize) {
+        for worker_id in 0..count.max(1) {
+            let queue = self.clone();
+            tokio::spawn(async move {
+                queue.run_worker(worker_id).await;
+            });
+        }
+
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(JOB_LEASE_TIMEOUT);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = queue.recover_interrupted_jobs().await {
+                    warn!("恢复中断的导出任务失败: {}", e);
+                }
+            }
+        });
+
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRED_CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = queue.remove_expired_exports().await {
+                    warn!("清理过期导出文件失败: {}", e);
+                }
+            }
+        });
+        info!(
+            "导出任务工作线程已启动，数量: {}，导出文件保留 {} 小时",
+            count.max(1),
+            self.retention.num_hours()
+        );
+    }
+
+    async fn run_worker(self, worker_id: usize) {
+        loop {
+            match self.claim_next_job().await {
+                Ok(Some(job)) => {
+                    info!("工作线程 {} 开始执行导出任务 {}", worker_id, job.id);
+                    self.run_job(job).await;
+                }
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.notify.notified() => {}
+                        _ = tokio::time::sleep(IDLE_POLL_INTERVAL) => {}
+                    }
+                }
+                Err(e) => {
+                    error!("工作线程 {} 领取导出任务失败: {}", worker_id, e);
+                    tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// 原子地领取一个排队中的任务，多个工作线程（及多个实例）之间不会重复领取；
+    /// 返回的attempts标识本次执行，执行期间的心跳与结果只写入仍由本次执行持有的任务
+    pub async fn claim_next_job(&self) -> Result<Option<export_jobs::Model>, DbErr> {
+        export_jobs::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE export_jobs
+                SET status = $1, attempts = attempts + 1, error = NULL,
+                    started_at = NOW(), finished_at = NULL, updated_at = NOW()
+                WHERE id = (
+                    SELECT id FROM export_jobs
+                    WHERE status = $2
+                    ORDER BY id
+                    FOR UPDATE SKIP LOCKED
+                    LIMIT 1
+                )
+                RETURNING *
+                "#,
+                [JOB_STATUS_RUNNING.into(), JOB_STATUS_QUEUED.into()],
+            ))
+            .one(&self.db)
+            .await
+    }
+
+    async fn run_job(&self, job: export_jobs::Model) {
+        let job_id = job.id;
+        let heartbeat = spawn_heartbeat(self.db.clone(), "export_jobs", job_id, job.attempts);
+        let result = self.export(&job).await;
+        heartbeat.abort();
+
+        let now = chrono::Utc::now();
+        let finished = match &result {
+            Ok(artifact) => {
+                info!("导出任务 {} 执行完成: {}（{} 字节）", job_id, artifact.file_name, artifact.file_size);
+                export_jobs::ActiveModel {
+                    status: Set(JOB_STATUS_COMPLETED.to_string()),
+                    file_path: Set(Some(artifact.file_path.clone())),
+                    file_name: Set(Some(artifact.file_name.clone())),
+                    file_size: Set(Some(artifact.file_size as i64)),
+                    expires_at: Set(Some(now + self.retention)),
+                    finished_at: Set(Some(now)),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+            }
+            Err(e) => {
+                error!("导出任务 {} 执行失败: {}", job_id, e);
+                export_jobs::ActiveModel {
+                    status: Set(JOB_STATUS_FAILED.to_string()),
+                    error: Set(Some(e.clone())),
+                    finished_at: Set(Some(now)),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+            }
+        };
+        // 心跳中断后任务可能已被重新领取，只更新仍由本次执行持有的任务
+        let updated = export_jobs::Entity::update_many()
+            .set(finished)
+            .filter(export_jobs::Column::Id.eq(job_id))
+            .filter(export_jobs::Column::Status.eq(JOB_STATUS_RUNNING))
+            .filter(export_jobs::Column::Attempts.eq(job.attempts))
+            .exec(&self.db)
+            .await;
+        match updated {
+            Ok(updated) if updated.rows_affected > 0 => {}
+            // 任务已被重新领取、或记录已不存在（如工作区已删除）时，本次生成的文件没有人能下载
+            Ok(_) => {
+                warn!("导出任务 {} 已不再由本次执行持有，丢弃本次结果", job_id);
+                if let Ok(artifact) = &result {
+                    remove_artifact(&artifact.file_path).await;
+                }
+            }
+            Err(e) => {
+                error!("更新导出任务 {} 状态失败: {}", job_id, e);
+                if let Ok(artifact) = &result {
+                    remove_artifact(&artifact.file_path).await;
+                }
+            }
+        }
+    }
+
+    /// 按任务保存的条件重新规划并写出导出文件
+    async fn export(&self, job: &export_jobs::Model) -> Result<ExportArtifact, String> {
+        let request: ExportRequest =
+            serde_json::from_value(job.request.clone()).map_err(|e| format!("导出条件无法解析: {}", e))?;
+        let format =
+            ExportFormat::from_extension(&job.format).ok_or_else(|| format!("不支持的导出格式: {}", job.format))?;
+        let criteria = request.criteria()?;
+
+        let processor = ExcelProcessor::new(self.db.clone());
+        let plan = match job.workspace_id {
+            Some(workspace_id) => {
+                // 提交后工作区可能被设为私有，执行时再次检查权限
+                let workspace = workspaces::Entity::find_by_id(workspace_id)
+                    .one(&self.db)
+                    .await
+                    .map_err(|e| format!("查询workspace失败: {}", e))?
+                    .ok_or("workspace不存在")?;
+                if !workspace.is_public && workspace.owner_id != job.created_by {
+                    return Err("无权限访问该workspace".to_string());
+                }
+                processor.plan_workspace_export(workspace_id, &criteria, request.search).await
+            }
+            None => processor.plan_public_export(&criteria, request.search).await,
+        };
+        let plan = plan
+            .map_err(|e| e.to_string())?
+            .ok_or("没有找到匹配的数据")?;
+
+        let total_rows = export_jobs::ActiveModel {
+            id: Set(job.id),
+            total_rows: Set(plan.total_rows() as i64),
+            updated_at: Set(chrono::Utc::now()),
+            ..Default::default()
+        };
+        if let Err(e) = total_rows.update(&self.db).await {
+            warn!("更新导出任务 {} 行数失败: {}", job.id, e);
+        }
+
+        let info = ExportInfo {
+            context: request.context,
+            mode: request.search,
+            exported_at: chrono::Utc::now(),
+            columns: request.columns,
+        };
+        let exporter = format.exporter(&plan.sheets, &info).map_err(|e| e.to_string())?;
+        let file_path = PathBuf::from(&self.export_dir)
+            .join(format!("export_{}_{}.{}", job.id, job.attempts, format.extension()))
+            .to_string_lossy()
+            .into_owned();
+
+        // 文件名包含执行次数，重新领取的执行不会与仍在运行的上一次执行写同一个文件；
+        // 之前中断的执行留下的文件不会再被使用，先删除。xlsx先写入临时文件，生成后再复制到导出目录
+        for attempt in 1..job.attempts {
+            let stale = PathBuf::from(&self.export_dir).join(format!("export_{}_{}.{}", job.id, attempt, format.extension()));
+            remove_artifact(&stale.to_string_lossy()).await;
+        }
+        let written = async {
+            let mut file = tokio::fs::File::create(&file_path).await?;
+            if let Some(workbook) = processor.write_export(&plan, exporter, &mut file).await? {
+                tokio::io::copy(&mut tokio::fs::File::from_std(workbook), &mut file).await?;
+            }
+            file.flush().await?;
+            Ok::<u64, crate::search_export::ExportError>(file.metadata().await?.len())
+        }
+        .await;
+        let file_size = match written {
+            Ok(file_size) => file_size,
+            Err(e) => {
+                remove_artifact(&file_path).await;
+                return Err(e.to_string());
+            }
+        };
+
+        Ok(ExportArtifact {
+            file_path,
+            file_name: format!("搜索结果导出_{}.{}", info.exported_at.format("%Y%m%d_%H%M%S"), format.extension()),
+            file_size,
+        })
+    }
+}
+
+async fn remove_artifact(file_path: &str) {
+    if let Err(e) = tokio::fs::remove_file(file_path).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("删除导出文件失败 {}: {}", file_path, e);
+        }
+    }
+}
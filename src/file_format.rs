@@ -0,0 +1,113 @@
+// 上传文件格式识别模块
+// 根据文件内容（魔数及压缩包结构）判断真实格式，文件名只用于允许单列的CSV/TSV
+use crate::csv_reader;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// OLE2复合文档魔数（.xls）
+const OLE2_MAGIC: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+/// ZIP本地文件头魔数（.xlsx/.xlsb/.ods）
+const ZIP_MAGIC: [u8; 4] = [0x50, 0x4B, 0x03, 0x04];
+/// OpenDocument电子表格的mimetype声明
+const ODS_MIMETYPE: &[u8] = b"application/vnd.oasis.opendocument.spreadsheet";
+/// 文本格式探测读取的字节数
+const TEXT_SNIFF_BYTES: usize = 8192;
+
+/// 支持导入的文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Xlsx,
+    Xlsb,
+    Xls,
+    Ods,
+    Csv,
+    Tsv,
+}
+
+impl FileFormat {
+    /// 保存文件时使用的扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Xlsx => "xlsx",
+            FileFormat::Xlsb => "xlsb",
+            FileFormat::Xls => "xls",
+            FileFormat::Ods => "ods",
+            FileFormat::Csv => "csv",
+            FileFormat::Tsv => "tsv",
+        }
+    }
+}
+
+/// 根据文件内容识别格式，无法识别时返回None；`file_name` 为上传时的原始文件名
+pub fn detect_file_format(file_path: &Path, file_name: &str) -> std::io::Result<Option<FileFormat>> {
+    let mut head = Vec::with_capacity(TEXT_SNIFF_BYTES);
+    File::open(file_path)?
+        .take(TEXT_SNIFF_BYTES as u64)
+        .read_to_end(&mut head)?;
+
+    if head.is_empty() {
+        return Ok(None);
+    }
+    if head.starts_with(&OLE2_MAGIC) {
+        return Ok(Some(FileFormat::Xls));
+    }
+    if head.starts_with(&ZIP_MAGIC) {
+        return Ok(detect_zip_format(file_path));
+    }
+
+    Ok(detect_text_format(&head, file_name))
+}
+
+/// 检查ZIP压缩包内的关键条目，区分ODS、XLSB和XLSX
+fn detect_zip_format(file_path: &Path) -> Option<FileFormat> {
+    let file = File::open(file_path).ok()?;
+    let mut archive = zip::ZipArchive::new(file).ok()?;
+
+    if let Ok(mut mimetype) = archive.by_name("mimetype") {
+        let mut content = Vec::new();
+        if mimetype.read_to_end(&mut content).is_ok() && content.trim_ascii() == ODS_MIMETYPE {
+            return Some(FileFormat::Ods);
+        }
+    }
+
+    let names: Vec<String> = archive.file_names().map(str::to_ascii_lowercase).collect();
+    if names.iter().any(|name| name == "xl/workbook.bin") {
+        Some(FileFormat::Xlsb)
+    } else if names.iter().any(|name| name == "xl/workbook.xml") {
+        Some(FileFormat::Xlsx)
+    } else {
+        None
+    }
+}
+
+/// 判断内容是否为分隔文本，并根据分隔符区分CSV和TSV
+///
+/// 内容须能解析为一致的两列以上；HTML、JSON、普通文本等识别不出分隔符，
+/// 只有原始文件名为 .csv/.tsv 时才作为单列表格接受
+fn detect_text_format(head: &[u8], file_name: &str) -> Option<FileFormat> {
+    let (text, _) = csv_reader::decode_text(head);
+
+    // 文本表格中不应出现除制表符、换行外的控制字符（截断处可能产生替换字符，忽略即可）
+    let total = text.chars().count();
+    let binary = text
+        .chars()
+        .filter(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n'))
+        .count();
+    if total == 0 || binary * 100 > total {
+        return None;
+    }
+
+    match csv_reader::sniff_dialect(&text) {
+        Some(dialect) if dialect.delimiter == b'\t' => Some(FileFormat::Tsv),
+        Some(_) => Some(FileFormat::Csv),
+        None => {
+            let extension = Path::new(file_name).extension()?.to_str()?.to_ascii_lowercase();
+            match extension.as_str() {
+                "csv" => Some(FileFormat::Csv),
+                "tsv" => Some(FileFormat::Tsv),
+                _ => None,
+            }
+        }
+    }
+}
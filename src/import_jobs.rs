@@ -0,0 +1,628 @@
+// 后台导入任务队列
+// 上传请求只负责保存文件并登记任务，由后台工作线程从import_jobs表中领取任务执行导入
+use crate::excel_processor_sea::{
+    ExcelProcessor, ImportEvent, ImportOptions, ImportProgress, ImportSummary, StrictModeRejection,
+};
+use crate::models::entity::{files, import_jobs};
+use crate::row_validation::{self, RejectionOwner};
+use serde::Serialize;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, Set,
+    Statement, TransactionTrait, UpdateMany,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, Notify};
+use tracing::{error, info, warn};
+
+pub const JOB_STATUS_QUEUED: &str = "queued";
+pub const JOB_STATUS_RUNNING: &str = "running";
+pub const JOB_STATUS_COMPLETED: &str = "completed";
+pub const JOB_STATUS_FAILED: &str = "failed";
+/// 试导入登记的任务，用确认令牌确认后才进入队列
+pub const JOB_STATUS_AWAITING_CONFIRMATION: &str = "awaiting_confirmation";
+
+/// 试导入确认令牌的有效期（小时），过期的待确认任务及其文件会被清理
+pub const CONFIRM_TOKEN_TTL_HOURS: i64 = 24;
+
+/// 任务最多执行次数（含重启后的恢复执行）
+const MAX_ATTEMPTS: i32 = 3;
+/// 没有新任务通知时的轮询间隔
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// 进度事件广播缓冲区大小，订阅端落后太多时会丢弃旧事件
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 执行中的任务刷新心跳（updated_at）的间隔
+pub const JOB_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// 执行中的任务超过该时间没有心跳时视为所在实例已中断，由任一实例重新排队
+pub const JOB_LEASE_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// 在后台定时刷新执行中任务的updated_at作为租约心跳，任务结束后由调用方abort
+///
+/// 只刷新仍由本次执行持有的任务（状态为running且执行次数未变），任务被其他实例重新领取后心跳不再生效。
+pub(crate) fn spawn_heartbeat(
+    db: DatabaseConnection,
+    table: &'static str,
+    job_id: i32,
+    attempts: i32,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(JOB_HEARTBEAT_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let result = db
+                .execute(Statement::from_sql_and_values(
+                    sea_orm::DatabaseBackend::Postgres,
+                    format!("UPDATE {} SET updated_at = NOW() WHERE id = $1 AND status = $2 AND attempts = $3", table),
+                    [job_id.into(), JOB_STATUS_RUNNING.into(), attempts.into()],
+                ))
+                .await;
+            match result {
+                Ok(updated) if updated.rows_affected() == 0 => {
+                    warn!("任务 {}({}) 已不再由本次执行持有，停止心跳", job_id, table);
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("刷新任务 {}({}) 心跳失败: {}", job_id, table, e),
+            }
+        }
+    })
+}
+
+/// 只更新仍由本次执行持有的导入任务（状态为running且执行次数未变）
+fn owned_job(update: UpdateMany<import_jobs::Entity>, job_id: i32, attempts: i32) -> UpdateMany<import_jobs::Entity> {
+    update
+        .filter(import_jobs::Column::Id.eq(job_id))
+        .filter(import_jobs::Column::Status.eq(JOB_STATUS_RUNNING))
+        .filter(import_jobs::Column::Attempts.eq(attempts))
+}
+
+/// 推送给订阅端的导入进度事件
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub job_id: i32,
+    pub workspace_id: i32,
+    pub file_name: String,
+    #[serde(flatten)]
+    pub event: ImportEvent,
+}
+
+impl JobEvent {
+    /// SSE事件名称，与序列化后的event字段一致
+    pub fn name(&self) -> &'static str {
+        match self.event {
+            ImportEvent::SheetStarted { .. } => "sheet_started",
+            ImportEvent::RowsInserted { .. } => "rows_inserted",
+            ImportEvent::SheetFinished { .. } => "sheet_finished",
+            ImportEvent::FileDone { .. } => "file_done",
+            ImportEvent::Failed { .. } => "failed",
+        }
+    }
+
+    /// 是否为任务的最后一个事件
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.event, ImportEvent::FileDone { .. } | ImportEvent::Failed { .. })
+    }
+}
+
+/// 与上传文件内容相同（MD5一致）的已导入文件或尚未完成的导入任务
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateUpload {
+    /// 已导入的文件ID，尚未完成的任务为None
+    pub file_id: Option<i32>,
+    /// 排队或执行中的任务ID，已导入的文件为None
+    pub job_id: Option<i32>,
+    pub file_name: String,
+    /// 已导入的文件为 imported，否则为任务状态
+    pub status: String,
+    /// 文件最近导入或任务登记的时间
+    pub time: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Clone)]
+pub struct ImportJobQueue {
+    db: DatabaseConnection,
+    notify: Arc<Notify>,
+    events: broadcast::Sender<JobEvent>,
+    progress_interval: usize,
+}
+
+impl ImportJobQueue {
+    pub fn new(db: DatabaseConnection, progress_interval: usize) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            db,
+            notify: Arc::new(Notify::new()),
+            events,
+            progress_interval,
+        }
+    }
+
+    /// 订阅所有导入任务的进度事件，由订阅方按任务或工作区过滤
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    /// 查询任务当前状态
+    pub async fn find_job(&self, job_id: i32) -> Result<Option<import_jobs::Model>, DbErr> {
+        import_jobs::Entity::find_by_id(job_id).one(&self.db).await
+    }
+
+    /// 查找工作区中内容相同的已导入文件（最近导入的在前）和排队或执行中的任务，
+    /// 同一次上传的多个相同文件在前一个登记任务后即可检测到
+    ///
+    /// 文件记录在导入事务中创建，导入失败（包括严格模式下整个文件被拒绝）时不会留下文件记录；
+    /// 仍只把存在已完成的导入任务，或没有任何导入任务记录（导入任务出现之前导入）的文件算作已导入。
+    pub async fn find_duplicates(&self, workspace_id: i32, file_hash: &str) -> Result<Vec<DuplicateUpload>, DbErr> {
+        let imported = files::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                SELECT f.* FROM files f
+                WHERE f.workspace_id = $1 AND f.file_hash = $2
+                  AND (EXISTS (SELECT 1 FROM import_jobs j WHERE j.file_id = f.id AND j.status = $3)
+                       OR NOT EXISTS (SELECT 1 FROM import_jobs j WHERE j.file_id = f.id))
+                ORDER BY f.updated_at DESC
+                "#,
+                [workspace_id.into(), file_hash.into(), JOB_STATUS_COMPLETED.into()],
+            ))
+            .all(&self.db)
+            .await?;
+        let pending = import_jobs::Entity::find()
+            .filter(import_jobs::Column::WorkspaceId.eq(workspace_id))
+            .filter(import_jobs::Column::FileHash.eq(file_hash))
+            .filter(import_jobs::Column::Status.is_in([JOB_STATUS_QUEUED, JOB_STATUS_RUNNING]))
+            .order_by_asc(import_jobs::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        let imported = imported.into_iter().map(|file| DuplicateUpload {
+            file_id: Some(file.id),
+            job_id: None,
+            file_name: file.file_name,
+            status: "imported".to_string(),
+            time: file.updated_at,
+        });
+        let pending = pending.into_iter().map(|job| DuplicateUpload {
+            file_id: None,
+            job_id: Some(job.id),
+            file_name: job.file_name,
+            status: job.status,
+            time: job.created_at,
+        });
+        Ok(imported.chain(pending).collect())
+    }
+
+    /// 登记新的导入任务并唤醒一个工作线程
+    pub async fn enqueue(
+        &self,
+        workspace_id: i32,
+        created_by: i32,
+        file_path: &str,
+        file_name: &str,
+        file_hash: &str,
+        options: &ImportOptions,
+    ) -> Result<import_jobs::Model, DbErr> {
+        let job = self
+            .insert_job(workspace_id, created_by, file_path, file_name, file_hash, options, None)
+            .await?;
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    /// 登记试导入的文件，生成确认令牌，确认前不会被工作线程执行
+    pub async fn register_dry_run(
+        &self,
+        workspace_id: i32,
+        created_by: i32,
+        file_path: &str,
+        file_name: &str,
+        file_hash: &str,
+        options: &ImportOptions,
+    ) -> Result<import_jobs::Model, DbErr> {
+        let token = uuid::Uuid::new_v4().to_string();
+        self.insert_job(workspace_id, created_by, file_path, file_name, file_hash, options, Some(token))
+            .await
+    }
+
+    /// 确认令牌的过期时间
+    pub fn confirm_token_expires_at(job: &import_jobs::Model) -> chrono::DateTime<chrono::Utc> {
+        job.created_at + chrono::Duration::hours(CONFIRM_TOKEN_TTL_HOURS)
+    }
+
+    /// 用确认令牌将试导入的任务加入队列；令牌不存在、已使用、已过期或不属于该用户和工作区时返回None
+    pub async fn confirm_dry_run(
+        &self,
+        token: &str,
+        workspace_id: i32,
+        user_id: i32,
+    ) -> Result<Option<import_jobs::Model>, DbErr> {
+        let job = import_jobs::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE import_jobs
+                SET status = $1, confirm_token = NULL, updated_at = NOW()
+                WHERE confirm_token = $2 AND status = $3 AND workspace_id = $4 AND created_by = $5
+                  AND created_at > NOW() - make_interval(hours => $6)
+                RETURNING *
+                "#,
+                [
+                    JOB_STATUS_QUEUED.into(),
+                    token.into(),
+                    JOB_STATUS_AWAITING_CONFIRMATION.into(),
+                    workspace_id.into(),
+                    user_id.into(),
+                    (CONFIRM_TOKEN_TTL_HOURS as i32).into(),
+                ],
+            ))
+            .one(&self.db)
+            .await?;
+
+        if job.is_some() {
+            self.notify.notify_one();
+        }
+        Ok(job)
+    }
+
+    /// 删除尚未确认的试导入任务，上传文件由调用方处理
+    pub async fn remove_dry_runs(&self, job_ids: &[i32]) -> Result<(), DbErr> {
+        if job_ids.is_empty() {
+            return Ok(());
+        }
+        import_jobs::Entity::delete_many()
+            .filter(import_jobs::Column::Id.is_in(job_ids.iter().copied()))
+            .filter(import_jobs::Column::Status.eq(JOB_STATUS_AWAITING_CONFIRMATION))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// 删除过期未确认的试导入任务及其上传文件
+    pub async fn remove_expired_dry_runs(&self) -> Result<(), DbErr> {
+        let rows = self
+            .db
+            .query_all(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                DELETE FROM import_jobs
+                WHERE status = $1 AND created_at <= NOW() - make_interval(hours => $2)
+                RETURNING file_path
+                "#,
+                [
+                    JOB_STATUS_AWAITING_CONFIRMATION.into(),
+                    (CONFIRM_TOKEN_TTL_HOURS as i32).into(),
+                ],
+            ))
+            .await?;
+
+        for row in &rows {
+            let file_path: String = row.try_get("", "file_path")?;
+            if let Err(e) = tokio::fs::remove_file(&file_path).await {
+                warn!("删除过期试导入文件失败 {}: {}", file_path, e);
+            }
+        }
+        if !rows.is_empty() {
+            info!("已清理 {} 个过期未确认的试导入", rows.len());
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_job(
+        &self,
+        workspace_id: i32,
+        created_by: i32,
+        file_path: &str,
+        file_name: &str,
+        file_hash: &str,
+        options: &ImportOptions,
+        confirm_token: Option<String>,
+    ) -> Result<import_jobs::Model, DbErr> {
+        let options = serde_json::to_value(options).map_err(|e| DbErr::Custom(format!("序列化导入选项失败: {}", e)))?;
+        let status = if confirm_token.is_some() {
+            JOB_STATUS_AWAITING_CONFIRMATION
+        } else {
+            JOB_STATUS_QUEUED
+        };
+        let now = chrono::Utc::now();
+        import_jobs::ActiveModel {
+            id: Default::default(),
+            workspace_id: Set(workspace_id),
+            created_by: Set(Some(created_by)),
+            file_path: Set(file_path.to_string()),
+            file_name: Set(file_name.to_string()),
+            file_hash: Set(Some(file_hash.to_string())),
+            options: Set(Some(options)),
+            confirm_token: Set(confirm_token),
+            status: Set(status.to_string()),
+            rows_processed: Set(0),
+            rows_inserted: Set(0),
+            rows_updated: Set(0),
+            rows_unchanged: Set(0),
+            rows_rejected: Set(0),
+            file_id: Set(None),
+            current_sheet: Set(None),
+            error: Set(None),
+            attempts: Set(0),
+            created_at: Set(now),
+            started_at: Set(None),
+            finished_at: Set(None),
+            updated_at: Set(now),
+        }
+        .insert(&self.db)
+        .await
+    }
+
+    /// 处理中断的任务：心跳超过租约时间的执行中任务，未超过重试次数的重新排队，否则标记为失败
+    ///
+    /// 执行中的任务由心跳与进度更新刷新updated_at，多个实例共用同一数据库时不会重新排队其他实例仍在执行的任务。
+    pub async fn recover_interrupted_jobs(&self) -> Result<(), DbErr> {
+        let lease_secs = JOB_LEASE_TIMEOUT.as_secs_f64();
+        let failed = self
+            .db
+            .execute(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE import_jobs
+                SET status = $1, error = $2, finished_at = NOW(), updated_at = NOW()
+                WHERE status = $3 AND attempts >= $4 AND updated_at < NOW() - make_interval(secs => $5)
+                "#,
+                [
+                    JOB_STATUS_FAILED.into(),
+                    "任务执行中断，且已达到最大重试次数".into(),
+                    JOB_STATUS_RUNNING.into(),
+                    MAX_ATTEMPTS.into(),
+                    lease_secs.into(),
+                ],
+            ))
+            .await?;
+
+        let requeued = self
+            .db
+            .execute(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE import_jobs
+                SET status = $1, current_sheet = NULL, rows_processed = 0, updated_at = NOW()
+                WHERE status = $2 AND updated_at < NOW() - make_interval(secs => $3)
+                "#,
+                [JOB_STATUS_QUEUED.into(), JOB_STATUS_RUNNING.into(), lease_secs.into()],
+            ))
+            .await?;
+
+        if requeued.rows_affected() > 0 || failed.rows_affected() > 0 {
+            info!(
+                "中断的导入任务处理完成: 重新排队 {} 个，标记失败 {} 个",
+                requeued.rows_affected(),
+                failed.rows_affected()
+            );
+        }
+        Ok(())
+    }
+
+    /// 启动指定数量的后台工作线程
+    pub fn start_workers(&self, count: usize) {
+        for worker_id in 0..count.max(1) {
+            let queue = self.clone();
+            tokio::spawn(async move {
+                queue.run_worker(worker_id).await;
+            });
+        }
+
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(JOB_LEASE_TIMEOUT);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = queue.recover_interrupted_jobs().await {
+                    warn!("恢复中断的导入任务失败: {}", e);
+                }
+            }
+        });
+        info!("导入任务工作线程已启动，数量: {}", count.max(1));
+    }
+
+    async fn run_worker(self, worker_id: usize) {
+        loop {
+            match self.claim_next_job().await {
+                Ok(Some(job)) => {
+                    info!("工作线程 {} 开始执行导入任务 {}: {}", worker_id, job.id, job.file_name);
+                    self.run_job(job).await;
+                }
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.notify.notified() => {}
+                        _ = tokio::time::sleep(IDLE_POLL_INTERVAL) => {}
+                    }
+                }
+                Err(e) => {
+                    error!("工作线程 {} 领取导入任务失败: {}", worker_id, e);
+                    tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// 原子地领取一个排队中的任务，多个工作线程之间不会重复领取
+    pub async fn claim_next_job(&self) -> Result<Option<import_jobs::Model>, DbErr> {
+        import_jobs::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                UPDATE import_jobs
+                SET status = $1, attempts = attempts + 1, error = NULL,
+                    started_at = NOW(), finished_at = NULL, updated_at = NOW()
+                WHERE id = (
+                    SELECT id FROM import_jobs
+                    WHERE status = $2
+                    ORDER BY id
+                    FOR UPDATE SKIP LOCKED
+                    LIMIT 1
+                )
+                RETURNING *
+                "#,
+                [JOB_STATUS_RUNNING.into(), JOB_STATUS_QUEUED.into()],
+            ))
+            .one(&self.db)
+            .await
+    }
+
+    async fn run_job(&self, job: import_jobs::Model) {
+        let job_id = job.id;
+        let attempts = job.attempts;
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ImportEvent>();
+
+        // 将进度事件写回任务记录并广播给订阅端；结束事件留到任务状态落库后再广播
+        let db = self.db.clone();
+        let events = self.events.clone();
+        let workspace_id = job.workspace_id;
+        let file_name = job.file_name.clone();
+        let reporter = tokio::spawn(async move {
+            let mut terminal = None;
+            while let Some(event) = receiver.recv().await {
+                let update = match &event {
+                    ImportEvent::SheetStarted { sheet, .. } => Some(import_jobs::ActiveModel {
+                        current_sheet: Set(Some(sheet.clone())),
+                        updated_at: Set(chrono::Utc::now()),
+                        ..Default::default()
+                    }),
+                    ImportEvent::RowsInserted { sheet, file_rows, .. } => Some(import_jobs::ActiveModel {
+                        current_sheet: Set(Some(sheet.clone())),
+                        rows_processed: Set(*file_rows as i64),
+                        updated_at: Set(chrono::Utc::now()),
+                        ..Default::default()
+                    }),
+                    _ => None,
+                };
+                if let Some(update) = update {
+                    if let Err(e) = owned_job(import_jobs::Entity::update_many().set(update), job_id, attempts)
+                        .exec(&db)
+                        .await
+                    {
+                        warn!("更新导入任务 {} 进度失败: {}", job_id, e);
+                    }
+                }
+
+                let job_event = JobEvent {
+                    job_id,
+                    workspace_id,
+                    file_name: file_name.clone(),
+                    event,
+                };
+                if job_event.is_terminal() {
+                    terminal = Some(job_event);
+                } else {
+                    // 没有订阅端时发送失败，忽略即可
+                    let _ = events.send(job_event);
+                }
+            }
+            terminal
+        });
+
+        // 旧任务没有导入选项，按默认选项导入
+        let options = match job.options.clone().map(serde_json::from_value::<ImportOptions>) {
+            Some(Ok(options)) => options,
+            Some(Err(e)) => {
+                warn!("导入任务 {} 的导入选项无法解析，使用默认选项: {}", job_id, e);
+                ImportOptions::default()
+            }
+            None => ImportOptions::default(),
+        };
+
+        let processor = ExcelProcessor::new(self.db.clone());
+        let progress = ImportProgress::new(sender, self.progress_interval);
+        // 解析大文件时可能长时间没有进度事件，另行刷新心跳
+        let heartbeat = spawn_heartbeat(self.db.clone(), "import_jobs", job_id, attempts);
+        let result = processor
+            .import_uploaded_file(
+                job.workspace_id,
+                &job.file_path,
+                job.created_by,
+                &job.file_name,
+                job.file_hash.as_deref(),
+                &options,
+                Some(&progress),
+            )
+            .await;
+        heartbeat.abort();
+        drop(progress);
+        let terminal = reporter.await.ok().flatten();
+
+        let now = chrono::Utc::now();
+        let (status, error_message) = match &result {
+            Ok(_) => {
+                info!("导入任务 {} 执行完成", job_id);
+                (JOB_STATUS_COMPLETED, None)
+            }
+            Err(e) => {
+                error!("导入任务 {} 执行失败: {}", job_id, e);
+                (JOB_STATUS_FAILED, Some(e.to_string()))
+            }
+        };
+
+        // 记录导入的文件与各类行数；严格模式下整个文件未导入时没有文件记录，拒绝的行按任务保存
+        let strict_rejections = result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<StrictModeRejection>())
+            .map(|rejection| rejection.rejections.as_slice());
+        let summary = match &result {
+            Ok(summary) => summary.clone(),
+            Err(_) => ImportSummary {
+                rejected_rows: strict_rejections.map_or(0, <[_]>::len),
+                ..Default::default()
+            },
+        };
+
+        let finished = import_jobs::ActiveModel {
+            status: Set(status.to_string()),
+            error: Set(error_message.clone()),
+            file_id: Set(summary.file_id),
+            rows_inserted: Set(summary.inserted_rows as i64),
+            rows_updated: Set(summary.updated_rows as i64),
+            rows_unchanged: Set(summary.unchanged_rows as i64),
+            rows_rejected: Set(summary.rejected_rows as i64),
+            finished_at: Set(Some(now)),
+            updated_at: Set(now),
+            ..Default::default()
+        };
+        // 心跳中断后任务可能已被重新排队或领取，只更新仍由本次执行持有的任务；拒绝记录与任务状态一起提交
+        let recorded = async {
+            let txn = self.db.begin().await?;
+            let updated = owned_job(import_jobs::Entity::update_many().set(finished), job_id, attempts)
+                .exec(&txn)
+                .await?;
+            if let (true, Some(rejections)) = (updated.rows_affected > 0, strict_rejections) {
+                row_validation::save_rejections(&txn, Some(job.workspace_id), RejectionOwner::Job(job_id), rejections).await?;
+            }
+            txn.commit().await?;
+            Ok::<_, DbErr>(updated.rows_affected)
+        };
+        match recorded.await {
+            Ok(0) => {
+                warn!("导入任务 {} 已不再由本次执行持有，不记录本次结果", job_id);
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => error!("更新导入任务 {} 状态失败: {}", job_id, e),
+        }
+
+        // 导入过程未上报结束事件时按任务结果补发，保证订阅端总能收到结束事件
+        let terminal = terminal.unwrap_or_else(|| JobEvent {
+            job_id,
+            workspace_id: job.workspace_id,
+            file_name: job.file_name.clone(),
+            event: match error_message {
+                Some(error) => ImportEvent::Failed { error },
+                None => ImportEvent::FileDone {
+                    total_rows: summary.inserted_rows + summary.updated_rows + summary.unchanged_rows,
+                    summary,
+                },
+            },
+        });
+        let _ = self.events.send(terminal);
+    }
+}
@@ -1,5 +1,16 @@
 pub mod database_sea;
 pub mod excel_processor_sea;
+pub mod column_profile;
+pub mod csv_reader;
+pub mod export_jobs;
+pub mod file_format;
+pub mod import_jobs;
+pub mod row_validation;
+pub mod search_export;
+pub mod search_query;
+pub mod sheet_header;
+pub mod text_normalize;
+pub mod upsert_key;
 pub mod web_server;
 pub mod models;
 pub mod utils;
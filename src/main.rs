@@ -1,11 +1,5 @@
-mod database_sea;
-mod excel_processor_sea;
-mod web_server;
-mod models;
-mod utils;
-mod i18n_manager;
-
-use database_sea::connect_database;
+use excel_data_hub::database_sea::connect_database;
+use excel_data_hub::web_server;
 use std::env;
 use tracing::{error, info};
 
@@ -16,6 +16,9 @@ pub struct Model {
     pub sheet_name: String,
     #[sea_orm(column_type = "Json")]
     pub data_json: JsonValue,
+    /// 非文本单元格的原生类型值，格式为 {"字段": {"type": ..., "value": ...}}
+    #[sea_orm(column_type = "Json")]
+    pub typed_json: JsonValue,
     pub search_text: String,
 }
 
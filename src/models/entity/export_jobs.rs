@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 后台导出任务，完成后生成的文件在保留期内可下载
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "export_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub created_by: i32,
+    /// 导出的工作区，导出公开数据时为None
+    pub workspace_id: Option<i32>,
+    /// 导出格式：xlsx、csv、json、ndjson
+    pub format: String,
+    /// 提交时的搜索条件（ExportRequest的JSON）
+    #[sea_orm(column_type = "Json")]
+    pub request: Json,
+    pub status: String,
+    /// 开始导出时统计的匹配行数
+    pub total_rows: i64,
+    /// 导出文件在服务器上的路径，过期清理后清空
+    pub file_path: Option<String>,
+    /// 下载时使用的文件名
+    pub file_name: Option<String>,
+    pub file_size: Option<i64>,
+    pub error: Option<String>,
+    pub attempts: i32,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// 导出文件的过期时间，导出完成时按保留期计算
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::CreatedBy",
+        to = "super::users::Column::Id"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::workspaces::Entity",
+        from = "Column::WorkspaceId",
+        to = "super::workspaces::Column::Id"
+    )]
+    Workspace,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::workspaces::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Workspace.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -16,6 +16,9 @@ pub struct Model {
     pub file_hash: String,
     #[sea_orm(column_type = "Json", nullable)]
     pub field_order: Option<serde_json::Value>,
+    /// 最近一次导入的附加信息，如由合并单元格填充的单元格
+    #[sea_orm(column_type = "Json", nullable)]
+    pub import_metadata: Option<serde_json::Value>,
     #[sea_orm(column_type = "TimestampWithTimeZone")]
     pub created_at: DateTime<Utc>,
     #[sea_orm(column_type = "TimestampWithTimeZone")]
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "import_jobs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub workspace_id: i32,
+    pub created_by: Option<i32>,
+    pub file_path: String,
+    pub file_name: String,
+    /// 上传时计算的文件哈希，导入时无需再次读取文件
+    pub file_hash: Option<String>,
+    /// 上传时指定的导入选项（ImportOptions的JSON）
+    #[sea_orm(column_type = "Json", nullable)]
+    pub options: Option<Json>,
+    /// 试导入生成的确认令牌，确认后清空
+    pub confirm_token: Option<String>,
+    pub status: String,
+    pub rows_processed: i64,
+    /// 任务结束后的导入结果：插入、更新（upsert）、未变化（upsert及replace时文件未变化）的行数
+    pub rows_inserted: i64,
+    pub rows_updated: i64,
+    pub rows_unchanged: i64,
+    /// 未通过校验或无法写入的行数，明细见import_rejections
+    pub rows_rejected: i64,
+    /// 导入生成的文件记录，任务执行后填写
+    pub file_id: Option<i32>,
+    pub current_sheet: Option<String>,
+    pub error: Option<String>,
+    pub attempts: i32,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub started_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "TimestampWithTimeZone", nullable)]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::workspaces::Entity",
+        from = "Column::WorkspaceId",
+        to = "super::workspaces::Column::Id"
+    )]
+    Workspace,
+}
+
+impl Related<super::workspaces::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Workspace.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
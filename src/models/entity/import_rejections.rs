@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 导入时被拒绝的数据行，文件重新导入时清空
+///
+/// 属于导入的文件；严格模式下整个文件未导入时没有文件记录，属于导入任务。
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "import_rejections")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub workspace_id: Option<i32>,
+    pub file_id: Option<i32>,
+    pub job_id: Option<i32>,
+    pub sheet_name: String,
+    /// 与导入成功的行使用相同的行号规则
+    pub row_number: i32,
+    /// 未通过的规则列表（RowViolation）
+    #[sea_orm(column_type = "Json")]
+    pub reasons: Json,
+    /// 清理并应用列映射规则后的数据
+    #[sea_orm(column_type = "Json")]
+    pub data_json: Json,
+    #[sea_orm(column_type = "TimestampWithTimeZone")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::files::Entity",
+        from = "Column::FileId",
+        to = "super::files::Column::Id"
+    )]
+    File,
+    #[sea_orm(
+        belongs_to = "super::import_jobs::Entity",
+        from = "Column::JobId",
+        to = "super::import_jobs::Column::Id"
+    )]
+    ImportJob,
+}
+
+impl Related<super::files::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::File.def()
+    }
+}
+
+impl Related<super::import_jobs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImportJob.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -3,3 +3,8 @@ pub mod files;
 pub mod users;
 pub mod workspaces;
 pub mod auth_tokens;
+pub mod import_jobs;
+pub mod column_profiles;
+pub mod column_profile_versions;
+pub mod import_rejections;
+pub mod export_jobs;
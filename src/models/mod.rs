@@ -14,6 +14,7 @@ pub struct ExcelData {
     pub row_number: i32,
     pub sheet_name: String,
     pub data_json: String,
+    pub typed_json: String,
     pub search_text: String,
     pub file_name: Option<String>,
     pub field_order: Option<serde_json::Value>,
@@ -58,6 +59,52 @@ pub struct WorkspaceResponse {
     pub updated_at: DateTime<Utc>,
 }
 
+/// 导入任务状态响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportJobResponse {
+    pub id: i32,
+    pub workspace_id: i32,
+    pub file_name: String,
+    pub status: String,
+    pub rows_processed: i64,
+    pub rows_inserted: i64,
+    pub rows_updated: i64,
+    pub rows_unchanged: i64,
+    pub rows_rejected: i64,
+    /// 导入生成的文件ID，用于查询和下载拒绝的行
+    pub file_id: Option<i32>,
+    pub current_sheet: Option<String>,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// 导出任务状态响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportJobResponse {
+    pub id: i32,
+    /// 导出的工作区，导出公开数据时为None
+    pub workspace_id: Option<i32>,
+    pub format: String,
+    /// 提交时的查询说明与范围
+    pub query: String,
+    pub scope: String,
+    pub status: String,
+    pub total_rows: i64,
+    pub file_name: Option<String>,
+    pub file_size: Option<i64>,
+    /// 导出文件可下载时的下载地址
+    pub download_url: Option<String>,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
 /// 语言响应
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageResponse {
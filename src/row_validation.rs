@@ -0,0 +1,374 @@
+// 数据行校验模块
+// 工作区可以声明必填列、正则表达式、数值范围、允许值和唯一性规则，导入时不合格的行记录到
+// import_rejections表，合格的行照常导入；严格模式下只要有不合格的行，整个文件都不导入
+use crate::models::entity::import_rejections;
+use regex::Regex;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, Statement,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// 每条批量INSERT语句包含的拒绝记录数（每行8个参数）
+const REJECTION_BATCH_SIZE: usize = 1000;
+
+/// 唯一列跨文件检查时每次查询的取值数
+const UNIQUE_LOOKUP_BATCH_SIZE: usize = 1000;
+
+/// 工作区的数据行校验规则
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationRules {
+    /// 严格模式：任意一行不合格时整个文件都不导入
+    pub strict: bool,
+    pub rules: Vec<ColumnValidation>,
+}
+
+/// 单列的校验规则，列名为列映射之后的名称（不区分大小写）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnValidation {
+    pub column: String,
+    /// 必须有值
+    #[serde(default)]
+    pub required: bool,
+    /// 值需要匹配的正则表达式（部分匹配，完整匹配请使用 ^...$）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// 数值下限（含）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// 数值上限（含）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// 允许的取值，为空时不限制
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+    /// 在本次导入的文件及工作区的其他文件中不能重复
+    #[serde(default)]
+    pub unique: bool,
+}
+
+/// 一行数据未通过的规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowViolation {
+    /// 违反规则的列，整行问题（如超过数据库存储上限）时为空
+    pub column: String,
+    /// 规则类型：required、pattern、range、allowed、unique、storage
+    pub rule: String,
+    pub message: String,
+}
+
+/// 导入时被拒绝的数据行
+#[derive(Debug, Clone, Serialize)]
+pub struct RowRejection {
+    pub sheet_name: String,
+    pub row_number: usize,
+    pub reasons: Vec<RowViolation>,
+    pub data: HashMap<String, Value>,
+}
+
+/// 拒绝记录的归属：导入的文件，或严格模式下整个文件未导入、没有文件记录的导入任务
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionOwner {
+    File(i32),
+    Job(i32),
+}
+
+impl RejectionOwner {
+    fn condition(self) -> Condition {
+        match self {
+            RejectionOwner::File(file_id) => Condition::all().add(import_rejections::Column::FileId.eq(file_id)),
+            RejectionOwner::Job(job_id) => Condition::all().add(import_rejections::Column::JobId.eq(job_id)),
+        }
+    }
+}
+
+impl RowViolation {
+    pub fn new(column: &str, rule: &str, message: String) -> Self {
+        Self {
+            column: column.to_string(),
+            rule: rule.to_string(),
+            message,
+        }
+    }
+}
+
+/// 列名比较，不区分大小写并忽略首尾空白，与列映射规则一致
+fn same_column(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+impl ValidationRules {
+    /// 校验规则本身，返回中文错误说明
+    pub fn validate(&self) -> Result<(), String> {
+        RowValidator::new(self).map(|_| ())
+    }
+}
+
+struct CompiledRule {
+    rule: ColumnValidation,
+    pattern: Option<Regex>,
+}
+
+/// 按规则逐行校验，同时记录已出现的唯一列取值以检查文件内重复
+pub struct RowValidator {
+    rules: Vec<CompiledRule>,
+    /// 唯一列 → 已出现的取值 → 首次出现的位置说明
+    seen: HashMap<String, HashMap<String, String>>,
+}
+
+impl RowValidator {
+    pub fn new(rules: &ValidationRules) -> Result<Self, String> {
+        let mut compiled = Vec::with_capacity(rules.rules.len());
+        for rule in &rules.rules {
+            if rule.column.trim().is_empty() {
+                return Err("校验规则的列名不能为空".to_string());
+            }
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                if min > max {
+                    return Err(format!("列 {} 的下限 {} 大于上限 {}", rule.column, min, max));
+                }
+            }
+            let pattern = rule
+                .pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| format!("列 {} 的正则表达式无效: {}", rule.column, e))?;
+            compiled.push(CompiledRule {
+                rule: rule.clone(),
+                pattern,
+            });
+        }
+        Ok(Self {
+            rules: compiled,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// 需要在工作区范围内检查唯一性的列
+    pub fn unique_columns(&self) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|compiled| compiled.rule.unique)
+            .map(|compiled| compiled.rule.column.as_str())
+            .collect()
+    }
+
+    /// 校验一行数据，`location` 用于说明重复值首次出现的位置（如 "Sheet1 第3行"）
+    pub fn check_row(
+        &mut self,
+        data: &HashMap<String, Value>,
+        typed: &HashMap<String, Value>,
+        location: &str,
+    ) -> Vec<RowViolation> {
+        let mut violations = Vec::new();
+        // 唯一列的取值在整行通过校验后才记录，被拒绝的行不会导入，不应与之后的行冲突
+        let mut unique_values = Vec::new();
+
+        for compiled in &self.rules {
+            let rule = &compiled.rule;
+            let key = data.keys().find(|key| same_column(key, &rule.column));
+            let text = key.and_then(|key| data.get(key)).and_then(value_text);
+
+            let Some(text) = text else {
+                if rule.required {
+                    violations.push(RowViolation::new(&rule.column, "required", format!("{} 不能为空", rule.column)));
+                }
+                continue;
+            };
+
+            if let Some(pattern) = &compiled.pattern {
+                if !pattern.is_match(&text) {
+                    violations.push(RowViolation::new(
+                        &rule.column,
+                        "pattern",
+                        format!("{} 的值 {} 不符合格式 {}", rule.column, text, pattern.as_str()),
+                    ));
+                }
+            }
+
+            if rule.min.is_some() || rule.max.is_some() {
+                let number = key
+                    .and_then(|key| typed.get(key))
+                    .filter(|typed| typed.get("type").and_then(Value::as_str) == Some("number"))
+                    .and_then(|typed| typed.get("value"))
+                    .and_then(Value::as_f64)
+                    .or_else(|| text.parse::<f64>().ok().filter(|number| number.is_finite()));
+                match number {
+                    None => violations.push(RowViolation::new(
+                        &rule.column,
+                        "range",
+                        format!("{} 的值 {} 不是数字", rule.column, text),
+                    )),
+                    Some(number) if rule.min.is_some_and(|min| number < min) || rule.max.is_some_and(|max| number > max) => {
+                        violations.push(RowViolation::new(
+                            &rule.column,
+                            "range",
+                            format!("{} 的值 {} 超出范围 {}", rule.column, text, describe_range(rule.min, rule.max)),
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if !rule.allowed.is_empty() && !rule.allowed.iter().any(|allowed| allowed.trim() == text) {
+                violations.push(RowViolation::new(
+                    &rule.column,
+                    "allowed",
+                    format!("{} 的值 {} 不在允许的取值中", rule.column, text),
+                ));
+            }
+
+            if rule.unique {
+                let column = rule.column.to_lowercase();
+                match self.seen.get(&column).and_then(|seen| seen.get(&text)) {
+                    Some(first) => violations.push(RowViolation::new(
+                        &rule.column,
+                        "unique",
+                        format!("{} 的值 {} 与{}重复", rule.column, text, first),
+                    )),
+                    None => unique_values.push((column, text)),
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            for (column, text) in unique_values {
+                self.seen.entry(column).or_default().insert(text, location.to_string());
+            }
+        }
+        violations
+    }
+}
+
+/// 单元格的文本值，空单元格返回None
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn describe_range(min: Option<f64>, max: Option<f64>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("[{}, {}]", min, max),
+        (Some(min), None) => format!("≥ {}", min),
+        (None, Some(max)) => format!("≤ {}", max),
+        (None, None) => String::new(),
+    }
+}
+
+/// 按唯一列取出一行中的取值，用于在工作区的其他文件中检查重复
+pub fn unique_value(data: &HashMap<String, Value>, column: &str) -> Option<String> {
+    data.iter()
+        .find(|(key, _)| same_column(key, column))
+        .and_then(|(_, value)| value_text(value))
+}
+
+/// 查询工作区其他文件中已存在的取值，用于唯一列的跨文件检查
+///
+/// file_id为本次导入将要替换的文件，新文件为None。按excel_key_fields表达式索引查找，
+/// 每批UNIQUE_LOOKUP_BATCH_SIZE个取值。
+pub async fn find_existing_values(
+    db: &DatabaseConnection,
+    workspace_id: i32,
+    file_id: Option<i32>,
+    column: &str,
+    values: &[String],
+) -> Result<HashSet<String>, DbErr> {
+    let mut existing = HashSet::new();
+    for batch in values.chunks(UNIQUE_LOOKUP_BATCH_SIZE) {
+        let rows = db
+            .query_all(Statement::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"
+                SELECT probe.value FROM jsonb_array_elements_text($4::jsonb) AS probe(value)
+                WHERE EXISTS (
+                    SELECT 1 FROM excel_data
+                    WHERE excel_data.workspace_id = $1
+                      AND excel_data.file_id IS DISTINCT FROM $2::integer
+                      AND excel_key_fields(excel_data.data_json) @> excel_key_fields(jsonb_build_object($3::text, probe.value))
+                )
+                "#,
+                [
+                    workspace_id.into(),
+                    file_id.into(),
+                    column.into(),
+                    serde_json::to_value(batch).unwrap_or_default().into(),
+                ],
+            ))
+            .await?;
+        for row in rows {
+            existing.insert(row.try_get::<String>("", "value")?);
+        }
+    }
+    Ok(existing)
+}
+
+/// 删除文件的拒绝记录，文件重新导入前调用
+pub async fn delete_rejections<C: ConnectionTrait>(db: &C, file_id: i32) -> Result<(), DbErr> {
+    import_rejections::Entity::delete_many()
+        .filter(import_rejections::Column::FileId.eq(file_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 保存文件或导入任务的拒绝记录
+pub async fn save_rejections<C: ConnectionTrait>(
+    db: &C,
+    workspace_id: Option<i32>,
+    owner: RejectionOwner,
+    rejections: &[RowRejection],
+) -> Result<(), DbErr> {
+    let now = chrono::Utc::now();
+    let (file_id, job_id) = match owner {
+        RejectionOwner::File(file_id) => (Some(file_id), None),
+        RejectionOwner::Job(job_id) => (None, Some(job_id)),
+    };
+    for batch in rejections.chunks(REJECTION_BATCH_SIZE) {
+        let records = batch.iter().map(|rejection| import_rejections::ActiveModel {
+            id: Default::default(),
+            workspace_id: Set(workspace_id),
+            file_id: Set(file_id),
+            job_id: Set(job_id),
+            sheet_name: Set(rejection.sheet_name.clone()),
+            row_number: Set(rejection.row_number as i32),
+            reasons: Set(serde_json::to_value(&rejection.reasons).unwrap_or_default()),
+            data_json: Set(serde_json::to_value(&rejection.data).unwrap_or_default()),
+            created_at: Set(now),
+        });
+        import_rejections::Entity::insert_many(records).exec(db).await?;
+    }
+    Ok(())
+}
+
+/// 统计文件或导入任务的拒绝记录数
+pub async fn count_rejections(db: &DatabaseConnection, owner: RejectionOwner) -> Result<u64, DbErr> {
+    import_rejections::Entity::find()
+        .filter(owner.condition())
+        .count(db)
+        .await
+}
+
+/// 按工作表和行号列出文件或导入任务的拒绝记录，limit为None时返回全部
+pub async fn list_rejections(
+    db: &DatabaseConnection,
+    owner: RejectionOwner,
+    limit: Option<u64>,
+    offset: u64,
+) -> Result<Vec<import_rejections::Model>, DbErr> {
+    import_rejections::Entity::find()
+        .filter(owner.condition())
+        .order_by_asc(import_rejections::Column::SheetName)
+        .order_by_asc(import_rejections::Column::RowNumber)
+        .order_by_asc(import_rejections::Column::Id)
+        .offset(offset)
+        .limit(limit)
+        .all(db)
+        .await
+}
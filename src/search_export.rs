@@ -0,0 +1,882 @@
+// 搜索结果导出模块
+// 按源文件和工作表分组，列按文件导入时记录的字段顺序排列，也可以由请求指定导出的列、顺序和显示名称；
+// 系统列（源文件、工作表、行号、导入时间）的表头和导出信息工作表的标签按请求者的语言取自语言包；各导出格式实现SearchExporter，
+// 数据按分组分页写入导出器，CSV/JSON/NDJSON每页产生的内容可以立即发送，
+// xlsx使用rust_xlsxwriter的常量内存模式写入临时文件，整个文件生成后再输出
+use crate::excel_processor_sea::{ExcelProcessor, SearchMode};
+use crate::i18n_manager::I18nManager;
+use crate::models::entity::{excel_data, files};
+use rust_xlsxwriter::{Format, FormatBorder, Workbook};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::future::Future;
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+
+/// CSV以UTF-8 BOM开头，Excel才能正确识别中文
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Xlsx,
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// 按扩展名（与序列化名称相同）取得导出格式
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        [ExportFormat::Xlsx, ExportFormat::Csv, ExportFormat::Json, ExportFormat::Ndjson]
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// 为一次导出创建该格式的导出器，`sheets` 为按导出顺序排列的全部分组
+    pub fn exporter(&self, sheets: &[ExportSheet], info: &ExportInfo) -> Result<Box<dyn SearchExporter>, ExportError> {
+        Ok(match self {
+            ExportFormat::Xlsx => Box::new(XlsxExporter::new(sheets, info)),
+            ExportFormat::Csv => Box::new(CsvExporter::new(sheets, info)?),
+            ExportFormat::Json => Box::new(JsonExporter::new(sheets, info)?),
+            ExportFormat::Ndjson => Box::new(NdjsonExporter::new(sheets, info)),
+        })
+    }
+}
+
+/// 导出工作表中的一列
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportColumn {
+    /// 源文件名（CSV/NDJSON中区分不同分组的数据）
+    FileName,
+    /// 源工作表名
+    SheetName,
+    /// 数据在源工作表中的行号
+    RowNumber,
+    /// 数据的导入时间
+    ImportTime,
+    /// 源文件中的字段
+    Field(String),
+}
+
+impl ExportColumn {
+    /// 请求中的系统列名称，如 `@row_number`
+    fn from_system_name(name: &str) -> Option<Self> {
+        match name {
+            "@file_name" => Some(ExportColumn::FileName),
+            "@sheet_name" => Some(ExportColumn::SheetName),
+            "@row_number" => Some(ExportColumn::RowNumber),
+            "@import_time" => Some(ExportColumn::ImportTime),
+            _ => None,
+        }
+    }
+}
+
+/// 系统列的表头，按请求者的语言从语言包中取得
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemColumnLabels {
+    pub file_name: String,
+    pub sheet_name: String,
+    pub row_number: String,
+    pub import_time: String,
+}
+
+impl Default for SystemColumnLabels {
+    fn default() -> Self {
+        Self {
+            file_name: "源文件".to_string(),
+            sheet_name: "工作表".to_string(),
+            row_number: "行号".to_string(),
+            import_time: "导入时间".to_string(),
+        }
+    }
+}
+
+impl SystemColumnLabels {
+    /// 语言包 export.columns 下的系统列名称
+    pub fn translated(i18n: &I18nManager, lang: &str) -> Self {
+        Self {
+            file_name: i18n.translate("export.columns.file_name", lang, None),
+            sheet_name: i18n.translate("export.columns.sheet_name", lang, None),
+            row_number: i18n.translate("export.columns.row_number", lang, None),
+            import_time: i18n.translate("export.columns.import_time", lang, None),
+        }
+    }
+}
+
+/// 请求选择的一列及其显示名称
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectedColumn {
+    pub column: ExportColumn,
+    /// 显示名称，为空时使用字段名或系统列的本地化名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// 导出的列：请求选择的列（按顺序）与系统列的表头
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportColumns {
+    /// 为空时导出全部字段及各格式默认的系统列
+    pub selected: Vec<SelectedColumn>,
+    pub labels: SystemColumnLabels,
+}
+
+impl ExportColumns {
+    /// 解析请求中的 column 参数：字段名或 `@file_name`、`@sheet_name`、`@row_number`、`@import_time`，
+    /// 可用 `列=显示名称` 重命名；`\=`、`\@`、`\\` 表示字面的 `=`、`@`、`\`，
+    /// 用于选择名称中含 `=` 或以 `@` 开头的字段
+    pub fn parse<'a>(specs: impl IntoIterator<Item = &'a str>, labels: SystemColumnLabels) -> Result<Self, String> {
+        let mut selected: Vec<SelectedColumn> = Vec::new();
+        for spec in specs {
+            let (raw_name, raw_label) = split_column_spec(spec);
+            let raw_name = raw_name.trim();
+            let name = unescape_column_spec(raw_name);
+            let label = raw_label.map(|label| unescape_column_spec(label.trim())).filter(|label| !label.is_empty());
+            if name.is_empty() {
+                return Err("导出列不能为空".to_string());
+            }
+            // 转义的 \@ 开头不是系统列
+            let column = if raw_name.starts_with('@') {
+                ExportColumn::from_system_name(&name).ok_or_else(|| {
+                    format!("未知的系统列: {}，可用: @file_name、@sheet_name、@row_number、@import_time", name)
+                })?
+            } else {
+                ExportColumn::Field(name.clone())
+            };
+            if selected.iter().any(|existing| existing.column == column) {
+                return Err(format!("导出列重复: {}", name));
+            }
+            selected.push(SelectedColumn { column, label });
+        }
+
+        let columns = Self { selected, labels };
+        let mut headers = HashSet::new();
+        for selected in &columns.selected {
+            let header = columns.header(&selected.column);
+            if !headers.insert(header) {
+                return Err(format!("导出列名称重复: {}", header));
+            }
+        }
+        Ok(columns)
+    }
+
+    /// 列的表头：选择时指定的显示名称、系统列的本地化名称或字段名
+    pub fn header<'a>(&'a self, column: &'a ExportColumn) -> &'a str {
+        if let Some(label) = self
+            .selected
+            .iter()
+            .find(|selected| &selected.column == column)
+            .and_then(|selected| selected.label.as_deref())
+        {
+            return label;
+        }
+        match column {
+            ExportColumn::FileName => &self.labels.file_name,
+            ExportColumn::SheetName => &self.labels.sheet_name,
+            ExportColumn::RowNumber => &self.labels.row_number,
+            ExportColumn::ImportTime => &self.labels.import_time,
+            ExportColumn::Field(name) => name,
+        }
+    }
+
+    /// 实际导出的列及表头：选择了列时按选择的顺序，否则使用格式默认的列
+    ///
+    /// 表头在导出中作为列名和JSON键，必须唯一：字段名保持不变，与字段或其他系统列重名的系统列表头依次追加 (2)、(3)…
+    fn resolve(&self, default: &[ExportColumn]) -> Vec<(ExportColumn, String)> {
+        let columns: Vec<&ExportColumn> = if self.selected.is_empty() {
+            default.iter().collect()
+        } else {
+            self.selected.iter().map(|selected| &selected.column).collect()
+        };
+        let mut used: HashSet<String> = columns
+            .iter()
+            .filter(|column| matches!(column, ExportColumn::Field(_)))
+            .map(|column| self.header(column).to_string())
+            .collect();
+        let mut resolved = Vec::with_capacity(columns.len());
+        for column in columns {
+            let base = self.header(column);
+            let mut header = base.to_string();
+            if !matches!(column, ExportColumn::Field(_)) {
+                let mut suffix = 2;
+                while used.contains(&header) {
+                    header = format!("{} ({})", base, suffix);
+                    suffix += 1;
+                }
+                used.insert(header.clone());
+            }
+            resolved.push((column.clone(), header));
+        }
+        resolved
+    }
+}
+
+/// 在第一个未转义的 `=` 处分开列名和显示名称，两部分都保留转义
+fn split_column_spec(spec: &str) -> (&str, Option<&str>) {
+    let mut escaped = false;
+    for (idx, ch) in spec.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' => return (&spec[..idx], Some(&spec[idx + 1..])),
+            _ => {}
+        }
+    }
+    (spec, None)
+}
+
+/// 去掉列参数中的转义：`\x` 表示字面的 `x`，结尾单独的 `\` 保持原样
+fn unescape_column_spec(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            result.push(chars.next().unwrap_or('\\'));
+        } else {
+            result.push(ch);
+        }
+    }
+    result
+}
+
+/// 同一源文件同一工作表的导出分组，数据行按源工作表中的行号分页读取
+#[derive(Debug, Clone)]
+pub struct ExportSheet {
+    pub file: files::Model,
+    pub sheet_name: String,
+    pub columns: Vec<ExportColumn>,
+    /// 导出开始前统计的匹配行数
+    pub row_count: u64,
+}
+
+/// 导出信息工作表的名称和标签，按请求者的语言从语言包中取得
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportMetadataLabels {
+    pub sheet_name: String,
+    pub query: String,
+    pub search_mode: String,
+    pub scope: String,
+    pub exported_at: String,
+    pub exported_rows: String,
+    pub sheet: String,
+    pub source_file: String,
+    pub file_id: String,
+    pub source_sheet: String,
+    pub rows: String,
+    pub file_imported_at: String,
+    pub full_text: String,
+    /// 模糊检索的说明，`{threshold}` 替换为相似度阈值
+    pub fuzzy: String,
+}
+
+impl Default for ExportMetadataLabels {
+    fn default() -> Self {
+        Self {
+            sheet_name: "导出信息".to_string(),
+            query: "查询".to_string(),
+            search_mode: "搜索模式".to_string(),
+            scope: "范围".to_string(),
+            exported_at: "导出时间".to_string(),
+            exported_rows: "导出行数".to_string(),
+            sheet: "工作表".to_string(),
+            source_file: "源文件".to_string(),
+            file_id: "文件ID".to_string(),
+            source_sheet: "源工作表".to_string(),
+            rows: "行数".to_string(),
+            file_imported_at: "文件导入时间".to_string(),
+            full_text: "全文检索".to_string(),
+            fuzzy: "模糊检索（相似度阈值 {threshold}）".to_string(),
+        }
+    }
+}
+
+impl ExportMetadataLabels {
+    /// 语言包 export.metadata 下的名称
+    pub fn translated(i18n: &I18nManager, lang: &str) -> Self {
+        let t = |key: &str| i18n.translate(&format!("export.metadata.{}", key), lang, None);
+        Self {
+            sheet_name: t("sheet_name"),
+            query: t("query"),
+            search_mode: t("search_mode"),
+            scope: t("scope"),
+            exported_at: t("exported_at"),
+            exported_rows: t("exported_rows"),
+            sheet: t("sheet"),
+            source_file: t("source_file"),
+            file_id: t("file_id"),
+            source_sheet: t("source_sheet"),
+            rows: t("rows"),
+            file_imported_at: t("file_imported_at"),
+            full_text: t("full_text"),
+            fuzzy: t("fuzzy"),
+        }
+    }
+
+    fn describe_mode(&self, mode: SearchMode) -> String {
+        match mode {
+            SearchMode::FullText => self.full_text.clone(),
+            SearchMode::Fuzzy { threshold } => self.fuzzy.replace("{threshold}", &threshold.to_string()),
+        }
+    }
+}
+
+/// 写入导出信息工作表的查询说明
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportContext {
+    /// 查询语句与范围筛选，如 `张三 filter[年龄]=18..30`
+    pub query: String,
+    /// 导出范围，如 `Workspace 销售数据 (ID 2)` 或 `公开数据`
+    pub scope: String,
+    /// 提交时按请求者语言确定的导出信息标签
+    #[serde(default)]
+    pub labels: ExportMetadataLabels,
+}
+
+/// 一次导出的说明信息，各导出格式都会写出
+#[derive(Debug, Clone)]
+pub struct ExportInfo {
+    pub context: ExportContext,
+    pub mode: SearchMode,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub columns: ExportColumns,
+}
+
+/// 导出过程中的错误，需要在后台任务之间传递
+pub type ExportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// 写完全部数据后导出器剩余的输出
+pub enum ExportOutput {
+    /// 尚未取出的内容
+    Bytes(Vec<u8>),
+    /// 完整的导出文件（已回到文件开头），临时文件关闭后自动删除
+    File(std::fs::File),
+}
+
+/// 搜索结果导出器，每种导出格式一个实现
+pub trait SearchExporter: Send {
+    /// 写入一个分组的一页数据；分组按导出顺序写入，同一分组的各页连续写入
+    fn write_rows(&mut self, sheet_index: usize, rows: &[excel_data::Model]) -> Result<(), ExportError>;
+
+    /// 取出已经生成、可以立即发送的内容，需要整个文件写完才能输出的格式返回空
+    fn take_output(&mut self) -> Vec<u8>;
+
+    /// 写完全部数据
+    fn finish(self: Box<Self>) -> Result<ExportOutput, ExportError>;
+}
+
+/// 导出内容的去向，如HTTP响应流
+pub trait ExportSink {
+    fn write_chunk(&mut self, chunk: Vec<u8>) -> impl Future<Output = Result<(), ExportError>> + Send;
+}
+
+impl ExportSink for Vec<u8> {
+    async fn write_chunk(&mut self, chunk: Vec<u8>) -> Result<(), ExportError> {
+        self.extend_from_slice(&chunk);
+        Ok(())
+    }
+}
+
+impl ExportSink for tokio::sync::mpsc::Sender<Result<Vec<u8>, std::io::Error>> {
+    async fn write_chunk(&mut self, chunk: Vec<u8>) -> Result<(), ExportError> {
+        self.send(Ok(chunk)).await.map_err(|_| "下载连接已断开".into())
+    }
+}
+
+impl ExportSink for tokio::fs::File {
+    async fn write_chunk(&mut self, chunk: Vec<u8>) -> Result<(), ExportError> {
+        use tokio::io::AsyncWriteExt;
+        self.write_all(&chunk).await.map_err(Into::into)
+    }
+}
+
+impl ExportSheet {
+    /// 按文件的字段顺序和分组中出现过的字段确定导出列
+    pub fn new<I>(file: files::Model, sheet_name: String, present_fields: I, row_count: u64) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let present: HashSet<String> = present_fields.into_iter().collect();
+        let columns = export_columns(&file, &present);
+        Self {
+            file,
+            sheet_name,
+            columns,
+            row_count,
+        }
+    }
+}
+
+/// 行号、文件字段顺序中在分组里出现过的列、字段顺序之外的其他列（按名称）、导入时间
+fn export_columns(file: &files::Model, present: &HashSet<String>) -> Vec<ExportColumn> {
+    let field_order: Vec<String> = file
+        .field_order
+        .clone()
+        .and_then(|order| serde_json::from_value(order).ok())
+        .unwrap_or_default();
+
+    let mut columns = vec![ExportColumn::RowNumber];
+    let mut listed = HashSet::new();
+    for field in &field_order {
+        if present.contains(field) && listed.insert(field.as_str()) {
+            columns.push(ExportColumn::Field(field.clone()));
+        }
+    }
+    let extra: BTreeSet<&String> = present.iter().filter(|field| !listed.contains(field.as_str())).collect();
+    columns.extend(extra.into_iter().map(|field| ExportColumn::Field(field.clone())));
+    columns.push(ExportColumn::ImportTime);
+    columns
+}
+/// 导出工作表的基础名称：工作簿为 `文件名 - 工作表`，CSV/TSV 只有一个工作表，只用文件名
+fn export_sheet_base_name(sheet: &ExportSheet) -> String {
+    let stem = Path::new(&sheet.file.file_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| sheet.file.file_name.clone());
+    let single_sheet = Path::new(&sheet.file.file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv") || ext.eq_ignore_ascii_case("tsv"));
+    if single_sheet {
+        stem
+    } else {
+        format!("{} - {}", stem, sheet.sheet_name)
+    }
+}
+
+/// 所有分组合并为一张表时的列：源文件、工作表、行号、各分组字段按首次出现的先后合并、导入时间
+pub fn combined_columns(sheets: &[ExportSheet]) -> Vec<ExportColumn> {
+    let mut columns = vec![ExportColumn::FileName, ExportColumn::SheetName, ExportColumn::RowNumber];
+    for column in sheets.iter().flat_map(|sheet| &sheet.columns) {
+        if matches!(column, ExportColumn::Field(_)) && !columns.contains(column) {
+            columns.push(column.clone());
+        }
+    }
+    columns.push(ExportColumn::ImportTime);
+    columns
+}
+
+/// 单元格的导出值：数字和布尔字段使用typed_json中的原生值，其余为显示文本
+pub fn column_value(sheet: &ExportSheet, row: &excel_data::Model, column: &ExportColumn) -> Value {
+    match column {
+        ExportColumn::FileName => Value::String(sheet.file.file_name.clone()),
+        ExportColumn::SheetName => Value::String(sheet.sheet_name.clone()),
+        ExportColumn::RowNumber => Value::from(row.row_number),
+        ExportColumn::ImportTime => Value::String(row.import_time.format("%Y-%m-%d %H:%M:%S").to_string()),
+        ExportColumn::Field(field) => {
+            let typed = row.typed_json.get(field);
+            match typed.and_then(|t| Some((t.get("type")?.as_str()?, t.get("value")?))) {
+                Some(("number", value)) if value.is_number() => value.clone(),
+                Some(("bool", Value::Bool(b))) => Value::Bool(*b),
+                _ => Value::String(cell_text(row.data_json.get(field))),
+            }
+        }
+    }
+}
+
+/// 单元格的显示文本，用于CSV；字段始终使用导入时的显示文本
+fn column_text(sheet: &ExportSheet, row: &excel_data::Model, column: &ExportColumn) -> String {
+    match column {
+        ExportColumn::Field(field) => cell_text(row.data_json.get(field)),
+        ExportColumn::RowNumber => row.row_number.to_string(),
+        other => cell_text(Some(&column_value(sheet, row, other))),
+    }
+}
+
+/// 按列顺序序列化的JSON对象（serde_json::Map会按键名排序）
+struct OrderedRow<'a>(Vec<(&'a str, Value)>);
+
+/// 导出的列及表头
+type LabeledColumns = Vec<(ExportColumn, String)>;
+
+impl Serialize for OrderedRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+fn ordered_row<'a>(sheet: &ExportSheet, row: &excel_data::Model, columns: &'a [(ExportColumn, String)]) -> OrderedRow<'a> {
+    OrderedRow(
+        columns
+            .iter()
+            .map(|(column, header)| (header.as_str(), column_value(sheet, row, column)))
+            .collect(),
+    )
+}
+
+/// 数据单元格的显示文本，整数形式的数字不带小数点
+fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Number(n)) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 => format!("{:.0}", f),
+            _ => n.to_string(),
+        },
+        Some(Value::Bool(b)) => b.to_string(),
+        Some(Value::Null) | None => String::new(),
+        Some(v) => v.to_string(),
+    }
+}
+
+/// JSON导出中的搜索模式
+fn mode_json(mode: SearchMode) -> Value {
+    serde_json::to_value(mode).unwrap_or_default()
+}
+
+fn header_format() -> Format {
+    Format::new()
+        .set_bold()
+        .set_background_color("#4472C4")
+        .set_font_color("#FFFFFF")
+        .set_border(FormatBorder::Thin)
+}
+
+/// xlsx：每个分组一个常量内存模式的工作表，最后是导出信息工作表
+pub struct XlsxExporter {
+    workbook: Workbook,
+    sheets: Vec<ExportSheet>,
+    /// 各分组导出的列及表头
+    columns: Vec<LabeledColumns>,
+    info: ExportInfo,
+    /// 导出信息工作表的名称预先占用，数据工作表不会与其重名
+    used_names: Vec<String>,
+    /// 已创建的数据工作表名称及写入的行数
+    written: Vec<(String, u64)>,
+    header_format: Format,
+    data_format: Format,
+}
+
+impl XlsxExporter {
+    fn new(sheets: &[ExportSheet], info: &ExportInfo) -> Self {
+        Self {
+            workbook: Workbook::new(),
+            sheets: sheets.to_vec(),
+            columns: sheets.iter().map(|sheet| info.columns.resolve(&sheet.columns)).collect(),
+            info: info.clone(),
+            used_names: vec![info.context.labels.sheet_name.clone()],
+            written: Vec::with_capacity(sheets.len()),
+            header_format: header_format(),
+            data_format: Format::new().set_border(FormatBorder::Thin),
+        }
+    }
+
+    /// 依次创建到指定分组为止的工作表（常量内存模式下工作表只能按顺序写入），没有数据的分组只有表头
+    fn open_sheet(&mut self, sheet_index: usize) -> Result<(), ExportError> {
+        while self.written.len() <= sheet_index {
+            let sheet = &self.sheets[self.written.len()];
+            let name = ExcelProcessor::unique_sheet_name(&export_sheet_base_name(sheet), &mut self.used_names);
+            let worksheet = self.workbook.add_worksheet_with_constant_memory().set_name(&name)?;
+            for (col_idx, (_, header)) in self.columns[self.written.len()].iter().enumerate() {
+                worksheet.set_column_width(col_idx as u16, 15.0)?;
+                worksheet.write_string_with_format(0, col_idx as u16, header, &self.header_format)?;
+            }
+            worksheet.set_freeze_panes(1, 0)?;
+            self.written.push((name, 0));
+        }
+        Ok(())
+    }
+
+    fn write_metadata_sheet(&mut self) -> Result<(), ExportError> {
+        let label_format = Format::new().set_bold();
+        let labels = &self.info.context.labels;
+        let worksheet = self.workbook.add_worksheet().set_name(&labels.sheet_name)?;
+        let total_rows: u64 = self.written.iter().map(|(_, rows)| rows).sum();
+        let summary = [
+            (&labels.query, self.info.context.query.clone()),
+            (&labels.search_mode, labels.describe_mode(self.info.mode)),
+            (&labels.scope, self.info.context.scope.clone()),
+            (&labels.exported_at, self.info.exported_at.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+            (&labels.exported_rows, total_rows.to_string()),
+        ];
+        for (row, (label, value)) in summary.iter().enumerate() {
+            worksheet.write_string_with_format(row as u32, 0, *label, &label_format)?;
+            worksheet.write_string(row as u32, 1, value)?;
+        }
+
+        let table_row = (summary.len() + 1) as u32;
+        let headers = [
+            &labels.sheet,
+            &labels.source_file,
+            &labels.file_id,
+            &labels.source_sheet,
+            &labels.rows,
+            &labels.file_imported_at,
+        ];
+        for (col_idx, header) in headers.iter().enumerate() {
+            worksheet.write_string_with_format(table_row, col_idx as u16, *header, &self.header_format)?;
+        }
+        for (idx, (sheet, (name, rows))) in self.sheets.iter().zip(&self.written).enumerate() {
+            let row = table_row + 1 + idx as u32;
+            worksheet.write_string_with_format(row, 0, name, &self.data_format)?;
+            worksheet.write_string_with_format(row, 1, &sheet.file.file_name, &self.data_format)?;
+            worksheet.write_number_with_format(row, 2, sheet.file.id as f64, &self.data_format)?;
+            worksheet.write_string_with_format(row, 3, &sheet.sheet_name, &self.data_format)?;
+            worksheet.write_number_with_format(row, 4, *rows as f64, &self.data_format)?;
+            let imported_at = sheet.file.updated_at.format("%Y-%m-%d %H:%M:%S").to_string();
+            worksheet.write_string_with_format(row, 5, &imported_at, &self.data_format)?;
+        }
+        worksheet.set_column_width(0, 30.0)?;
+        worksheet.set_column_width(1, 30.0)?;
+        for col_idx in 2..headers.len() as u16 {
+            worksheet.set_column_width(col_idx, 15.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl SearchExporter for XlsxExporter {
+    fn write_rows(&mut self, sheet_index: usize, rows: &[excel_data::Model]) -> Result<(), ExportError> {
+        self.open_sheet(sheet_index)?;
+        let sheet = &self.sheets[sheet_index];
+        let worksheet = self.workbook.worksheet_from_index(sheet_index)?;
+        let written = &mut self.written[sheet_index].1;
+        for excel_model in rows {
+            let row = (*written + 1) as u32;
+            for (col_idx, (column, _)) in self.columns[sheet_index].iter().enumerate() {
+                let col = col_idx as u16;
+                // 数字和布尔单元格按原生类型写出，其余按显示文本写出
+                match column_value(sheet, excel_model, column) {
+                    Value::Number(n) => {
+                        worksheet.write_number_with_format(row, col, n.as_f64().unwrap_or_default(), &self.data_format)?;
+                    }
+                    Value::Bool(b) => {
+                        worksheet.write_boolean_with_format(row, col, b, &self.data_format)?;
+                    }
+                    other => {
+                        worksheet.write_string_with_format(row, col, cell_text(Some(&other)), &self.data_format)?;
+                    }
+                }
+            }
+            *written += 1;
+        }
+        Ok(())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<ExportOutput, ExportError> {
+        if !self.sheets.is_empty() {
+            self.open_sheet(self.sheets.len() - 1)?;
+        }
+        self.write_metadata_sheet()?;
+        let mut file = tempfile::tempfile()?;
+        self.workbook.save_to_writer(&mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(ExportOutput::File(file))
+    }
+}
+
+/// CSV（UTF-8 BOM）：所有分组合并为一张表，默认前两列为源文件和工作表
+pub struct CsvExporter {
+    sheets: Vec<ExportSheet>,
+    columns: LabeledColumns,
+    buffer: Vec<u8>,
+}
+
+impl CsvExporter {
+    fn new(sheets: &[ExportSheet], info: &ExportInfo) -> Result<Self, ExportError> {
+        let columns = info.columns.resolve(&combined_columns(sheets));
+        let mut writer = csv::Writer::from_writer(UTF8_BOM.to_vec());
+        writer.write_record(columns.iter().map(|(_, header)| header))?;
+        let buffer = writer.into_inner().map_err(|e| e.into_error())?;
+        Ok(Self {
+            sheets: sheets.to_vec(),
+            columns,
+            buffer,
+        })
+    }
+}
+
+impl SearchExporter for CsvExporter {
+    fn write_rows(&mut self, sheet_index: usize, rows: &[excel_data::Model]) -> Result<(), ExportError> {
+        let sheet = &self.sheets[sheet_index];
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(std::mem::take(&mut self.buffer));
+        for row in rows {
+            writer.write_record(self.columns.iter().map(|(column, _)| column_text(sheet, row, column)))?;
+        }
+        self.buffer = writer.into_inner().map_err(|e| e.into_error())?;
+        Ok(())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    fn finish(self: Box<Self>) -> Result<ExportOutput, ExportError> {
+        Ok(ExportOutput::Bytes(self.buffer))
+    }
+}
+
+/// JSON：导出说明加上按分组嵌套的数据行，行对象的键按列顺序排列；按顺序拼接输出，不在内存中构建整个文档
+///
+/// total_rows写在sheets之后，为实际写出的行数（统计行数之后数据可能发生变化）。
+pub struct JsonExporter {
+    sheets: Vec<ExportSheet>,
+    columns: Vec<LabeledColumns>,
+    /// 正在写入的分组
+    current: Option<usize>,
+    first_row: bool,
+    total_rows: u64,
+    buffer: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct JsonSheetHeader<'a> {
+    file_id: i32,
+    file_name: &'a str,
+    sheet_name: &'a str,
+    columns: Vec<&'a str>,
+}
+
+impl JsonExporter {
+    fn new(sheets: &[ExportSheet], info: &ExportInfo) -> Result<Self, ExportError> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(b"{\"query\":");
+        serde_json::to_writer(&mut buffer, &info.context.query)?;
+        buffer.extend_from_slice(b",\"scope\":");
+        serde_json::to_writer(&mut buffer, &info.context.scope)?;
+        buffer.extend_from_slice(b",\"search\":");
+        serde_json::to_writer(&mut buffer, &mode_json(info.mode))?;
+        buffer.extend_from_slice(b",\"exported_at\":");
+        serde_json::to_writer(&mut buffer, &info.exported_at)?;
+        buffer.extend_from_slice(b",\"sheets\":[");
+        Ok(Self {
+            sheets: sheets.to_vec(),
+            columns: sheets.iter().map(|sheet| info.columns.resolve(&sheet.columns)).collect(),
+            current: None,
+            first_row: true,
+            total_rows: 0,
+            buffer,
+        })
+    }
+
+    /// 结束当前分组并开始新的分组对象，对象中rows之前的字段与列顺序一致
+    fn open_sheet(&mut self, sheet_index: usize) -> Result<(), ExportError> {
+        if self.current == Some(sheet_index) {
+            return Ok(());
+        }
+        if self.current.is_some() {
+            self.buffer.extend_from_slice(b"]},");
+        }
+        let sheet = &self.sheets[sheet_index];
+        let header = JsonSheetHeader {
+            file_id: sheet.file.id,
+            file_name: &sheet.file.file_name,
+            sheet_name: &sheet.sheet_name,
+            columns: self.columns[sheet_index].iter().map(|(_, header)| header.as_str()).collect(),
+        };
+        let header = serde_json::to_vec(&header)?;
+        // 去掉结尾的 }，接着写rows数组
+        self.buffer.extend_from_slice(&header[..header.len() - 1]);
+        self.buffer.extend_from_slice(b",\"rows\":[");
+        self.current = Some(sheet_index);
+        self.first_row = true;
+        Ok(())
+    }
+}
+
+impl SearchExporter for JsonExporter {
+    fn write_rows(&mut self, sheet_index: usize, rows: &[excel_data::Model]) -> Result<(), ExportError> {
+        self.open_sheet(sheet_index)?;
+        let sheet = &self.sheets[sheet_index];
+        for row in rows {
+            if !self.first_row {
+                self.buffer.push(b',');
+            }
+            self.first_row = false;
+            serde_json::to_writer(&mut self.buffer, &ordered_row(sheet, row, &self.columns[sheet_index]))?;
+        }
+        self.total_rows += rows.len() as u64;
+        Ok(())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<ExportOutput, ExportError> {
+        if self.current.is_some() {
+            self.buffer.extend_from_slice(b"]}");
+        }
+        self.buffer.extend_from_slice(b"],\"total_rows\":");
+        serde_json::to_writer(&mut self.buffer, &self.total_rows)?;
+        self.buffer.push(b'}');
+        Ok(ExportOutput::Bytes(self.buffer))
+    }
+}
+
+/// NDJSON：每行一个JSON对象，默认包含源文件、工作表和该分组的列
+pub struct NdjsonExporter {
+    sheets: Vec<ExportSheet>,
+    columns: Vec<LabeledColumns>,
+    buffer: Vec<u8>,
+}
+
+impl NdjsonExporter {
+    fn new(sheets: &[ExportSheet], info: &ExportInfo) -> Self {
+        let columns = sheets
+            .iter()
+            .map(|sheet| {
+                let mut columns = vec![ExportColumn::FileName, ExportColumn::SheetName];
+                columns.extend(sheet.columns.iter().cloned());
+                info.columns.resolve(&columns)
+            })
+            .collect();
+        Self {
+            sheets: sheets.to_vec(),
+            columns,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl SearchExporter for NdjsonExporter {
+    fn write_rows(&mut self, sheet_index: usize, rows: &[excel_data::Model]) -> Result<(), ExportError> {
+        let sheet = &self.sheets[sheet_index];
+        for row in rows {
+            serde_json::to_writer(&mut self.buffer, &ordered_row(sheet, row, &self.columns[sheet_index]))?;
+            self.buffer.push(b'\n');
+        }
+        Ok(())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    fn finish(self: Box<Self>) -> Result<ExportOutput, ExportError> {
+        Ok(ExportOutput::Bytes(self.buffer))
+    }
+}
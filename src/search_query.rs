@@ -0,0 +1,539 @@
+// 搜索查询语法解析模块
+// 支持 field:value 字段限定、"引号短语"、AND/OR/NOT、-排除以及括号分组；
+// 只由普通关键词组成的查询按任一关键词命中（OR）并按相关度排序，使用任何查询语法后
+// 相邻条件之间省略的运算符视为 AND。另外解析 filter[字段]=下限..上限 形式的数值/日期范围筛选
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+/// 范围筛选支持的日期时间格式（不含时间的日期单独处理）
+const FILTER_DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// 单个查询中允许的最大条件数量
+const MAX_TERMS: usize = 32;
+/// 括号及NOT的最大嵌套层数
+const MAX_DEPTH: usize = 16;
+
+/// 搜索条件
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTerm {
+    /// 限定的字段名（对应data_json中的键），None表示匹配整行
+    pub field: Option<String>,
+    pub value: String,
+    /// 是否为引号短语
+    pub phrase: bool,
+}
+
+/// 解析后的查询表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    Term(SearchTerm),
+    Not(Box<QueryExpr>),
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+}
+
+/// 查询语法错误，position为出错位置的字符下标（从0开始）
+#[derive(Debug, Clone, Serialize)]
+pub struct QuerySyntaxError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl std::fmt::Display for QuerySyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}（位置 {}）", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QuerySyntaxError {}
+
+/// 解析结果
+#[derive(Debug, Clone)]
+pub struct ParsedQuery {
+    pub expr: QueryExpr,
+    /// 查询是否只由空格分隔的普通关键词组成（未使用任何查询语法）
+    plain: bool,
+}
+
+impl ParsedQuery {
+    /// 未使用查询语法时返回关键词列表，此时沿用多关键词按相关度排序的搜索方式
+    ///
+    /// 这种情况下关键词之间是OR关系（命中任一关键词即可，命中越多排名越靠前），
+    /// 兼容引入查询语法之前的搜索行为；`a b` 与 `a AND b` 因此结果不同，
+    /// 需要同时命中多个词时应显式使用AND或任一查询语法。
+    pub fn plain_keywords(&self) -> Option<Vec<&str>> {
+        if !self.plain {
+            return None;
+        }
+        let mut keywords = Vec::new();
+        collect_terms(&self.expr, &mut keywords);
+        Some(keywords.into_iter().map(|term| term.value.as_str()).collect())
+    }
+}
+
+/// 范围筛选的边界值
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeValue {
+    /// 数值，保留原始文本以便在数据库中按numeric精确比较
+    Number(String),
+    /// 日期时间
+    DateTime(NaiveDateTime),
+}
+
+/// 范围筛选的一侧边界
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeBound {
+    pub value: RangeValue,
+    /// 是否包含边界值
+    pub inclusive: bool,
+}
+
+/// 对单个字段的数值或日期范围筛选，上下限至少有一个
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeFilter {
+    /// 字段名（对应data_json中的键，不区分大小写）
+    pub field: String,
+    pub lower: Option<RangeBound>,
+    pub upper: Option<RangeBound>,
+}
+
+/// 一次搜索请求的全部条件：查询语句与范围筛选至少有一项
+#[derive(Debug, Clone, Default)]
+pub struct SearchCriteria {
+    pub query: Option<ParsedQuery>,
+    pub filters: Vec<RangeFilter>,
+}
+
+impl SearchCriteria {
+    pub fn is_empty(&self) -> bool {
+        self.query.is_none() && self.filters.is_empty()
+    }
+}
+
+fn collect_terms<'a>(expr: &'a QueryExpr, terms: &mut Vec<&'a SearchTerm>) {
+    match expr {
+        QueryExpr::Term(term) => terms.push(term),
+        QueryExpr::Not(inner) => collect_terms(inner, terms),
+        QueryExpr::And(items) | QueryExpr::Or(items) => {
+            for item in items {
+                collect_terms(item, terms);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Term(SearchTerm),
+    And,
+    Or,
+    Not,
+    Minus,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn syntax_error(message: impl Into<String>, position: usize) -> QuerySyntaxError {
+    QuerySyntaxError {
+        message: message.into(),
+        position,
+    }
+}
+
+/// 解析搜索查询
+pub fn parse_search_query(input: &str) -> Result<ParsedQuery, QuerySyntaxError> {
+    let chars: Vec<char> = input.chars().collect();
+    let tokens = tokenize(&chars)?;
+    if tokens.is_empty() {
+        return Err(syntax_error("查询内容为空", 0));
+    }
+
+    let plain = tokens
+        .iter()
+        .all(|token| matches!(&token.kind, TokenKind::Term(term) if term.field.is_none() && !term.phrase));
+
+    let mut parser = Parser {
+        tokens,
+        index: 0,
+        end: chars.len(),
+        depth: 0,
+        terms: 0,
+    };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        let message = match token.kind {
+            TokenKind::RParen => "多余的右括号",
+            _ => "无法识别的查询内容",
+        };
+        return Err(syntax_error(message, token.position));
+    }
+
+    Ok(ParsedQuery { expr, plain })
+}
+
+fn tokenize(chars: &[char]) -> Result<Vec<Token>, QuerySyntaxError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        match c {
+            '(' => {
+                tokens.push(Token { kind: TokenKind::LParen, position: start });
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token { kind: TokenKind::RParen, position: start });
+                i += 1;
+            }
+            '-' if chars.get(i + 1).is_some_and(|next| !next.is_whitespace()) && !starts_number(chars, i + 1) => {
+                tokens.push(Token { kind: TokenKind::Minus, position: start });
+                i += 1;
+            }
+            '"' => {
+                let (text, next) = read_quoted(chars, i)?;
+                i = next;
+                // 引号内容后紧跟冒号时作为字段名，如 "Full Name":Ali
+                if chars.get(i) == Some(&':') {
+                    let (value, phrase, next) = read_field_value(chars, i + 1, start)?;
+                    i = next;
+                    tokens.push(field_token(text, value, phrase, start));
+                } else {
+                    tokens.push(Token {
+                        kind: TokenKind::Term(SearchTerm { field: None, value: text, phrase: true }),
+                        position: start,
+                    });
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while i < chars.len() && !is_word_boundary(chars[i]) {
+                    if chars[i] == ':' && is_field_name(&word) {
+                        let (value, phrase, next) = read_field_value(chars, i + 1, start)?;
+                        i = next;
+                        tokens.push(field_token(word, value, phrase, start));
+                        word = String::new();
+                        break;
+                    }
+                    word.push(chars[i]);
+                    i += 1;
+                }
+                if !word.is_empty() {
+                    let kind = match word.as_str() {
+                        "AND" => TokenKind::And,
+                        "OR" => TokenKind::Or,
+                        "NOT" => TokenKind::Not,
+                        _ => TokenKind::Term(SearchTerm { field: None, value: word, phrase: false }),
+                    };
+                    tokens.push(Token { kind, position: start });
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 减号后紧跟数字（如 -5、-.5）时视为负数而不是排除运算符
+fn starts_number(chars: &[char], start: usize) -> bool {
+    match chars.get(start) {
+        Some(c) if c.is_ascii_digit() => true,
+        Some('.') => chars.get(start + 1).is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+fn is_word_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '"')
+}
+
+/// 冒号前的内容可作为字段名；纯数字不作为字段名，避免把 10:30 这类时间拆开
+fn is_field_name(word: &str) -> bool {
+    !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit())
+}
+
+fn field_token(field: String, value: String, phrase: bool, position: usize) -> Token {
+    Token {
+        kind: TokenKind::Term(SearchTerm { field: Some(field), value, phrase }),
+        position,
+    }
+}
+
+/// 读取引号短语，支持用反斜杠转义引号，返回短语内容和结束引号之后的位置
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QuerySyntaxError> {
+    let mut text = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                text.push(chars[i + 1]);
+                i += 2;
+            }
+            '"' => {
+                if text.trim().is_empty() {
+                    return Err(syntax_error("引号内容为空", start));
+                }
+                return Ok((text, i + 1));
+            }
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(syntax_error("引号未闭合", start))
+}
+
+/// 读取冒号之后的字段值，返回值、是否为短语以及结束位置
+fn read_field_value(
+    chars: &[char],
+    start: usize,
+    field_position: usize,
+) -> Result<(String, bool, usize), QuerySyntaxError> {
+    match chars.get(start) {
+        Some('"') => {
+            let (text, next) = read_quoted(chars, start)?;
+            Ok((text, true, next))
+        }
+        Some(c) if !is_word_boundary(*c) => {
+            let mut value = String::new();
+            let mut i = start;
+            while i < chars.len() && !is_word_boundary(chars[i]) {
+                value.push(chars[i]);
+                i += 1;
+            }
+            Ok((value, false, i))
+        }
+        _ => Err(syntax_error("字段缺少搜索值", field_position)),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+    /// 查询末尾位置，用于报告“查询不完整”
+    end: usize,
+    depth: usize,
+    terms: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr, QuerySyntaxError> {
+        let mut items = vec![self.parse_and()?];
+        while matches!(self.peek(), Some(Token { kind: TokenKind::Or, .. })) {
+            self.next();
+            items.push(self.parse_and()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { QueryExpr::Or(items) })
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr, QuerySyntaxError> {
+        let mut items = vec![self.parse_unary()?];
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::And) => {
+                    self.next();
+                    items.push(self.parse_unary()?);
+                }
+                // 相邻条件之间省略的运算符视为AND
+                Some(TokenKind::Term(_) | TokenKind::Not | TokenKind::Minus | TokenKind::LParen) => {
+                    items.push(self.parse_unary()?);
+                }
+                _ => break,
+            }
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { QueryExpr::And(items) })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr, QuerySyntaxError> {
+        let Some(token) = self.next() else {
+            return Err(syntax_error("查询不完整，缺少搜索条件", self.end));
+        };
+
+        match token.kind {
+            TokenKind::Not | TokenKind::Minus => {
+                let inner = self.nested(token.position, |parser| parser.parse_unary())?;
+                Ok(QueryExpr::Not(Box::new(inner)))
+            }
+            TokenKind::LParen => {
+                let inner = self.nested(token.position, |parser| parser.parse_or())?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => Ok(inner),
+                    _ => Err(syntax_error("括号未闭合", token.position)),
+                }
+            }
+            TokenKind::Term(term) => {
+                self.terms += 1;
+                if self.terms > MAX_TERMS {
+                    return Err(syntax_error(format!("搜索条件过多，最多支持 {} 个", MAX_TERMS), token.position));
+                }
+                Ok(QueryExpr::Term(term))
+            }
+            TokenKind::And | TokenKind::Or => Err(syntax_error("运算符前缺少搜索条件", token.position)),
+            TokenKind::RParen => Err(syntax_error("右括号前缺少搜索条件", token.position)),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        position: usize,
+        parse: impl FnOnce(&mut Self) -> Result<QueryExpr, QuerySyntaxError>,
+    ) -> Result<QueryExpr, QuerySyntaxError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(syntax_error(format!("嵌套层数过多，最多支持 {} 层", MAX_DEPTH), position));
+        }
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+}
+
+/// 从URL查询参数中解析范围筛选，忽略非 filter[...] 参数
+///
+/// 支持的写法（值可以是数字或 YYYY-MM-DD[ HH:MM[:SS]] 格式的日期）：
+/// - `filter[amount]=1000..5000`：闭区间，省略一侧表示不限，如 `1000..`、`..5000`
+/// - `filter[date]>=2024-01-01`、`filter[date]<2024-02-01`：单侧比较
+/// - `filter[amount]=100`：等于
+///
+/// URL解析时 `>=`、`<=` 会在等号处被拆成键和值，这里重新拼接后再解析。
+pub fn parse_range_filters(params: &[(String, String)]) -> Result<Vec<RangeFilter>, String> {
+    let mut filters = Vec::new();
+    for (key, value) in params {
+        let Some(rest) = key.strip_prefix("filter[") else {
+            continue;
+        };
+        let Some((field, suffix)) = rest.split_once(']') else {
+            return Err(format!("筛选参数格式错误: {}", key));
+        };
+        let field = field.trim();
+        if field.is_empty() {
+            return Err(format!("筛选参数缺少字段名: {}", key));
+        }
+
+        let expression = match suffix {
+            "" => value.clone(),
+            ">" | "<" => format!("{}={}", suffix, value),
+            _ if value.is_empty() => suffix.to_string(),
+            _ => return Err(format!("筛选参数格式错误: {}={}", key, value)),
+        };
+        filters.push(parse_range_expression(field, expression.trim())?);
+    }
+    Ok(filters)
+}
+
+fn parse_range_expression(field: &str, expression: &str) -> Result<RangeFilter, String> {
+    let bound = |text: &str, inclusive: bool| -> Result<(RangeValue, bool, bool), String> {
+        let (value, date_only) = parse_range_value(field, text.trim())?;
+        Ok((value, inclusive, date_only))
+    };
+
+    let (lower, upper) = if let Some(text) = expression.strip_prefix(">=") {
+        (Some(bound(text, true)?), None)
+    } else if let Some(text) = expression.strip_prefix("<=") {
+        (None, Some(bound(text, true)?))
+    } else if let Some(text) = expression.strip_prefix('>') {
+        (Some(bound(text, false)?), None)
+    } else if let Some(text) = expression.strip_prefix('<') {
+        (None, Some(bound(text, false)?))
+    } else if let Some((low, high)) = expression.split_once("..") {
+        let lower = if low.trim().is_empty() { None } else { Some(bound(low, true)?) };
+        let upper = if high.trim().is_empty() { None } else { Some(bound(high, true)?) };
+        if lower.is_none() && upper.is_none() {
+            return Err(format!("筛选字段 {} 的范围上下限不能都为空", field));
+        }
+        (lower, upper)
+    } else {
+        let value = bound(expression, true)?;
+        (Some(value.clone()), Some(value))
+    };
+
+    if let (Some((low, ..)), Some((high, ..))) = (&lower, &upper) {
+        let reversed = match (low, high) {
+            (RangeValue::Number(low), RangeValue::Number(high)) => {
+                low.parse::<f64>().unwrap_or_default() > high.parse::<f64>().unwrap_or_default()
+            }
+            (RangeValue::DateTime(low), RangeValue::DateTime(high)) => low > high,
+            _ => return Err(format!("筛选字段 {} 的上下限类型不一致", field)),
+        };
+        if reversed {
+            return Err(format!("筛选字段 {} 的下限大于上限", field));
+        }
+    }
+
+    Ok(RangeFilter {
+        field: field.to_string(),
+        lower: lower.map(|(value, inclusive, date_only)| lower_bound(value, inclusive, date_only)),
+        upper: upper.map(|(value, inclusive, date_only)| upper_bound(value, inclusive, date_only)),
+    })
+}
+
+/// 仅含日期的下限：`> 日期` 等价于 `>= 次日`
+fn lower_bound(value: RangeValue, inclusive: bool, date_only: bool) -> RangeBound {
+    match value {
+        RangeValue::DateTime(datetime) if date_only && !inclusive => RangeBound {
+            value: RangeValue::DateTime(datetime + chrono::Duration::days(1)),
+            inclusive: true,
+        },
+        value => RangeBound { value, inclusive },
+    }
+}
+
+/// 仅含日期的上限：`<= 日期` 需包含当天全天，等价于 `< 次日`
+fn upper_bound(value: RangeValue, inclusive: bool, date_only: bool) -> RangeBound {
+    match value {
+        RangeValue::DateTime(datetime) if date_only && inclusive => RangeBound {
+            value: RangeValue::DateTime(datetime + chrono::Duration::days(1)),
+            inclusive: false,
+        },
+        value => RangeBound { value, inclusive },
+    }
+}
+
+/// 解析边界值，返回值及是否为不含时间的日期
+fn parse_range_value(field: &str, text: &str) -> Result<(RangeValue, bool), String> {
+    let is_number_text = !text.is_empty()
+        && text.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'));
+    if is_number_text && text.parse::<f64>().is_ok_and(|n| n.is_finite()) {
+        return Ok((RangeValue::Number(text.to_string()), false));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok((RangeValue::DateTime(date.and_time(chrono::NaiveTime::MIN)), true));
+    }
+    for format in FILTER_DATETIME_FORMATS {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok((RangeValue::DateTime(datetime), false));
+        }
+    }
+    Err(format!(
+        "筛选字段 {} 的值无法识别: {}（应为数字或 YYYY-MM-DD 格式的日期）",
+        field, text
+    ))
+}
@@ -0,0 +1,131 @@
+// 工作表表头解析模块
+// 负责表头行自动检测、多行表头合并（"上级 / 下级"）以及空白、重复列名的消歧
+use calamine::{Data, Dimensions};
+
+/// 自动检测表头时扫描的最大行数
+const AUTO_DETECT_SCAN_ROWS: usize = 20;
+
+/// 多行表头各级名称之间的分隔符
+const HEADER_LEVEL_SEPARATOR: &str = " / ";
+
+/// 空白列名的前缀，后接从1开始的列号，如 EMPTY_3
+const EMPTY_HEADER_PREFIX: &str = "EMPTY";
+
+/// 自动检测表头行，返回相对于数据区域首行的下标
+///
+/// 标题行、单位说明等通常只填写一两个单元格，因此在前若干行中选择第一个满足以下条件的行：
+/// 非空单元格数量不少于2个且不少于最宽行的一半，并且以文本单元格为主。没有满足条件的行时使用首行。
+pub fn detect_header_row(rows: &[&[Data]]) -> usize {
+    let scanned = &rows[..rows.len().min(AUTO_DETECT_SCAN_ROWS)];
+    let filled_counts: Vec<usize> = scanned
+        .iter()
+        .map(|row| row.iter().filter(|cell| !is_blank(cell)).count())
+        .collect();
+    let max_filled = filled_counts.iter().copied().max().unwrap_or(0);
+
+    scanned
+        .iter()
+        .zip(&filled_counts)
+        .position(|(row, &filled)| {
+            let text_cells = row.iter().filter(|cell| matches!(cell, Data::String(s) if !s.trim().is_empty())).count();
+            filled >= 2 && filled * 2 >= max_filled && text_cells * 2 >= filled
+        })
+        .unwrap_or(0)
+}
+
+fn is_blank(cell: &Data) -> bool {
+    match cell {
+        Data::Empty => true,
+        Data::String(s) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// 用合并单元格左上角的文字填充表头区域内被合并的空白单元格
+///
+/// `texts` 为表头各行的单元格文字，`origin` 为表头区域左上角在工作表中的绝对坐标。
+/// 格式不支持读取合并信息（merged_regions为None）且表头多于一行时，上级表头行中的空白单元格
+/// 沿用左侧最近的非空文字，与常见的横向合并写法一致。
+pub fn fill_merged_header_cells(texts: &mut [Vec<String>], origin: (u32, u32), merged_regions: Option<&[Dimensions]>) {
+    let Some(regions) = merged_regions else {
+        let levels = texts.len();
+        for row in texts.iter_mut().take(levels.saturating_sub(1)) {
+            let mut last = String::new();
+            for text in row.iter_mut() {
+                if text.is_empty() {
+                    text.clone_from(&last);
+                } else {
+                    last.clone_from(text);
+                }
+            }
+        }
+        return;
+    };
+
+    let header_rows = texts.len() as u32;
+    for region in regions {
+        let (top, left) = region.start;
+        let (bottom, right) = region.end;
+        // 只处理左上角位于表头区域内的合并区域，标题行等表头上方的合并不参与
+        if top < origin.0 || top >= origin.0 + header_rows || left < origin.1 {
+            continue;
+        }
+        let source_row = (top - origin.0) as usize;
+        let source_col = (left - origin.1) as usize;
+        let Some(source) = texts[source_row].get(source_col).cloned() else {
+            continue;
+        };
+
+        for row in top..=bottom.min(origin.0 + header_rows - 1) {
+            for col in left..=right {
+                if let Some(text) = texts[(row - origin.0) as usize].get_mut((col - origin.1) as usize) {
+                    if text.is_empty() {
+                        text.clone_from(&source);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 将多行表头按列合并为 "上级 / 下级" 形式，同一列中连续重复的名称（纵向合并）只保留一次
+pub fn combine_header_rows(texts: &[Vec<String>]) -> Vec<String> {
+    let width = texts.iter().map(Vec::len).max().unwrap_or(0);
+    (0..width)
+        .map(|col| {
+            let mut parts: Vec<&str> = Vec::new();
+            for row in texts {
+                let text = row.get(col).map(|text| text.trim()).unwrap_or_default();
+                if !text.is_empty() && parts.last() != Some(&text) {
+                    parts.push(text);
+                }
+            }
+            parts.join(HEADER_LEVEL_SEPARATOR)
+        })
+        .collect()
+}
+
+/// 为空白或重复的列名生成唯一名称：空白列命名为 EMPTY_列号，重复列名依次追加 (2)、(3)…
+pub fn disambiguate_headers(headers: Vec<String>) -> Vec<String> {
+    let mut used = std::collections::HashSet::new();
+    let mut result = Vec::with_capacity(headers.len());
+
+    for (index, header) in headers.into_iter().enumerate() {
+        let base = if header.is_empty() {
+            format!("{}_{}", EMPTY_HEADER_PREFIX, index + 1)
+        } else {
+            header
+        };
+
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while used.contains(&name) {
+            name = format!("{} ({})", base, suffix);
+            suffix += 1;
+        }
+        used.insert(name.clone());
+        result.push(name);
+    }
+
+    result
+}
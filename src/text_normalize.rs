@@ -0,0 +1,45 @@
+// 检索文本规范化模块
+// 导入时写入search_text与搜索时处理查询词使用同一套规则，保证不同输入方式的文字能够互相匹配；
+// 数据库中的excel_normalize_search函数实现相同的规则，用于字段条件及已有数据的迁移
+use unicode_normalization::UnicodeNormalization;
+
+/// 阿拉伯文延长符（Tatweel/Kashida），仅用于排版拉长字形
+const ARABIC_TATWEEL: char = '\u{0640}';
+/// 表意文字空格（全角空格）
+const IDEOGRAPHIC_SPACE: char = '\u{3000}';
+
+/// 规范化用于检索的文本
+///
+/// - NFKC规范化：全角ASCII字符及全角空格折叠为半角（如 `ＡＢＣ１２３` → `ABC123`），
+///   阿拉伯文表现形式（词首、词中、词尾、独立形式及连写字形）折叠为基本字母
+/// - 移除阿拉伯文延长符（部分表现形式分解后也会产生延长符，因此放在NFKC之后）
+pub fn normalize_for_search(text: &str) -> String {
+    text.nfkc().filter(|c| *c != ARABIC_TATWEEL).collect()
+}
+
+/// 全角ASCII（U+FF01-U+FF5E）及全角空格折叠为对应的半角字符
+///
+/// 只做宽度折叠、不改变其他字符，用于电话号码等需要保留原有写法的转换；检索文本使用normalize_for_search。
+pub fn fold_full_width(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        IDEOGRAPHIC_SPACE => ' ',
+        _ => c,
+    }
+}
+
+/// 是否含有中日韩文字（汉字、假名、谚文）
+///
+/// 这些文字之间不用空格分词，全文检索会把一段连续文字切成一个词素，搜索时需要补充子串匹配。
+pub fn contains_cjk(text: &str) -> bool {
+    text.chars().any(|c| {
+        matches!(c,
+            '\u{3040}'..='\u{30FF}'     // 平假名、片假名
+            | '\u{3400}'..='\u{4DBF}'   // CJK扩展A
+            | '\u{4E00}'..='\u{9FFF}'   // CJK统一汉字
+            | '\u{AC00}'..='\u{D7AF}'   // 谚文音节
+            | '\u{F900}'..='\u{FAFF}'   // CJK兼容汉字
+            | '\u{20000}'..='\u{3134F}' // CJK扩展B-G
+        )
+    })
+}
@@ -0,0 +1,88 @@
+// upsert模式的键列匹配模块
+// 按键列的取值在文件内检查重复、与工作区中已有的数据行对应，并判断已有的行是否需要更新
+use crate::column_profile::same_column;
+use crate::models::entity::excel_data;
+use crate::row_validation::RowViolation;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 已有数据行与导入行比较后的处理方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpsertAction {
+    /// 工作区中没有相同键的行，作为新行插入
+    Insert,
+    /// 相同键的行取值都没有变化
+    Unchanged,
+    /// 需要更新的已有行ID（工作区中有多行使用同一键时全部更新）
+    Update(Vec<i32>),
+}
+
+/// 键列的取值（列名不区分大小写，首尾空白不参与比较），没有该列或值为空时返回None
+pub fn key_value(data: &HashMap<String, Value>, column: &str) -> Option<String> {
+    let value = data
+        .get(column)
+        .or_else(|| data.iter().find(|(key, _)| same_column(key, column)).map(|(_, value)| value))?;
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// 数据行的完整键，任一键列为空时返回None
+pub fn row_key(data: &HashMap<String, Value>, key_columns: &[String]) -> Option<Vec<String>> {
+    key_columns.iter().map(|column| key_value(data, column)).collect()
+}
+
+/// 检查键列：键列必须有值，且同一键在文件内只能出现一次，不满足的行追加违规记录
+///
+/// rows依次为 (工作表, 行号, 数据, 违规记录)，调用方只传入尚未被拒绝的行。
+pub fn check_keys<'a>(
+    key_columns: &[String],
+    rows: impl IntoIterator<Item = (&'a str, usize, &'a HashMap<String, Value>, &'a mut Vec<RowViolation>)>,
+) {
+    let mut seen: HashMap<Vec<String>, String> = HashMap::new();
+
+    for (sheet_name, row_number, data, violations) in rows {
+        let Some(key) = row_key(data, key_columns) else {
+            for column in key_columns {
+                if key_value(data, column).is_none() {
+                    violations.push(RowViolation::new(column, "key", format!("键列 {} 不能为空", column)));
+                }
+            }
+            continue;
+        };
+        match seen.get(&key) {
+            Some(first) => violations.push(RowViolation::new(
+                &key_columns.join(", "),
+                "key",
+                format!("键 ({}) 与{}重复", key.join(", "), first),
+            )),
+            None => {
+                seen.insert(key, format!("工作表 {} 第 {} 行", sheet_name, row_number));
+            }
+        }
+    }
+}
+
+/// 按键找到工作区中已有的数据行，比较data_json与typed_json决定插入、跳过还是更新
+pub fn match_existing(
+    key: Option<&Vec<String>>,
+    data_json: &Value,
+    typed_json: &Value,
+    existing: &HashMap<Vec<String>, Vec<excel_data::Model>>,
+) -> UpsertAction {
+    let Some(matched) = key.and_then(|key| existing.get(key)) else {
+        return UpsertAction::Insert;
+    };
+    let changed: Vec<i32> = matched
+        .iter()
+        .filter(|row| row.data_json != *data_json || row.typed_json != *typed_json)
+        .map(|row| row.id)
+        .collect();
+    if changed.is_empty() {
+        UpsertAction::Unchanged
+    } else {
+        UpsertAction::Update(changed)
+    }
+}
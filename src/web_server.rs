@@ -1,22 +1,33 @@
 use crate::models::{
-    AuthResponse, BatchTranslationRequest, BatchTranslationResponse, I18nStatusResponse, LanguageResponse, SearchResponse,
-    StatsResponse, TranslationResponse, UserResponse, WorkspaceResponse,
+    AuthResponse, BatchTranslationRequest, BatchTranslationResponse, ExportJobResponse, I18nStatusResponse,
+    ImportJobResponse, LanguageResponse, SearchResponse, StatsResponse, TranslationResponse, UserResponse,
+    WorkspaceResponse,
 };
-use crate::models::entity::{auth_tokens, files, users, workspaces};
+use crate::models::entity::{auth_tokens, column_profiles, export_jobs, files, import_jobs, users, workspaces};
+use crate::row_validation::{self, RejectionOwner};
 use crate::i18n_manager::I18nManager;
+use crate::column_profile::{self, ColumnMapping};
+use crate::file_format::{detect_file_format, FileFormat};
+use crate::search_export::{ExportColumns, ExportContext, ExportFormat, ExportInfo, ExportMetadataLabels, SystemColumnLabels};
+use crate::search_query::{parse_range_filters, parse_search_query, QuerySyntaxError, SearchCriteria};
+use crate::export_jobs::{download_state, DownloadState, ExportJobQueue, ExportRequest, DEFAULT_EXPORT_RETENTION_HOURS};
+use crate::import_jobs::{DuplicateUpload, ImportJobQueue, JobEvent, JOB_STATUS_COMPLETED, JOB_STATUS_FAILED};
+use crate::excel_processor_sea::{DryRunReport, ExcelProcessor, ImportMode, ImportOptions, SearchMode, DEFAULT_FUZZY_THRESHOLD, DEFAULT_PROGRESS_INTERVAL};
 use axum::{
     extract::{DefaultBodyLimit, Multipart, Path, Query, State},
     http::{StatusCode, header, HeaderMap},
-    response::{Html, Response},
+    response::{Html, IntoResponse, Response},
+    response::sse::{Event, KeepAlive, Sse},
     routing::{get, post, put},
     Json, Router,
 };
 use tower_http::services::ServeDir;
 use sea_orm::DatabaseConnection;
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tokio::fs;
+use tokio::io::AsyncReadExt;
 use std::net::SocketAddr;
 use std::path::Path as StdPath;
 use std::sync::{Arc, Mutex};
@@ -68,6 +79,8 @@ struct AppState {
     i18n_manager: Arc<Mutex<I18nManager>>,
     stats_cache: Arc<Mutex<StatsCache>>,
     upload_dir: String,
+    import_queue: ImportJobQueue,
+    export_queue: ExportJobQueue,
 }
 
 #[derive(Deserialize)]
@@ -76,6 +89,88 @@ pub struct SearchQuery {
     workspace_id: Option<i32>,
     limit: Option<i64>,
     offset: Option<i64>,
+    /// 搜索模式：fulltext（默认）或 fuzzy
+    mode: Option<String>,
+    /// 模糊搜索的相似度阈值（0到1）
+    threshold: Option<f64>,
+}
+
+fn parse_search_mode(params: &SearchQuery) -> Result<SearchMode, (StatusCode, String)> {
+    match params.mode.as_deref().unwrap_or("fulltext") {
+        "fulltext" => Ok(SearchMode::FullText),
+        "fuzzy" => {
+            let threshold = params.threshold.unwrap_or(DEFAULT_FUZZY_THRESHOLD);
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err((StatusCode::BAD_REQUEST, "相似度阈值必须在0到1之间".to_string()));
+            }
+            Ok(SearchMode::Fuzzy { threshold })
+        }
+        other => Err((StatusCode::BAD_REQUEST, format!("不支持的搜索模式: {}", other))),
+    }
+}
+
+/// 搜索与导出接口的错误：查询语法错误返回带出错位置的JSON，其余错误沿用文本响应
+pub enum SearchApiError {
+    Message(StatusCode, String),
+    Syntax { query: String, error: QuerySyntaxError },
+}
+
+impl From<(StatusCode, String)> for SearchApiError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        SearchApiError::Message(status, message)
+    }
+}
+
+impl IntoResponse for SearchApiError {
+    fn into_response(self) -> Response {
+        match self {
+            SearchApiError::Message(status, message) => (status, message).into_response(),
+            SearchApiError::Syntax { query, error } => (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "query_syntax_error",
+                    "message": error.message,
+                    "position": error.position,
+                    "query": query,
+                })),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// 解析查询语句与 filter[字段] 范围筛选参数，两者至少提供一项
+fn parse_search_criteria(
+    query_text: &str,
+    raw_params: &[(String, String)],
+) -> Result<SearchCriteria, SearchApiError> {
+    let query = if query_text.trim().is_empty() {
+        None
+    } else {
+        Some(parse_search_query(query_text).map_err(|error| SearchApiError::Syntax {
+            query: query_text.to_string(),
+            error,
+        })?)
+    };
+    let filters = parse_range_filters(raw_params).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let criteria = SearchCriteria { query, filters };
+    if criteria.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "查询参数不能为空".to_string()).into());
+    }
+    Ok(criteria)
+}
+
+/// 流式导出时等待发送的内容块数，下载较慢时导出暂停读取数据库
+const EXPORT_CHANNEL_CAPACITY: usize = 8;
+/// 发送导出文件时每次读取的字节数
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// 导出格式：xlsx（默认）、csv、json、ndjson
+    #[serde(default)]
+    format: ExportFormat,
 }
 
 #[derive(Deserialize)]
@@ -83,6 +178,77 @@ pub struct StatsQuery {
     workspace_id: Option<i32>,
 }
 
+/// 试导入时每个工作表默认返回的样例行数
+const DEFAULT_DRY_RUN_SAMPLE_ROWS: usize = 20;
+/// 试导入样例行数上限
+const MAX_DRY_RUN_SAMPLE_ROWS: usize = 200;
+
+#[derive(Deserialize)]
+pub struct DryRunQuery {
+    /// 每个工作表返回的样例行数
+    sample_rows: Option<usize>,
+}
+
+/// 上传的文件与工作区中已导入的文件（或排队中的任务）内容相同时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// 照常导入，在响应中列出内容相同的文件
+    #[default]
+    Warn,
+    /// 不导入，删除上传的文件
+    Skip,
+    /// 不导入，返回已导入的文件（或进行中的任务）供前端直接使用
+    Link,
+}
+
+/// 上传的文件与已有文件重复时实际的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+    /// 照常登记了导入任务
+    Warned,
+    /// 未导入，上传的文件已删除
+    Skipped,
+    /// 未导入，响应中返回已导入的文件或进行中的任务
+    Linked,
+}
+
+impl DuplicatePolicy {
+    /// 按处理方式决定重复文件的处理结果，没有重复时返回None
+    pub fn action(self, has_duplicates: bool) -> Option<DuplicateAction> {
+        if !has_duplicates {
+            return None;
+        }
+        Some(match self {
+            DuplicatePolicy::Warn => DuplicateAction::Warned,
+            DuplicatePolicy::Skip => DuplicateAction::Skipped,
+            DuplicatePolicy::Link => DuplicateAction::Linked,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    #[serde(default)]
+    on_duplicate: DuplicatePolicy,
+}
+
+/// 拒绝行列表默认和最多返回的条数
+const DEFAULT_REJECTION_PAGE_SIZE: u64 = 100;
+const MAX_REJECTION_PAGE_SIZE: u64 = 1000;
+
+#[derive(Deserialize)]
+pub struct RejectionListQuery {
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmImportRequest {
+    confirm_token: String,
+}
+
 #[derive(Deserialize)]
 pub struct RegisterRequest {
     username: String,
@@ -124,8 +290,11 @@ fn bearer_token_from_headers(headers: &HeaderMap) -> Option<String> {
 }
 
 async fn authenticate_user(headers: &HeaderMap, db: &DatabaseConnection) -> Result<users::Model, (StatusCode, String)> {
-    let token = bearer_token_from_headers(headers)
-        .ok_or((StatusCode::UNAUTHORIZED, "缺少认证Token".to_string()))?;
+    authenticate_token(bearer_token_from_headers(headers), db).await
+}
+
+async fn authenticate_token(token: Option<String>, db: &DatabaseConnection) -> Result<users::Model, (StatusCode, String)> {
+    let token = token.ok_or((StatusCode::UNAUTHORIZED, "缺少认证Token".to_string()))?;
 
     let now = chrono::Utc::now();
     let token_model = auth_tokens::Entity::find()
@@ -154,11 +323,28 @@ async fn get_workspace_by_id(
         .ok_or((StatusCode::NOT_FOUND, "workspace不存在".to_string()))
 }
 
+/// 清理上次运行时中断上传留下的临时文件
+async fn remove_stale_partial_uploads(upload_dir: &str) {
+    let Ok(mut entries) = fs::read_dir(upload_dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("part") {
+            match fs::remove_file(&path).await {
+                Ok(_) => info!("已清理未完成的上传文件: {}", path.display()),
+                Err(e) => warn!("清理未完成的上传文件失败 {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
 pub async fn start_server(db: DatabaseConnection, port: u16) -> Result<(), Box<dyn std::error::Error>> {
     let upload_dir = std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "./uploads".to_string());
     if !StdPath::new(&upload_dir).exists() {
         fs::create_dir_all(&upload_dir).await?;
     }
+    remove_stale_partial_uploads(&upload_dir).await;
 
     // 初始化多语言管理器
     info!("初始化多语言管理器...");
@@ -167,7 +353,48 @@ pub async fn start_server(db: DatabaseConnection, port: u16) -> Result<(), Box<d
     
     // 初始化统计缓存
     let stats_cache = Arc::new(Mutex::new(StatsCache::new()));
+
+    // 启动后台导入任务队列，先处理上次运行时中断的任务
+    let import_workers: usize = std::env::var("IMPORT_WORKERS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2);
+    let progress_interval: usize = std::env::var("IMPORT_PROGRESS_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_PROGRESS_INTERVAL);
+    let import_queue = ImportJobQueue::new(db.clone(), progress_interval);
+    import_queue.recover_interrupted_jobs().await?;
+    import_queue.remove_expired_dry_runs().await?;
+    import_queue.start_workers(import_workers);
+
+    // 启动后台导出任务队列，导出文件保存在导出目录中，超过保留期后删除
+    let export_dir = std::env::var("EXPORT_DIR").unwrap_or_else(|_| "./exports".to_string());
+    if !StdPath::new(&export_dir).exists() {
+        fs::create_dir_all(&export_dir).await?;
+    }
+    let export_workers: usize = std::env::var("EXPORT_WORKERS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1);
+    let export_retention_hours: i64 = std::env::var("EXPORT_RETENTION_HOURS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_EXPORT_RETENTION_HOURS);
+    let export_queue = ExportJobQueue::new(db.clone(), export_dir, export_retention_hours);
+    export_queue.recover_interrupted_jobs().await?;
+    export_queue.remove_expired_exports().await?;
+    export_queue.start_workers(export_workers);
     
+    // 请求体大小上限，上传文件流式写入磁盘，调大该值不会增加内存占用
+    let max_upload_mb: usize = std::env::var("MAX_UPLOAD_SIZE_MB")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(50);
+    let max_upload_bytes = max_upload_mb.saturating_mul(1024 * 1024);
+    info!("上传大小上限: {} MB", max_upload_mb);
+
     // 配置CORS
     info!("配置CORS策略...");
     let cors = CorsLayer::very_permissive();
@@ -179,6 +406,8 @@ pub async fn start_server(db: DatabaseConnection, port: u16) -> Result<(), Box<d
         i18n_manager: i18n_manager.clone(),
         stats_cache: stats_cache.clone(),
         upload_dir,
+        import_queue,
+        export_queue,
     };
     
     // 创建路由
@@ -189,10 +418,30 @@ pub async fn start_server(db: DatabaseConnection, port: u16) -> Result<(), Box<d
         .route("/api/auth/login", post(login_handler))
         .route("/api/workspaces", get(list_workspaces_handler).post(create_workspace_handler))
         .route("/api/workspaces/{id}", put(update_workspace_handler).delete(delete_workspace_handler))
+        .route(
+            "/api/workspaces/{id}/column-profile",
+            get(get_column_profile_handler)
+                .put(save_column_profile_handler)
+                .delete(delete_column_profile_handler),
+        )
+        .route("/api/workspaces/{id}/column-profile/versions", get(list_column_profile_versions_handler))
         .route("/api/workspaces/{id}/upload", post(upload_to_workspace_handler))
+        .route("/api/workspaces/{id}/upload/preview", post(preview_upload_handler))
+        .route("/api/workspaces/{id}/upload/dry-run", post(dry_run_upload_handler))
+        .route("/api/workspaces/{id}/upload/confirm", post(confirm_upload_handler))
+        .route("/api/workspaces/{id}/import-events", get(workspace_import_events_handler))
+        .route("/api/files/{id}/rejections", get(list_file_rejections_handler))
+        .route("/api/files/{id}/rejections/export", get(export_file_rejections_handler))
+        .route("/api/jobs/{id}", get(get_import_job_handler))
+        .route("/api/jobs/{id}/events", get(import_job_events_handler))
+        .route("/api/jobs/{id}/rejections", get(list_job_rejections_handler))
+        .route("/api/jobs/{id}/rejections/export", get(export_job_rejections_handler))
         .route("/api/search", get(search_handler))
         .route("/api/stats", get(stats_handler))
         .route("/api/export", get(export_handler))
+        .route("/api/exports", get(list_export_jobs_handler).post(create_export_job_handler))
+        .route("/api/exports/{id}", get(get_export_job_handler))
+        .route("/api/exports/{id}/download", get(download_export_handler))
         // 多语言API路由
         .route("/api/i18n/languages", get(get_languages_handler))
         .route("/api/i18n/status", get(get_i18n_status_handler))
@@ -201,7 +450,7 @@ pub async fn start_server(db: DatabaseConnection, port: u16) -> Result<(), Box<d
         .route("/api/i18n/reload", post(reload_translations_handler))
         // 静态文件服务
         .nest_service("/static", ServeDir::new("static"))
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024))
+        .layer(DefaultBodyLimit::max(max_upload_bytes))
         .layer(cors)
         .with_state(app_state);
     debug!("路由创建完成");
@@ -519,7 +768,7 @@ async fn home_handler() -> Html<&'static str> {
         <!-- Search Section -->
         <div class="bg-white border-b border-gray-300 px-4 py-4">
             <div class="max-w-4xl mx-auto">
-                <input id="uploadInput" type="file" accept=".xlsx,.xls" multiple style="display:none;" onchange="uploadSelectedFiles()">
+                <input id="uploadInput" type="file" accept=".xlsx,.xls,.xlsb,.ods,.csv,.tsv" multiple style="display:none;" onchange="uploadSelectedFiles()">
                 <div class="flex items-center space-x-4 mb-3">
                     <div id="workspaceControls" style="display:none;">
                         <select id="workspaceSelect" class="excel-search-bar px-3 py-2 text-sm min-w-40" onchange="onWorkspaceChange()">
@@ -538,6 +787,10 @@ async fn home_handler() -> Html<&'static str> {
                             </svg>
                         </div>
                     </div>
+                    <label class="flex items-center text-sm text-gray-600 whitespace-nowrap" title="按子串和相似度匹配，可找到写法不完全一致的内容" data-i18n-title="search.fuzzy_hint">
+                        <input type="checkbox" id="fuzzySearchToggle" class="mr-1">
+                        <span data-i18n="search.fuzzy">模糊匹配</span>
+                    </label>
                     <button class="excel-button px-6 py-2 rounded font-medium" onclick="performSearch()">
                         <span data-i18n="search.button">搜索</span>
                     </button>
@@ -548,7 +801,7 @@ async fn home_handler() -> Html<&'static str> {
                 <!-- 搜索提示和按钮在同一行 -->
                 <div class="flex items-center justify-between">
                     <div class="search-tips px-3 py-2 text-sm">
-                        <span data-i18n="search.tips">💡 搜索提示：输入多个关键词用空格分隔，如"阿迪力 阿布拉"可匹配包含这两个词的内容</span>
+                        <span data-i18n="search.tips">💡 搜索提示：多个关键词用空格分隔；支持 字段:值、"完整短语"、AND / OR / NOT、-排除 和括号分组，如 城市:乌鲁木齐 -"测试数据"</span>
                     </div>
                 </div>
             </div>
@@ -725,6 +978,7 @@ async fn home_handler() -> Html<&'static str> {
         let lastClickedCell = null; // 最后点击的单元格，用于Shift选择
 
         let currentQuery = '';
+        let currentSearchMode = 'fulltext';
         let currentPage = 0;
         const pageSize = 50;
         const WORKSPACE_STORAGE_KEY = 'selected_workspace_id';
@@ -1272,6 +1526,89 @@ async fn home_handler() -> Html<&'static str> {
             });
         }
 
+        function sleep(ms) {
+            return new Promise(resolve => setTimeout(resolve, ms));
+        }
+
+        // 轮询后台导入任务直到完成或失败（浏览器不支持EventSource时使用）
+        async function pollImportJob(job, fileName) {
+            while (true) {
+                const response = await fetch(`/api/jobs/${job.id}`, { headers: getAuthHeaders() });
+                if (!response.ok) {
+                    throw new Error(await response.text());
+                }
+                const current = await response.json();
+                if (current.status === 'completed') {
+                    return current;
+                }
+                if (current.status === 'failed') {
+                    throw new Error(`${t('upload.import_failed', '导入失败')}: ${fileName} ${current.error || ''}`);
+                }
+                const text = current.status === 'queued'
+                    ? t('upload.import_queued', '排队等待导入: {file}', { file: fileName })
+                    : t('upload.importing', '正在导入: {file}', { file: fileName });
+                const detail = current.current_sheet
+                    ? t('upload.import_progress', '工作表: {sheet}，已处理 {rows} 行', { sheet: current.current_sheet, rows: current.rows_processed })
+                    : '';
+                setUploadProgress(100, text, detail, true);
+                await sleep(1000);
+            }
+        }
+
+        function importStageText(status, fileName) {
+            return status === 'queued'
+                ? t('upload.import_queued', '排队等待导入: {file}', { file: fileName })
+                : t('upload.importing', '正在导入: {file}', { file: fileName });
+        }
+
+        // 通过SSE接收导入进度，直到文件导入完成或失败
+        function waitForImportJob(job, fileName) {
+            if (!window.EventSource) {
+                return pollImportJob(job, fileName);
+            }
+            return new Promise((resolve, reject) => {
+                const source = new EventSource(`/api/jobs/${job.id}/events?token=${encodeURIComponent(currentToken)}`);
+                let sheetTotal = 0;
+                const finish = (callback, value) => {
+                    source.close();
+                    callback(value);
+                };
+                const fail = (error) => finish(reject, new Error(`${t('upload.import_failed', '导入失败')}: ${fileName} ${error || ''}`));
+
+                source.addEventListener('snapshot', (e) => {
+                    const current = JSON.parse(e.data);
+                    if (current.status === 'completed') {
+                        finish(resolve, current);
+                    } else if (current.status === 'failed') {
+                        fail(current.error);
+                    } else {
+                        const detail = current.current_sheet
+                            ? t('upload.import_progress', '工作表: {sheet}，已处理 {rows} 行', { sheet: current.current_sheet, rows: current.rows_processed })
+                            : '';
+                        setUploadProgress(100, importStageText(current.status, fileName), detail, true);
+                    }
+                });
+                source.addEventListener('sheet_started', (e) => {
+                    const data = JSON.parse(e.data);
+                    sheetTotal = data.total_rows;
+                    setUploadProgress(0, importStageText('running', fileName), t('upload.import_progress', '工作表: {sheet}，已处理 {rows} 行', { sheet: data.sheet, rows: 0 }));
+                });
+                source.addEventListener('rows_inserted', (e) => {
+                    const data = JSON.parse(e.data);
+                    const percent = sheetTotal > 0 ? data.sheet_rows * 100 / sheetTotal : 100;
+                    setUploadProgress(percent, importStageText('running', fileName), t('upload.import_progress', '工作表: {sheet}，已处理 {rows} 行', { sheet: data.sheet, rows: data.file_rows }));
+                });
+                source.addEventListener('file_done', (e) => finish(resolve, JSON.parse(e.data)));
+                source.addEventListener('failed', (e) => fail(JSON.parse(e.data).error));
+                source.onerror = () => {
+                    // 连接被拒绝（如Token失效）时浏览器不会重连，改为轮询获取最终状态
+                    if (source.readyState === EventSource.CLOSED) {
+                        pollImportJob(job, fileName).then(resolve, reject);
+                    }
+                };
+            });
+        }
+
         async function uploadSelectedFiles() {
             const workspaceId = uploadWorkspaceId || currentWorkspaceId;
             const ws = workspaceId ? workspaceList.find(w => w.id === workspaceId) : null;
@@ -1297,7 +1634,7 @@ async fn home_handler() -> Html<&'static str> {
             try {
                 for (let i = 0; i < filesToUpload.length; i += 1) {
                     const file = filesToUpload[i];
-                    await uploadOneFileWithProgress(ws.id, file, i + 1, filesToUpload.length);
+                    const responseText = await uploadOneFileWithProgress(ws.id, file, i + 1, filesToUpload.length);
                     const donePercent = ((i + 1) / filesToUpload.length) * 100;
                     setUploadProgress(
                         donePercent,
@@ -1305,6 +1642,10 @@ async fn home_handler() -> Html<&'static str> {
                         t('upload.wait_indexing', '等待服务端检索: {file}', { file: file.name }),
                         true
                     );
+                    const uploadResult = JSON.parse(responseText || '{}');
+                    for (const job of (uploadResult.jobs || [])) {
+                        await waitForImportJob(job, file.name);
+                    }
                 }
                 setUploadProgress(100, t('upload.all_uploaded', '全部文件上传完成'), t('upload.refreshing', '正在刷新数据...'), false);
                 loadStats();
@@ -1382,6 +1723,7 @@ async fn home_handler() -> Html<&'static str> {
             }
 
             currentQuery = query;
+            currentSearchMode = document.getElementById('fuzzySearchToggle').checked ? 'fuzzy' : 'fulltext';
             currentPage = 0;
             search(query, currentPage);
         }
@@ -1401,19 +1743,34 @@ async fn home_handler() -> Html<&'static str> {
                 </div>
             `;
 
-            // 处理多关键词搜索 - 提取关键词用于高亮显示
-            const keywords = query.trim().split(/\s+/).filter(k => k.length > 0);
+            // 从查询语法中提取搜索词用于高亮显示
+            const keywords = extractHighlightTerms(query);
 
             const workspaceParam = currentWorkspaceId ? `&workspace_id=${currentWorkspaceId}` : '';
-            fetch(`/api/search?q=${encodeURIComponent(query)}&limit=${pageSize}&offset=${offset}${workspaceParam}`, {
+            fetch(`/api/search?q=${encodeURIComponent(query)}&mode=${currentSearchMode}&limit=${pageSize}&offset=${offset}${workspaceParam}`, {
                 headers: getAuthHeaders()
             })
-                .then(response => response.json())
+                .then(async response => {
+                    if (!response.ok) {
+                        const body = await response.text();
+                        let detail = null;
+                        try { detail = JSON.parse(body); } catch (e) {}
+                        if (detail && detail.error === 'query_syntax_error') {
+                            throw { syntaxError: detail };
+                        }
+                        throw new Error(body);
+                    }
+                    return response.json();
+                })
                 .then(data => {
                     displayResults(data, keywords);
                     updatePagination(data);
                 })
                 .catch(error => {
+                    if (error && error.syntaxError) {
+                        showQuerySyntaxError(error.syntaxError);
+                        return;
+                    }
                     console.error('搜索失败:', error);
                     const errorText = window.i18n ? window.i18n.translate('search.failed') : '搜索失败，请重试';
                     document.getElementById('results').innerHTML = `
@@ -1426,6 +1783,45 @@ async fn home_handler() -> Html<&'static str> {
                 });
         }
 
+        // 提取查询中需要高亮的搜索词：去掉字段前缀、引号、运算符和括号，排除条件不高亮
+        function extractHighlightTerms(query) {
+            const terms = [];
+            const tokenPattern = /(-?)(?:(?:"(?:[^"\\]|\\.)*"|[^\s()":]+):)?(?:"((?:[^"\\]|\\.)*)"|([^\s()"]+))/g;
+            let excludeNext = false;
+            let match;
+            while ((match = tokenPattern.exec(query)) !== null) {
+                const term = match[2] !== undefined ? match[2].replace(/\\(.)/g, '$1') : match[3];
+                if (term === 'NOT') {
+                    excludeNext = true;
+                    continue;
+                }
+                if (term === 'AND' || term === 'OR') continue;
+                if (!match[1] && !excludeNext && term.trim()) {
+                    terms.push(term);
+                }
+                excludeNext = false;
+            }
+            return terms;
+        }
+
+        // 显示查询语法错误，并在查询下方标出出错位置
+        function showQuerySyntaxError(detail) {
+            const titleText = window.i18n ? window.i18n.translate('search.syntax_error') : '查询语法错误';
+            const escape = text => String(text).replace(/[&<>"']/g, c => ({ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#39;' }[c]));
+            const chars = Array.from(detail.query || '');
+            const marker = chars.slice(0, detail.position).map(c => c === '\t' ? '\t' : ' ').join('') + '^';
+            document.getElementById('exportResultsBtn').style.display = 'none';
+            document.getElementById('pagination').classList.add('hidden');
+            document.getElementById('results').innerHTML = `
+                <div class="text-center py-16">
+                    <div class="text-red-600">
+                        <p class="text-lg">${titleText}: ${escape(detail.message)}</p>
+                        <pre class="inline-block text-left mt-4 text-sm bg-red-50 rounded px-4 py-2">${escape(chars.join(''))}\n${marker}</pre>
+                    </div>
+                </div>
+            `;
+        }
+
         // 高亮显示关键词
         function highlightKeywords(text, keywords) {
             if (!keywords || keywords.length === 0) return text;
@@ -1993,7 +2389,7 @@ async fn home_handler() -> Html<&'static str> {
             exportBtn.textContent = '导出中...';
 
             const workspaceParam = currentWorkspaceId ? `&workspace_id=${currentWorkspaceId}` : '';
-            const exportUrl = `/api/export?q=${encodeURIComponent(query)}${workspaceParam}`;
+            const exportUrl = `/api/export?q=${encodeURIComponent(query)}&mode=${currentSearchMode}${workspaceParam}`;
 
             fetch(exportUrl, {
                 headers: getAuthHeaders()
@@ -2260,6 +2656,9 @@ async fn delete_workspace_handler(
             }
         }
     }
+    if let Err(e) = app_state.export_queue.remove_workspace_exports(workspace_id).await {
+        warn!("删除workspace导出文件失败: workspace_id={}, err={}", workspace_id, e);
+    }
 
     workspaces::Entity::delete_by_id(workspace_id)
         .exec(&app_state.db)
@@ -2312,10 +2711,128 @@ async fn list_workspaces_handler(
     Ok(Json(resp))
 }
 
+/// 可以查看工作区的列映射规则：公开工作区或工作区拥有者
+async fn authorize_column_profile_read(
+    db: &DatabaseConnection,
+    headers: &HeaderMap,
+    workspace_id: i32,
+) -> Result<(), (StatusCode, String)> {
+    let workspace = get_workspace_by_id(db, workspace_id).await?;
+    if !workspace.is_public {
+        let user = authenticate_user(headers, db).await?;
+        if user.id != workspace.owner_id {
+            return Err((StatusCode::FORBIDDEN, "无权限访问该workspace".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// 修改工作区的列映射规则：仅工作区拥有者，返回当前用户
+async fn authorize_column_profile_write(
+    db: &DatabaseConnection,
+    headers: &HeaderMap,
+    workspace_id: i32,
+) -> Result<users::Model, (StatusCode, String)> {
+    let user = authenticate_user(headers, db).await?;
+    let workspace = get_workspace_by_id(db, workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "仅workspace拥有者可修改列映射规则".to_string()));
+    }
+    Ok(user)
+}
+
+fn column_profile_response(profile: column_profiles::Model) -> serde_json::Value {
+    serde_json::json!({
+        "workspace_id": profile.workspace_id,
+        "version": profile.version,
+        "rules": profile.rules,
+        "updated_by": profile.updated_by,
+        "created_at": profile.created_at,
+        "updated_at": profile.updated_at,
+    })
+}
+
+async fn get_column_profile_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    authorize_column_profile_read(&app_state.db, &headers, workspace_id).await?;
+    let profile = column_profile::find_profile(&app_state.db, workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询列映射规则失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "该workspace没有列映射规则".to_string()))?;
+    Ok(Json(column_profile_response(profile)))
+}
+
+/// 新建或更新列映射规则，每次保存生成新版本，对之后的导入生效
+async fn save_column_profile_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+    Json(mapping): Json<ColumnMapping>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = authorize_column_profile_write(&app_state.db, &headers, workspace_id).await?;
+    mapping.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let profile = column_profile::save_profile(&app_state.db, workspace_id, user.id, &mapping)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存列映射规则失败: {}", e)))?;
+    Ok(Json(column_profile_response(profile)))
+}
+
+async fn delete_column_profile_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = authorize_column_profile_write(&app_state.db, &headers, workspace_id).await?;
+    let deleted = column_profile::delete_profile(&app_state.db, workspace_id, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("删除列映射规则失败: {}", e)))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, "该workspace没有列映射规则".to_string()));
+    }
+    Ok(Json(serde_json::json!({
+        "workspace_id": workspace_id,
+        "deleted": true
+    })))
+}
+
+/// 列映射规则的历史版本，从新到旧，删除规则的版本rules为null
+async fn list_column_profile_versions_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    authorize_column_profile_read(&app_state.db, &headers, workspace_id).await?;
+    let versions = column_profile::list_versions(&app_state.db, workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询列映射规则历史失败: {}", e)))?;
+
+    let versions: Vec<serde_json::Value> = versions
+        .into_iter()
+        .map(|version| {
+            serde_json::json!({
+                "version": version.version,
+                "rules": version.rules,
+                "created_by": version.created_by,
+                "created_at": version.created_at,
+            })
+        })
+        .collect();
+    Ok(Json(serde_json::json!({
+        "workspace_id": workspace_id,
+        "versions": versions
+    })))
+}
+
 async fn upload_to_workspace_handler(
     State(app_state): State<AppState>,
     Path(workspace_id): Path<i32>,
     headers: HeaderMap,
+    Query(options): Query<ImportOptions>,
+    Query(params): Query<UploadQuery>,
     mut multipart: Multipart,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
     let user = authenticate_user(&headers, &app_state.db).await?;
@@ -2323,50 +2840,607 @@ async fn upload_to_workspace_handler(
     if workspace.owner_id != user.id {
         return Err((StatusCode::FORBIDDEN, "仅workspace拥有者可上传".to_string()));
     }
+    options.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ensure_upsert_key_columns(&app_state.db, workspace_id, &options).await?;
 
-    let mut imported = 0i32;
-    let processor = crate::excel_processor_sea::ExcelProcessor::new(app_state.db.clone());
+    let mut jobs = Vec::new();
+    let mut duplicates = Vec::new();
 
     while let Some(field) = multipart
         .next_field()
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("读取上传字段失败: {}", e)))?
+        .map_err(|e| (e.status(), format!("读取上传字段失败: {}", e.body_text())))?
     {
-        let file_name = field.file_name().unwrap_or("upload.xlsx").to_string();
-        let file_name_lower = file_name.to_lowercase();
-        if !file_name_lower.ends_with(".xlsx") && !file_name_lower.ends_with(".xls") {
+        // 非文件字段和空文件直接跳过
+        let Some(file_name) = field.file_name().map(ToOwned::to_owned) else {
+            continue;
+        };
+        let Some(stored) = store_upload_field(&app_state.upload_dir, workspace_id, &file_name, field).await? else {
+            continue;
+        };
+        let full_path = stored.path;
+        let file_hash = stored.file_hash;
+
+        // 按文件哈希检查工作区中是否已导入过相同内容的文件
+        let duplicate_of = match app_state.import_queue.find_duplicates(workspace_id, &file_hash).await {
+            Ok(duplicate_of) => duplicate_of,
+            Err(e) => {
+                let _ = fs::remove_file(&full_path).await;
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("检查重复文件失败: {}", e)));
+            }
+        };
+        let action = params.on_duplicate.action(!duplicate_of.is_empty());
+        if let Some(action @ (DuplicateAction::Skipped | DuplicateAction::Linked)) = action {
+            let _ = fs::remove_file(&full_path).await;
+            info!("跳过重复上传的文件 {}（workspace {}）: {:?}", file_name, workspace_id, action);
+            duplicates.push(serde_json::json!({
+                "file_name": file_name,
+                "action": action,
+                "job_id": null,
+                "linked": (action == DuplicateAction::Linked).then(|| &duplicate_of[0]),
+                "duplicate_of": duplicate_of,
+            }));
             continue;
         }
 
-        let data = field
-            .bytes()
+        // 登记后台导入任务，立即返回任务ID
+        let path_str = full_path.to_string_lossy().to_string();
+        let job = app_state
+            .import_queue
+            .enqueue(workspace_id, user.id, &path_str, &file_name, &file_hash, &options)
             .await
-            .map_err(|e| (StatusCode::BAD_REQUEST, format!("读取上传文件失败: {}", e)))?;
-        if data.is_empty() {
-            continue;
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("创建导入任务失败: {}", e)))?;
+        if let Some(action) = action {
+            duplicates.push(serde_json::json!({
+                "file_name": file_name,
+                "action": action,
+                "job_id": job.id,
+                "linked": null,
+                "duplicate_of": duplicate_of,
+            }));
         }
+        jobs.push(import_job_response(job));
+    }
 
-        let ext = if file_name_lower.ends_with(".xls") { "xls" } else { "xlsx" };
-        let stored_name = format!("{}_{}.{}", workspace_id, Uuid::new_v4(), ext);
-        let full_path = StdPath::new(&app_state.upload_dir).join(stored_name);
-        fs::write(&full_path, data)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存上传文件失败: {}", e)))?;
+    Ok(Json(serde_json::json!({
+        "workspace_id": workspace_id,
+        "queued_files": jobs.len(),
+        "jobs": jobs,
+        "duplicates": duplicates
+    })))
+}
 
-        let path_str = full_path.to_string_lossy().to_string();
-        processor
-            .import_uploaded_file(workspace_id, &path_str, user.id, &file_name)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导入Excel失败: {}", e)))?;
-        imported += 1;
+/// upsert模式需要键列：上传时未指定key_columns时，工作区的列映射规则中必须设置了key_columns
+async fn ensure_upsert_key_columns(
+    db: &DatabaseConnection,
+    workspace_id: i32,
+    options: &ImportOptions,
+) -> Result<(), (StatusCode, String)> {
+    if options.mode != ImportMode::Upsert || !options.key_columns.is_empty() {
+        return Ok(());
+    }
+    let mapping = column_profile::load_mapping(db, workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("读取列映射规则失败: {}", e)))?;
+    if mapping.is_some_and(|(_, mapping)| !mapping.key_columns.is_empty()) {
+        Ok(())
+    } else {
+        Err((StatusCode::BAD_REQUEST, "upsert模式需要指定键列（key_columns），或在列映射规则中设置key_columns".to_string()))
+    }
+}
+
+/// 预览上传文件中的工作表（数据行数与表头），不登记导入任务，预览完成后删除文件
+async fn preview_upload_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+    Query(options): Query<ImportOptions>,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = authenticate_user(&headers, &app_state.db).await?;
+    let workspace = get_workspace_by_id(&app_state.db, workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "仅workspace拥有者可上传".to_string()));
+    }
+    options.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let mut previews = Vec::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (e.status(), format!("读取上传字段失败: {}", e.body_text())))?
+    {
+        let Some(file_name) = field.file_name().map(ToOwned::to_owned) else {
+            continue;
+        };
+        let Some(stored) = store_upload_field(&app_state.upload_dir, workspace_id, &file_name, field).await? else {
+            continue;
+        };
+
+        // 读取表头是同步操作，放到阻塞线程池中执行
+        let path_str = stored.path.to_string_lossy().to_string();
+        let task_options = options.clone();
+        let preview = tokio::task::spawn_blocking(move || {
+            ExcelProcessor::preview_excel_file(&path_str, &task_options).map_err(|e| e.to_string())
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("预览任务异常退出: {}", e)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("读取文件失败 {}: {}", file_name, e)));
+        let _ = fs::remove_file(&stored.path).await;
+        let preview = preview?;
+
+        previews.push(serde_json::json!({
+            "file_name": file_name,
+            "format": stored.format.extension(),
+            "sheets": preview.sheets,
+            "missing_sheets": preview.missing_sheets,
+        }));
+    }
+
+    Ok(Json(serde_json::json!({
+        "workspace_id": workspace_id,
+        "files": previews
+    })))
+}
+
+/// 试导入上传文件：读取并清理全部数据但不写入excel_data，保留文件并返回确认令牌，
+/// 确认后按试导入时的选项导入，无需再次上传
+async fn dry_run_upload_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+    Query(options): Query<ImportOptions>,
+    Query(params): Query<DryRunQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = authenticate_user(&headers, &app_state.db).await?;
+    let workspace = get_workspace_by_id(&app_state.db, workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "仅workspace拥有者可上传".to_string()));
+    }
+    options.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    ensure_upsert_key_columns(&app_state.db, workspace_id, &options).await?;
+    let sample_rows = params
+        .sample_rows
+        .unwrap_or(DEFAULT_DRY_RUN_SAMPLE_ROWS)
+        .min(MAX_DRY_RUN_SAMPLE_ROWS);
+
+    if let Err(e) = app_state.import_queue.remove_expired_dry_runs().await {
+        warn!("清理过期试导入失败: {}", e);
+    }
+    let column_profile = column_profile::load_mapping(&app_state.db, workspace_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("读取列映射规则失败: {}", e)))?;
+
+    // 全部文件试导入成功后才登记，任一文件失败时删除本次保存的所有文件
+    let mut dry_runs = Vec::new();
+    let result = dry_run_upload_fields(&app_state, workspace_id, &options, &column_profile, sample_rows, &mut multipart, &mut dry_runs).await;
+    if let Err(e) = result {
+        remove_stored_uploads(dry_runs.iter().map(|dry_run| &dry_run.stored)).await;
+        return Err(e);
+    }
+
+    let mut results = Vec::with_capacity(dry_runs.len());
+    let mut registered = Vec::with_capacity(dry_runs.len());
+    for dry_run in &dry_runs {
+        let path_str = dry_run.stored.path.to_string_lossy().to_string();
+        let job = app_state
+            .import_queue
+            .register_dry_run(workspace_id, user.id, &path_str, &dry_run.file_name, &dry_run.stored.file_hash, &options)
+            .await;
+        let job = match job {
+            Ok(job) => job,
+            Err(e) => {
+                if let Err(e) = app_state.import_queue.remove_dry_runs(&registered).await {
+                    warn!("删除已登记的试导入失败: {}", e);
+                }
+                remove_stored_uploads(dry_runs.iter().map(|dry_run| &dry_run.stored)).await;
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("登记试导入失败: {}", e)));
+            }
+        };
+        registered.push(job.id);
+
+        results.push(serde_json::json!({
+            "file_name": dry_run.file_name,
+            "format": dry_run.stored.format.extension(),
+            "confirm_token": job.confirm_token,
+            "expires_at": ImportJobQueue::confirm_token_expires_at(&job),
+            "column_profile_version": column_profile.as_ref().map(|(version, _)| *version),
+            "duplicate_of": dry_run.duplicate_of,
+            "report": dry_run.report,
+        }));
+    }
+
+    Ok(Json(serde_json::json!({
+        "workspace_id": workspace_id,
+        "files": results
+    })))
+}
+
+/// 试导入完成、尚未登记的上传文件
+struct StoredDryRun {
+    file_name: String,
+    stored: StoredUpload,
+    report: DryRunReport,
+    duplicate_of: Vec<DuplicateUpload>,
+}
+
+/// 依次保存并试导入上传的文件，成功的文件追加到dry_runs；出错时已保存的文件留在dry_runs中由调用方清理
+async fn dry_run_upload_fields(
+    app_state: &AppState,
+    workspace_id: i32,
+    options: &ImportOptions,
+    column_profile: &Option<(i32, ColumnMapping)>,
+    sample_rows: usize,
+    multipart: &mut Multipart,
+    dry_runs: &mut Vec<StoredDryRun>,
+) -> Result<(), (StatusCode, String)> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (e.status(), format!("读取上传字段失败: {}", e.body_text())))?
+    {
+        let Some(file_name) = field.file_name().map(ToOwned::to_owned) else {
+            continue;
+        };
+        let Some(stored) = store_upload_field(&app_state.upload_dir, workspace_id, &file_name, field).await? else {
+            continue;
+        };
+
+        // 读取与解析是同步操作，放到阻塞线程池中执行
+        let path_str = stored.path.to_string_lossy().to_string();
+        let task_options = options.clone();
+        let mapping = column_profile.as_ref().map(|(_, mapping)| mapping.clone());
+        let report = tokio::task::spawn_blocking(move || {
+            ExcelProcessor::dry_run_excel_file(&path_str, &task_options, mapping.as_ref(), sample_rows)
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("试导入任务异常退出: {}", e)));
+        let report = match report {
+            Ok(report) => report,
+            Err(e) => {
+                let _ = fs::remove_file(&stored.path).await;
+                return Err((StatusCode::BAD_REQUEST, format!("读取文件失败 {}: {}", file_name, e)));
+            }
+        };
+
+        let duplicate_of = match app_state.import_queue.find_duplicates(workspace_id, &stored.file_hash).await {
+            Ok(duplicate_of) => duplicate_of,
+            Err(e) => {
+                let _ = fs::remove_file(&stored.path).await;
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("检查重复文件失败: {}", e)));
+            }
+        };
+
+        dry_runs.push(StoredDryRun { file_name, stored, report, duplicate_of });
+    }
+    Ok(())
+}
+
+/// 删除已保存的上传文件
+async fn remove_stored_uploads<'a>(uploads: impl Iterator<Item = &'a StoredUpload>) {
+    for upload in uploads {
+        if let Err(e) = fs::remove_file(&upload.path).await {
+            warn!("删除上传文件失败 {}: {}", upload.path.display(), e);
+        }
     }
+}
+
+/// 用试导入返回的确认令牌登记导入任务
+async fn confirm_upload_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<ConfirmImportRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let user = authenticate_user(&headers, &app_state.db).await?;
+    let workspace = get_workspace_by_id(&app_state.db, workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "仅workspace拥有者可上传".to_string()));
+    }
+
+    let job = app_state
+        .import_queue
+        .confirm_dry_run(payload.confirm_token.trim(), workspace_id, user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("确认导入失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "确认令牌不存在、已使用或已过期".to_string()))?;
 
     Ok(Json(serde_json::json!({
         "workspace_id": workspace_id,
-        "imported_files": imported
+        "queued_files": 1,
+        "jobs": [import_job_response(job)]
     })))
 }
 
+/// 已保存到上传目录的文件
+struct StoredUpload {
+    path: std::path::PathBuf,
+    file_hash: String,
+    format: FileFormat,
+}
+
+/// 保存上传的文件：先流式写入临时文件，按内容识别真实格式后再使用正确的扩展名，空文件返回None
+async fn store_upload_field(
+    upload_dir: &str,
+    workspace_id: i32,
+    file_name: &str,
+    field: axum::extract::multipart::Field<'_>,
+) -> Result<Option<StoredUpload>, (StatusCode, String)> {
+    let stored_stem = format!("{}_{}", workspace_id, Uuid::new_v4());
+    let temp_path = StdPath::new(upload_dir).join(format!("{}.part", stored_stem));
+    let (file_size, file_hash) = match save_upload_field(field, &temp_path).await {
+        Ok(saved) => saved,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+    };
+    if file_size == 0 {
+        let _ = fs::remove_file(&temp_path).await;
+        return Ok(None);
+    }
+
+    let format = match detect_file_format(&temp_path, file_name) {
+        Ok(Some(format)) => format,
+        Ok(None) => {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err((StatusCode::BAD_REQUEST, format!("不支持的文件格式: {}", file_name)));
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("识别文件格式失败: {}", e)));
+        }
+    };
+
+    let path = StdPath::new(upload_dir).join(format!("{}.{}", stored_stem, format.extension()));
+    fs::rename(&temp_path, &path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存上传文件失败: {}", e)))?;
+
+    Ok(Some(StoredUpload { path, file_hash, format }))
+}
+
+/// 只保存第一个上传字段的路由，供集成测试检查流式保存与上传大小限制，不访问数据库
+///
+/// 响应为 `哈希 保存的文件名 格式扩展名`，空文件为 `empty`；读取字段的错误处理与上传接口相同。
+#[doc(hidden)]
+pub fn upload_storage_test_router(upload_dir: String, max_upload_bytes: usize) -> Router {
+    let handler = move |mut multipart: Multipart| async move {
+        let field = multipart
+            .next_field()
+            .await
+            .map_err(|e| (e.status(), format!("读取上传字段失败: {}", e.body_text())))?
+            .ok_or((StatusCode::BAD_REQUEST, "没有上传文件".to_string()))?;
+        let file_name = field.file_name().unwrap_or_default().to_string();
+        let stored = store_upload_field(&upload_dir, 1, &file_name, field).await?;
+        Ok::<_, (StatusCode, String)>(match stored {
+            Some(stored) => format!(
+                "{} {} {}",
+                stored.file_hash,
+                stored.path.file_name().unwrap_or_default().to_string_lossy(),
+                stored.format.extension()
+            ),
+            None => "empty".to_string(),
+        })
+    };
+    Router::new()
+        .route("/", post(handler))
+        .layer(DefaultBodyLimit::max(max_upload_bytes))
+}
+
+/// 将上传字段分块写入临时文件，同时计算MD5，返回文件大小与哈希值
+async fn save_upload_field(
+    mut field: axum::extract::multipart::Field<'_>,
+    temp_path: &StdPath,
+) -> Result<(u64, String), (StatusCode, String)> {
+    use tokio::io::AsyncWriteExt;
+
+    let file = fs::File::create(temp_path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存上传文件失败: {}", e)))?;
+    let mut writer = tokio::io::BufWriter::new(file);
+    let mut context = md5::Context::new();
+    let mut file_size = 0u64;
+
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| (e.status(), format!("读取上传文件失败: {}", e.body_text())))?
+    {
+        context.consume(&chunk);
+        file_size += chunk.len() as u64;
+        writer
+            .write_all(&chunk)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存上传文件失败: {}", e)))?;
+    }
+
+    // 确保数据落盘后再交给后续的格式识别与重命名
+    writer
+        .flush()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存上传文件失败: {}", e)))?;
+    writer
+        .into_inner()
+        .sync_all()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("保存上传文件失败: {}", e)))?;
+
+    Ok((file_size, format!("{:x}", context.compute())))
+}
+
+fn import_job_response(job: import_jobs::Model) -> ImportJobResponse {
+    ImportJobResponse {
+        id: job.id,
+        workspace_id: job.workspace_id,
+        file_name: job.file_name,
+        status: job.status,
+        rows_processed: job.rows_processed,
+        rows_inserted: job.rows_inserted,
+        rows_updated: job.rows_updated,
+        rows_unchanged: job.rows_unchanged,
+        rows_rejected: job.rows_rejected,
+        file_id: job.file_id,
+        current_sheet: job.current_sheet,
+        error: job.error,
+        attempts: job.attempts,
+        created_at: job.created_at,
+        started_at: job.started_at,
+        finished_at: job.finished_at,
+    }
+}
+
+async fn get_import_job_handler(
+    State(app_state): State<AppState>,
+    Path(job_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Json<ImportJobResponse>, (StatusCode, String)> {
+    let user = authenticate_user(&headers, &app_state.db).await?;
+    let job = import_jobs::Entity::find_by_id(job_id)
+        .one(&app_state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询导入任务失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "导入任务不存在".to_string()))?;
+
+    let workspace = get_workspace_by_id(&app_state.db, job.workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "无权限查看该导入任务".to_string()));
+    }
+
+    Ok(Json(import_job_response(job)))
+}
+
+/// 进度事件流的查询参数（EventSource无法设置请求头，允许通过token参数认证）
+#[derive(Deserialize)]
+pub struct EventStreamQuery {
+    token: Option<String>,
+}
+
+/// 进度事件的订阅范围
+#[derive(Clone, Copy)]
+enum EventScope {
+    Job(i32),
+    Workspace(i32),
+}
+
+impl EventScope {
+    fn matches(&self, event: &JobEvent) -> bool {
+        match self {
+            EventScope::Job(job_id) => event.job_id == *job_id,
+            EventScope::Workspace(workspace_id) => event.workspace_id == *workspace_id,
+        }
+    }
+}
+
+type EventStream = futures::stream::BoxStream<'static, Result<Event, std::convert::Infallible>>;
+
+fn json_event(name: &str, data: &impl serde::Serialize) -> Result<Event, std::convert::Infallible> {
+    Ok(Event::default()
+        .event(name)
+        .data(serde_json::to_string(data).unwrap_or_default()))
+}
+
+/// 将广播的导入事件转换为SSE事件流；按任务订阅时在结束事件后关闭
+fn live_job_events(receiver: tokio::sync::broadcast::Receiver<JobEvent>, scope: EventScope) -> EventStream {
+    use futures::StreamExt;
+    use tokio::sync::broadcast::error::RecvError;
+
+    futures::stream::unfold((receiver, false), move |(mut receiver, finished)| async move {
+        if finished {
+            return None;
+        }
+        loop {
+            match receiver.recv().await {
+                Ok(event) if scope.matches(&event) => {
+                    let finished = matches!(scope, EventScope::Job(_)) && event.is_terminal();
+                    return Some((json_event(event.name(), &event), (receiver, finished)));
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("导入进度订阅端处理过慢，丢弃 {} 个事件", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
+async fn import_job_events_handler(
+    State(app_state): State<AppState>,
+    Path(job_id): Path<i32>,
+    headers: HeaderMap,
+    Query(params): Query<EventStreamQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use futures::StreamExt;
+
+    let token = bearer_token_from_headers(&headers).or(params.token);
+    let user = authenticate_token(token, &app_state.db).await?;
+
+    // 先订阅再读取任务状态，避免两者之间产生的事件丢失
+    let receiver = app_state.import_queue.subscribe();
+    let job = app_state
+        .import_queue
+        .find_job(job_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询导入任务失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "导入任务不存在".to_string()))?;
+
+    let workspace = get_workspace_by_id(&app_state.db, job.workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "无权限查看该导入任务".to_string()));
+    }
+
+    // 首个事件为任务当前状态；已结束的任务只返回该事件
+    let finished = job.status == JOB_STATUS_COMPLETED || job.status == JOB_STATUS_FAILED;
+    let snapshot = futures::stream::once(std::future::ready(json_event("snapshot", &import_job_response(job))));
+    let stream: EventStream = if finished {
+        snapshot.boxed()
+    } else {
+        snapshot.chain(live_job_events(receiver, EventScope::Job(job_id))).boxed()
+    };
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn workspace_import_events_handler(
+    State(app_state): State<AppState>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+    Query(params): Query<EventStreamQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    use futures::StreamExt;
+
+    let token = bearer_token_from_headers(&headers).or(params.token);
+    let user = authenticate_token(token, &app_state.db).await?;
+    let workspace = get_workspace_by_id(&app_state.db, workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "无权限查看该workspace的导入任务".to_string()));
+    }
+
+    let receiver = app_state.import_queue.subscribe();
+    let active_jobs: Vec<ImportJobResponse> = import_jobs::Entity::find()
+        .filter(import_jobs::Column::WorkspaceId.eq(workspace_id))
+        .filter(import_jobs::Column::Status.is_not_in([JOB_STATUS_COMPLETED, JOB_STATUS_FAILED]))
+        .all(&app_state.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询导入任务失败: {}", e)))?
+        .into_iter()
+        .map(import_job_response)
+        .collect();
+
+    // 首个事件为当前未结束的任务列表，之后持续推送该workspace下所有任务的进度
+    let stream = futures::stream::once(std::future::ready(json_event("snapshot", &active_jobs)))
+        .chain(live_job_events(receiver, EventScope::Workspace(workspace_id)))
+        .boxed();
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 
 
 async fn stats_handler(
@@ -2417,15 +3491,15 @@ async fn search_handler(
     State(app_state): State<AppState>,
     headers: HeaderMap,
     Query(params): Query<SearchQuery>,
-) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    Query(raw_params): Query<Vec<(String, String)>>,
+) -> Result<Json<SearchResponse>, SearchApiError> {
     let db = app_state.db.clone();
-    let query_text = params.q.unwrap_or_default();
-    let limit = params.limit.unwrap_or(20).max(1).min(100) as u64;
+    let query_text = params.q.clone().unwrap_or_default();
+    let limit = params.limit.unwrap_or(20).clamp(1, 100) as u64;
     let offset = params.offset.unwrap_or(0).max(0) as u64;
+    let mode = parse_search_mode(&params)?;
     
-    if query_text.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "查询参数不能为空".to_string()));
-    }
+    let criteria = parse_search_criteria(&query_text, &raw_params)?;
     
     let processor = crate::excel_processor_sea::ExcelProcessor::new(db.clone());
 
@@ -2434,17 +3508,17 @@ async fn search_handler(
         if !workspace.is_public {
             let user = authenticate_user(&headers, &db).await?;
             if user.id != workspace.owner_id {
-                return Err((StatusCode::FORBIDDEN, "无权限访问该workspace".to_string()));
+                return Err((StatusCode::FORBIDDEN, "无权限访问该workspace".to_string()).into());
             }
         }
-        match processor.search_workspace_data(workspace_id, &query_text, limit, offset).await {
+        match processor.search_workspace_data(workspace_id, &criteria, mode, limit, offset).await {
             Ok(results) => Ok(Json(results)),
-            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("搜索失败: {}", e))),
+            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("搜索失败: {}", e)).into()),
         }
     } else {
-        match processor.search_public_data(&query_text, limit, offset).await {
+        match processor.search_public_data(&criteria, mode, limit, offset).await {
             Ok(results) => Ok(Json(results)),
-            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("搜索失败: {}", e))),
+            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("搜索失败: {}", e)).into()),
         }
     }
 }
@@ -2453,45 +3527,464 @@ async fn export_handler(
     State(app_state): State<AppState>,
     headers: HeaderMap,
     Query(params): Query<SearchQuery>,
-) -> Result<Response<axum::body::Body>, (StatusCode, String)> {
+    Query(export_params): Query<ExportQuery>,
+    Query(raw_params): Query<Vec<(String, String)>>,
+) -> Result<Response<axum::body::Body>, SearchApiError> {
+    let format = export_params.format;
     let db = app_state.db.clone();
-    let query_text = params.q.unwrap_or_default();
+    let query_text = params.q.clone().unwrap_or_default();
+    let mode = parse_search_mode(&params)?;
     
-    if query_text.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "查询参数不能为空".to_string()));
-    }
+    let criteria = parse_search_criteria(&query_text, &raw_params)?;
+    let (column_labels, metadata_labels) = export_labels(&app_state, &headers);
+    let columns = parse_export_columns(&raw_params, column_labels)?;
     
     let processor = crate::excel_processor_sea::ExcelProcessor::new(db.clone());
-    let export_result = if let Some(workspace_id) = params.workspace_id {
-        let workspace = get_workspace_by_id(&db, workspace_id).await?;
-        if !workspace.is_public {
-            let user = authenticate_user(&headers, &db).await?;
-            if user.id != workspace.owner_id {
-                return Err((StatusCode::FORBIDDEN, "无权限访问该workspace".to_string()));
+    let scope = authorize_export_scope(&db, &headers, params.workspace_id).await?;
+    let plan = match params.workspace_id {
+        Some(workspace_id) => processor.plan_workspace_export(workspace_id, &criteria, mode).await,
+        None => processor.plan_public_export(&criteria, mode).await,
+    };
+    let plan = plan
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导出失败: {}", e)))?
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "导出失败: 没有找到匹配的数据".to_string()))?;
+
+    let info = ExportInfo {
+        context: export_context(&query_text, &raw_params, scope, metadata_labels),
+        mode,
+        exported_at: chrono::Utc::now(),
+        columns,
+    };
+    let exporter = format
+        .exporter(&plan.sheets, &info)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导出失败: {}", e)))?;
+    let filename = format!("搜索结果导出_{}.{}", info.exported_at.format("%Y%m%d_%H%M%S"), format.extension());
+    let response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename));
+    info!("开始导出 {} 行搜索结果（{}）", plan.total_rows(), format.extension());
+
+    let response = if format == ExportFormat::Xlsx {
+        // xlsx写入临时文件后再发送，此时文件大小已知
+        let mut unused = Vec::new();
+        let file = processor
+            .write_export(&plan, exporter, &mut unused)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导出失败: {}", e)))?
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "导出失败: 没有生成导出文件".to_string()))?;
+        let file_size = file
+            .metadata()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导出失败: {}", e)))?
+            .len();
+        response
+            .header(header::CONTENT_LENGTH, file_size)
+            .body(file_body(fs::File::from_std(file)))
+    } else {
+        // 文本格式边查询边发送，长度未知，使用分块传输
+        let (mut sender, receiver) = tokio::sync::mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            if let Err(e) = processor.write_export(&plan, exporter, &mut sender).await {
+                warn!("导出搜索结果失败: {}", e);
+                let _ = sender.send(Err(std::io::Error::other(e.to_string()))).await;
             }
+        });
+        response.body(axum::body::Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(receiver)))
+    };
+    response.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("构建响应失败: {}", e)).into())
+}
+
+/// 按请求者的语言取得系统列表头和导出信息工作表的标签
+fn export_labels(app_state: &AppState, headers: &HeaderMap) -> (SystemColumnLabels, ExportMetadataLabels) {
+    let i18n_manager = app_state.i18n_manager.lock().unwrap();
+    let lang = i18n_manager.detect_language_from_headers(headers);
+    (
+        SystemColumnLabels::translated(&i18n_manager, &lang),
+        ExportMetadataLabels::translated(&i18n_manager, &lang),
+    )
+}
+
+/// 解析导出的列（可重复的 column 参数）
+fn parse_export_columns(
+    raw_params: &[(String, String)],
+    labels: SystemColumnLabels,
+) -> Result<ExportColumns, (StatusCode, String)> {
+    let specs = raw_params
+        .iter()
+        .filter(|(key, _)| key == "column")
+        .map(|(_, value)| value.as_str());
+    ExportColumns::parse(specs, labels).map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// 检查导出范围的访问权限并返回范围说明：私有workspace仅拥有者可导出
+async fn authorize_export_scope(
+    db: &DatabaseConnection,
+    headers: &HeaderMap,
+    workspace_id: Option<i32>,
+) -> Result<String, (StatusCode, String)> {
+    let Some(workspace_id) = workspace_id else {
+        return Ok("公开数据".to_string());
+    };
+    let workspace = get_workspace_by_id(db, workspace_id).await?;
+    if !workspace.is_public {
+        let user = authenticate_user(headers, db).await?;
+        if user.id != workspace.owner_id {
+            return Err((StatusCode::FORBIDDEN, "无权限访问该workspace".to_string()));
         }
-        processor.export_workspace_search_results(workspace_id, &query_text).await
-    } else {
-        processor.export_public_search_results(&query_text).await
+    }
+    Ok(format!("Workspace {} (ID {})", workspace.name, workspace.id))
+}
+
+#[derive(Deserialize)]
+pub struct ExportJobListQuery {
+    limit: Option<u64>,
+}
+
+fn export_job_response(job: export_jobs::Model) -> ExportJobResponse {
+    let context = serde_json::from_value::<ExportRequest>(job.request)
+        .map(|request| request.context)
+        .unwrap_or_default();
+    let downloadable = job.status == JOB_STATUS_COMPLETED && job.expires_at.is_some_and(|at| at > chrono::Utc::now());
+    ExportJobResponse {
+        id: job.id,
+        workspace_id: job.workspace_id,
+        format: job.format,
+        query: context.query,
+        scope: context.scope,
+        status: job.status,
+        total_rows: job.total_rows,
+        file_name: job.file_name,
+        file_size: job.file_size,
+        download_url: downloadable.then(|| format!("/api/exports/{}/download", job.id)),
+        error: job.error,
+        attempts: job.attempts,
+        created_at: job.created_at,
+        started_at: job.started_at,
+        finished_at: job.finished_at,
+        expires_at: job.expires_at,
+    }
+}
+
+/// 提交后台导出任务：参数与同步导出接口相同，立即返回任务，完成后通过下载地址获取文件
+async fn create_export_job_handler(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<SearchQuery>,
+    Query(export_params): Query<ExportQuery>,
+    Query(raw_params): Query<Vec<(String, String)>>,
+) -> Result<(StatusCode, Json<ExportJobResponse>), SearchApiError> {
+    let user = authenticate_user(&headers, &app_state.db).await?;
+    let query_text = params.q.clone().unwrap_or_default();
+    let mode = parse_search_mode(&params)?;
+    parse_search_criteria(&query_text, &raw_params)?;
+    let (column_labels, metadata_labels) = export_labels(&app_state, &headers);
+    let columns = parse_export_columns(&raw_params, column_labels)?;
+    let scope = authorize_export_scope(&app_state.db, &headers, params.workspace_id).await?;
+
+    let request = ExportRequest {
+        query: query_text.clone(),
+        filters: raw_params
+            .iter()
+            .filter(|(key, _)| key.starts_with("filter["))
+            .cloned()
+            .collect(),
+        search: mode,
+        context: export_context(&query_text, &raw_params, scope, metadata_labels),
+        columns,
     };
+    let job = app_state
+        .export_queue
+        .enqueue(user.id, params.workspace_id, export_params.format, &request)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("创建导出任务失败: {}", e)))?;
+    info!("用户 {} 提交导出任务 {}（{}）", user.id, job.id, job.format);
 
-    match export_result {
-        Ok(excel_data) => {
-            let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-            let filename = format!("搜索结果导出_{}.xlsx", timestamp);
-            
-            let response = Response::builder()
-                .status(StatusCode::OK)
-                .header(header::CONTENT_TYPE, "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
-                .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
-                .header(header::CONTENT_LENGTH, excel_data.len())
-                .body(axum::body::Body::from(excel_data))
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("构建响应失败: {}", e)))?;
-                
-            Ok(response)
-        },
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("导出失败: {}", e))),
+    Ok((StatusCode::ACCEPTED, Json(export_job_response(job))))
+}
+
+/// 列出当前用户最近的导出任务
+async fn list_export_jobs_handler(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ExportJobListQuery>,
+) -> Result<Json<Vec<ExportJobResponse>>, (StatusCode, String)> {
+    let user = authenticate_user(&headers, &app_state.db).await?;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let jobs = app_state
+        .export_queue
+        .list_recent(user.id, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询导出任务失败: {}", e)))?;
+    Ok(Json(jobs.into_iter().map(export_job_response).collect()))
+}
+
+/// 查询导出任务，仅提交者可查看
+async fn find_own_export_job(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    job_id: i32,
+) -> Result<export_jobs::Model, (StatusCode, String)> {
+    let user = authenticate_user(headers, &app_state.db).await?;
+    let job = app_state
+        .export_queue
+        .find_job(job_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询导出任务失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "导出任务不存在".to_string()))?;
+    if job.created_by != user.id {
+        return Err((StatusCode::FORBIDDEN, "无权限查看该导出任务".to_string()));
     }
+    Ok(job)
+}
+
+async fn get_export_job_handler(
+    State(app_state): State<AppState>,
+    Path(job_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Json<ExportJobResponse>, (StatusCode, String)> {
+    let job = find_own_export_job(&app_state, &headers, job_id).await?;
+    Ok(Json(export_job_response(job)))
+}
+
+/// 下载导出任务生成的文件：未完成时返回409，过期后返回410
+async fn download_export_handler(
+    State(app_state): State<AppState>,
+    Path(job_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response<axum::body::Body>, (StatusCode, String)> {
+    let job = find_own_export_job(&app_state, &headers, job_id).await?;
+    let (file_path, file_name) = match download_state(&job, chrono::Utc::now()) {
+        DownloadState::Ready { file_path, file_name } => (file_path, file_name),
+        DownloadState::NotReady(message) => return Err((StatusCode::CONFLICT, message)),
+        DownloadState::Expired => return Err((StatusCode::GONE, "导出文件已过期".to_string())),
+    };
+    let format = ExportFormat::from_extension(&job.format).unwrap_or_default();
+
+    let file = fs::File::open(file_path)
+        .await
+        .map_err(|e| (StatusCode::GONE, format!("导出文件不存在: {}", e)))?;
+    let file_size = file
+        .metadata()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("读取导出文件失败: {}", e)))?
+        .len();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name))
+        .header(header::CONTENT_LENGTH, file_size)
+        .body(file_body(file))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("构建响应失败: {}", e)))
+}
+
+/// 分块读取文件作为响应体
+fn file_body(file: fs::File) -> axum::body::Body {
+    let stream = futures::stream::unfold(file, |mut file| async move {
+        let mut buffer = vec![0u8; DOWNLOAD_CHUNK_SIZE];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok(buffer), file))
+            }
+            Err(e) => Some((Err(e), file)),
+        }
+    });
+    axum::body::Body::from_stream(stream)
+}
+
+/// 导出信息工作表中的查询说明：查询语句加上原样的 filter[字段] 筛选参数
+fn export_context(
+    query_text: &str,
+    raw_params: &[(String, String)],
+    scope: String,
+    labels: ExportMetadataLabels,
+) -> ExportContext {
+    let mut parts: Vec<String> = Vec::new();
+    if !query_text.trim().is_empty() {
+        parts.push(query_text.trim().to_string());
+    }
+    parts.extend(
+        raw_params
+            .iter()
+            .filter(|(key, _)| key.starts_with("filter["))
+            .map(|(key, value)| if value.is_empty() { key.clone() } else { format!("{}={}", key, value) }),
+    );
+    ExportContext {
+        query: parts.join(" "),
+        scope,
+        labels,
+    }
+}
+
+/// 查看文件被拒绝的行：仅文件所在工作区的拥有者，返回文件记录
+async fn authorize_file_rejections(
+    db: &DatabaseConnection,
+    headers: &HeaderMap,
+    file_id: i32,
+) -> Result<files::Model, (StatusCode, String)> {
+    let user = authenticate_user(headers, db).await?;
+    let file = files::Entity::find_by_id(file_id)
+        .one(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询文件失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "文件不存在".to_string()))?;
+    let workspace_id = file
+        .workspace_id
+        .ok_or((StatusCode::FORBIDDEN, "无权限查看该文件".to_string()))?;
+    let workspace = get_workspace_by_id(db, workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "无权限查看该文件".to_string()));
+    }
+    Ok(file)
+}
+
+/// 查看导入任务被拒绝的行：仅任务所在工作区的拥有者，返回任务记录
+async fn authorize_job_rejections(
+    db: &DatabaseConnection,
+    headers: &HeaderMap,
+    job_id: i32,
+) -> Result<import_jobs::Model, (StatusCode, String)> {
+    let user = authenticate_user(headers, db).await?;
+    let job = import_jobs::Entity::find_by_id(job_id)
+        .one(db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询导入任务失败: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "导入任务不存在".to_string()))?;
+    let workspace = get_workspace_by_id(db, job.workspace_id).await?;
+    if workspace.owner_id != user.id {
+        return Err((StatusCode::FORBIDDEN, "无权限查看该导入任务".to_string()));
+    }
+    Ok(job)
+}
+
+/// 分页查询拒绝记录，返回total、limit、offset与rejections
+async fn rejection_page(
+    db: &DatabaseConnection,
+    owner: RejectionOwner,
+    params: RejectionListQuery,
+) -> Result<serde_json::Value, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(DEFAULT_REJECTION_PAGE_SIZE).clamp(1, MAX_REJECTION_PAGE_SIZE);
+    let offset = params.offset.unwrap_or(0);
+
+    let total = row_validation::count_rejections(db, owner)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询拒绝记录失败: {}", e)))?;
+    let rejections = row_validation::list_rejections(db, owner, Some(limit), offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询拒绝记录失败: {}", e)))?;
+
+    let rejections: Vec<serde_json::Value> = rejections
+        .into_iter()
+        .map(|rejection| {
+            serde_json::json!({
+                "sheet_name": rejection.sheet_name,
+                "row_number": rejection.row_number,
+                "reasons": rejection.reasons,
+                "data": rejection.data_json,
+                "created_at": rejection.created_at,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!({
+        "total": total,
+        "limit": limit,
+        "offset": offset,
+        "rejections": rejections,
+    }))
+}
+
+/// 拒绝行工作簿的下载响应，文件名取原文件名加“_拒绝数据”
+fn rejection_workbook_response(
+    file_name: &str,
+    excel_data: Vec<u8>,
+) -> Result<Response<axum::body::Body>, (StatusCode, String)> {
+    let stem = StdPath::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("file");
+    let filename = format!("{}_拒绝数据.xlsx", stem.replace('"', ""));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .header(header::CONTENT_LENGTH, excel_data.len())
+        .body(axum::body::Body::from(excel_data))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("构建响应失败: {}", e)))
+}
+
+/// 分页列出文件最近一次导入时被拒绝的行
+async fn list_file_rejections_handler(
+    State(app_state): State<AppState>,
+    Path(file_id): Path<i32>,
+    headers: HeaderMap,
+    Query(params): Query<RejectionListQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let file = authorize_file_rejections(&app_state.db, &headers, file_id).await?;
+    let mut page = rejection_page(&app_state.db, RejectionOwner::File(file.id), params).await?;
+    page["file_id"] = serde_json::json!(file.id);
+    page["file_name"] = serde_json::json!(file.file_name);
+    Ok(Json(page))
+}
+
+/// 下载带错误原因列的拒绝行工作簿
+async fn export_file_rejections_handler(
+    State(app_state): State<AppState>,
+    Path(file_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response<axum::body::Body>, (StatusCode, String)> {
+    let file = authorize_file_rejections(&app_state.db, &headers, file_id).await?;
+    let total = row_validation::count_rejections(&app_state.db, RejectionOwner::File(file.id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询拒绝记录失败: {}", e)))?;
+    if total == 0 {
+        return Err((StatusCode::NOT_FOUND, "该文件没有被拒绝的数据行".to_string()));
+    }
+
+    let processor = ExcelProcessor::new(app_state.db.clone());
+    let excel_data = processor
+        .export_file_rejections(&file)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导出拒绝记录失败: {}", e)))?;
+    rejection_workbook_response(&file.file_name, excel_data)
+}
+
+/// 分页列出严格模式下整个文件未导入的任务被拒绝的行（导入成功的任务按file_id查看文件的拒绝记录）
+async fn list_job_rejections_handler(
+    State(app_state): State<AppState>,
+    Path(job_id): Path<i32>,
+    headers: HeaderMap,
+    Query(params): Query<RejectionListQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let job = authorize_job_rejections(&app_state.db, &headers, job_id).await?;
+    let mut page = rejection_page(&app_state.db, RejectionOwner::Job(job.id), params).await?;
+    page["job_id"] = serde_json::json!(job.id);
+    page["file_name"] = serde_json::json!(job.file_name);
+    Ok(Json(page))
+}
+
+/// 下载导入任务被拒绝的行
+async fn export_job_rejections_handler(
+    State(app_state): State<AppState>,
+    Path(job_id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response<axum::body::Body>, (StatusCode, String)> {
+    let job = authorize_job_rejections(&app_state.db, &headers, job_id).await?;
+    let total = row_validation::count_rejections(&app_state.db, RejectionOwner::Job(job.id))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("查询拒绝记录失败: {}", e)))?;
+    if total == 0 {
+        return Err((StatusCode::NOT_FOUND, "该导入任务没有被拒绝的数据行".to_string()));
+    }
+
+    let processor = ExcelProcessor::new(app_state.db.clone());
+    let excel_data = processor
+        .export_job_rejections(job.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("导出拒绝记录失败: {}", e)))?;
+    rejection_workbook_response(&job.file_name, excel_data)
 }
 
 // 多语言API处理器
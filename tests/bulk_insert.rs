@@ -0,0 +1,67 @@
+mod common;
+
+use excel_data_hub::excel_processor_sea::{ExcelProcessor, ImportOptions};
+use excel_data_hub::models::entity::{excel_data, import_rejections};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+
+/// 超过一批的数据行分批插入，某一批中有数据库拒绝的行时逐条重试，只拒绝该行，需要PostgreSQL
+#[tokio::test]
+async fn rejects_only_the_failing_row_of_a_batch() {
+    let Some((db, user, workspace)) = common::setup("bulk_insert_test").await else {
+        return;
+    };
+
+    // 第1500行的备注超过tsvector的1MB上限，生成search_vector时被数据库拒绝
+    let oversized: Vec<String> = (0..150_000).map(|word| format!("w{}", word)).collect();
+    let oversized = oversized.join(" ");
+    let mut contents = String::from("编号,城市,备注\n");
+    for row in 1..=2500 {
+        let note = if row == 1500 { oversized.as_str() } else { "" };
+        contents.push_str(&format!("{},乌鲁木齐,{}\n", row, note));
+    }
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_bulk_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file_path = dir.join("cities.csv");
+    std::fs::write(&file_path, contents).unwrap();
+
+    let summary = ExcelProcessor::new(db.clone())
+        .import_uploaded_file(
+            workspace.id,
+            file_path.to_str().unwrap(),
+            Some(user.id),
+            "cities.csv",
+            None,
+            &ImportOptions::default(),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!((summary.inserted_rows, summary.rejected_rows), (2499, 1));
+
+    let file_id = summary.file_id.unwrap();
+    let rows = excel_data::Entity::find().filter(excel_data::Column::FileId.eq(file_id));
+    assert_eq!(rows.clone().count(&db).await.unwrap(), 2499);
+    // 同一批的其余行与后续批次照常写入，行号保持不变
+    let row_numbers: Vec<i32> = rows
+        .order_by_asc(excel_data::Column::RowNumber)
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.row_number)
+        .collect();
+    assert_eq!(row_numbers[1498..1500], [1499, 1501]);
+    assert_eq!(row_numbers.last(), Some(&2500));
+
+    let rejections = import_rejections::Entity::find()
+        .filter(import_rejections::Column::FileId.eq(file_id))
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(rejections.len(), 1);
+    assert_eq!(rejections[0].row_number, 1500);
+    assert!(rejections[0].reasons.to_string().contains("写入数据库失败"), "{}", rejections[0].reasons);
+
+    common::teardown(&db, &user).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,53 @@
+// 需要PostgreSQL的测试共用的准备工作
+use chrono::Utc;
+use excel_data_hub::database_sea::connect_database;
+use excel_data_hub::models::entity::{users, workspaces};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+
+/// 连接TEST_DATABASE_URL指定的测试库，并重新创建测试用户及其工作区；未设置时返回None，调用方跳过测试
+///
+/// 测试会写入并删除数据，TEST_DATABASE_URL应指向专用的测试库。
+pub async fn setup(username: &str) -> Option<(DatabaseConnection, users::Model, workspaces::Model)> {
+    let Ok(database_url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("未设置TEST_DATABASE_URL，跳过");
+        return None;
+    };
+    std::env::set_var("DATABASE_URL", database_url);
+    let db = connect_database().await.unwrap();
+
+    // 上次运行中断时留下的数据随用户一并删除
+    users::Entity::delete_many()
+        .filter(users::Column::Username.eq(username))
+        .exec(&db)
+        .await
+        .unwrap();
+    let now = Utc::now();
+    let user = users::ActiveModel {
+        username: Set(username.into()),
+        password_hash: Set(String::new()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let workspace = workspaces::ActiveModel {
+        owner_id: Set(user.id),
+        name: Set(format!("{} workspace", username)),
+        description: Set(None),
+        is_public: Set(false),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    Some((db, user, workspace))
+}
+
+/// 删除测试用户，其工作区、文件与任务随之删除
+pub async fn teardown(db: &DatabaseConnection, user: &users::Model) {
+    users::Entity::delete_by_id(user.id).exec(db).await.unwrap();
+}
@@ -0,0 +1,134 @@
+use calamine::Data;
+use encoding_rs::{GB18030, UTF_16BE, UTF_16LE, UTF_8};
+use excel_data_hub::csv_reader::{decode_text, detect_dialect, is_delimited_text_file, read_delimited_range, CsvDialect};
+use std::path::PathBuf;
+
+fn write_temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_csv_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+/// 读取文件并按行返回单元格文本，空单元格为空字符串
+fn read_rows(name: &str, bytes: &[u8]) -> Vec<Vec<String>> {
+    let path = write_temp_file(name, bytes);
+    let range = read_delimited_range(path.to_str().unwrap()).unwrap();
+    range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    Data::String(s) => s.clone(),
+                    Data::Empty => String::new(),
+                    other => panic!("文本表格只应产生字符串单元格: {:?}", other),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+    let mut bytes = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+#[test]
+fn recognizes_delimited_text_extensions() {
+    assert!(is_delimited_text_file("/tmp/a.csv"));
+    assert!(is_delimited_text_file("/tmp/a.TSV"));
+    assert!(!is_delimited_text_file("/tmp/a.xlsx"));
+    assert!(!is_delimited_text_file("/tmp/csv"));
+}
+
+#[test]
+fn decodes_by_bom_before_guessing() {
+    let (text, encoding) = decode_text("\u{FEFF}姓名,城市".as_bytes());
+    assert_eq!((text.as_str(), encoding), ("姓名,城市", UTF_8));
+
+    let (text, encoding) = decode_text(&utf16le("姓名,城市", true));
+    assert_eq!((text.as_str(), encoding), ("姓名,城市", UTF_16LE));
+
+    let mut big_endian = vec![0xFE, 0xFF];
+    big_endian.extend("a,b".encode_utf16().flat_map(u16::to_be_bytes));
+    let (text, encoding) = decode_text(&big_endian);
+    assert_eq!((text.as_str(), encoding), ("a,b", UTF_16BE));
+}
+
+#[test]
+fn guesses_utf16_without_bom_from_zero_bytes() {
+    let (text, encoding) = decode_text(&utf16le("name,city\nAli,Kashgar\n", false));
+    assert_eq!((text.as_str(), encoding), ("name,city\nAli,Kashgar\n", UTF_16LE));
+}
+
+#[test]
+fn falls_back_to_gbk_for_invalid_utf8() {
+    let (gbk, _, _) = GB18030.encode("姓名,城市\n阿里,喀什\n");
+    assert!(std::str::from_utf8(&gbk).is_err());
+    let (text, encoding) = decode_text(&gbk);
+    assert_eq!((text.as_str(), encoding), ("姓名,城市\n阿里,喀什\n", GB18030));
+
+    let (text, encoding) = decode_text("姓名,城市".as_bytes());
+    assert_eq!((text.as_str(), encoding), ("姓名,城市", UTF_8));
+}
+
+#[test]
+fn detects_delimiter_from_consistent_field_counts() {
+    for delimiter in [b',', b'\t', b';', b'|'] {
+        let sep = delimiter as char;
+        let text = format!("姓名{0}城市{0}金额\n阿里{0}喀什{0}1000\n古丽{0}和田{0}2500\n", sep);
+        assert_eq!(detect_dialect(&text), CsvDialect { delimiter, quote: b'"' }, "{:?}", sep);
+    }
+
+    // 分号分隔的欧洲格式中逗号是小数点，不应被当作分隔符
+    let text = "名称;金额\n苹果;1,5\n香蕉;2,75\n";
+    assert_eq!(detect_dialect(text).delimiter, b';');
+}
+
+#[test]
+fn detects_single_quotes_around_delimiters() {
+    let text = "name,city\n'Ali, Jr.','Kashgar, Xinjiang'\n'Gul, Sr.','Hotan, Xinjiang'\n";
+    assert_eq!(detect_dialect(text), CsvDialect { delimiter: b',', quote: b'\'' });
+}
+
+#[test]
+fn reads_tsv_with_quoted_fields_and_ragged_rows() {
+    let rows = read_rows("people.tsv", "姓名\t备注\t城市\n阿里\t\"含\t制表符\"\t喀什\n古丽\t\n".as_bytes());
+    assert_eq!(
+        rows,
+        vec![
+            vec!["姓名", "备注", "城市"],
+            vec!["阿里", "含\t制表符", "喀什"],
+            // 字段数不足的行补齐为空单元格
+            vec!["古丽", "", ""],
+        ]
+    );
+}
+
+#[test]
+fn bom_is_not_part_of_the_first_header() {
+    let rows = read_rows("bom.csv", "\u{FEFF}姓名,城市\n阿里,喀什\n".as_bytes());
+    assert_eq!(rows[0], vec!["姓名", "城市"]);
+}
+
+#[test]
+fn reads_gbk_encoded_file() {
+    let (gbk, _, _) = GB18030.encode("姓名,城市\n阿里,乌鲁木齐\n");
+    let rows = read_rows("gbk.csv", &gbk);
+    assert_eq!(rows, vec![vec!["姓名", "城市"], vec!["阿里", "乌鲁木齐"]]);
+}
+
+#[test]
+fn honors_excel_separator_hint() {
+    // 首行 sep= 声明优先于自动检测，且不作为数据行
+    let rows = read_rows("hint.csv", b"sep=|\r\na,b|c\r\n1,2|3\r\n");
+    assert_eq!(rows, vec![vec!["a,b", "c"], vec!["1,2", "3"]]);
+}
+
+#[test]
+fn empty_file_has_no_cells() {
+    let path = write_temp_file("empty.csv", b"");
+    assert!(read_delimited_range(path.to_str().unwrap()).unwrap().is_empty());
+}
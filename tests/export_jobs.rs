@@ -0,0 +1,130 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use excel_data_hub::excel_processor_sea::SearchMode;
+use excel_data_hub::export_jobs::{download_state, DownloadState, ExportJobQueue, ExportRequest};
+use excel_data_hub::models::entity::export_jobs;
+use excel_data_hub::search_export::{ExportColumns, ExportContext, ExportFormat};
+use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait, Statement};
+use serde_json::json;
+
+fn job(status: &str) -> export_jobs::Model {
+    let now = Utc::now();
+    export_jobs::Model {
+        id: 1,
+        created_by: 1,
+        workspace_id: None,
+        format: "csv".into(),
+        request: json!({}),
+        status: status.into(),
+        total_rows: 10,
+        file_path: Some("/tmp/export_1_1.csv".into()),
+        file_name: Some("export.csv".into()),
+        file_size: Some(100),
+        error: None,
+        attempts: 1,
+        created_at: now,
+        started_at: Some(now),
+        finished_at: Some(now),
+        expires_at: Some(now + Duration::hours(1)),
+        updated_at: now,
+    }
+}
+
+#[test]
+fn completed_job_within_retention_is_ready() {
+    let job = job("completed");
+    assert_eq!(
+        download_state(&job, Utc::now()),
+        DownloadState::Ready { file_path: "/tmp/export_1_1.csv", file_name: "export.csv" }
+    );
+}
+
+#[test]
+fn unfinished_and_failed_jobs_are_not_ready() {
+    for status in ["queued", "running"] {
+        assert_eq!(
+            download_state(&job(status), Utc::now()),
+            DownloadState::NotReady("导出任务尚未完成".into()),
+            "{}",
+            status
+        );
+    }
+
+    let mut failed = job("failed");
+    failed.error = Some("磁盘已满".into());
+    assert_eq!(download_state(&failed, Utc::now()), DownloadState::NotReady("导出任务失败: 磁盘已满".into()));
+}
+
+#[test]
+fn expired_or_cleaned_up_jobs_are_expired() {
+    assert_eq!(download_state(&job("expired"), Utc::now()), DownloadState::Expired);
+
+    // 清理任务尚未运行，但已超过保留期
+    let completed = job("completed");
+    assert_eq!(download_state(&completed, Utc::now() + Duration::hours(2)), DownloadState::Expired);
+
+    let mut missing = job("completed");
+    missing.file_path = None;
+    assert_eq!(download_state(&missing, Utc::now()), DownloadState::Expired);
+}
+
+/// 模拟执行任务的实例已停止：将任务的心跳时间改到租约之前，可同时改写执行次数
+async fn expire_lease(db: &DatabaseConnection, job_id: i32, attempts: Option<i32>) {
+    db.execute(Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Postgres,
+        "UPDATE export_jobs SET updated_at = NOW() - INTERVAL '10 minutes', attempts = COALESCE($2, attempts) WHERE id = $1",
+        [job_id.into(), attempts.into()],
+    ))
+    .await
+    .unwrap();
+}
+
+/// 领取与中断恢复需要PostgreSQL，设置TEST_DATABASE_URL（专用的测试库）后运行
+#[tokio::test]
+async fn recovers_only_jobs_whose_lease_expired() {
+    let Some((db, user, workspace)) = common::setup("export_jobs_lease_test").await else {
+        return;
+    };
+
+    let queue = ExportJobQueue::new(db.clone(), std::env::temp_dir().to_string_lossy().into_owned(), 24);
+    let request = ExportRequest {
+        query: "乌鲁木齐".into(),
+        filters: Vec::new(),
+        search: SearchMode::FullText,
+        context: ExportContext { query: "乌鲁木齐".into(), scope: "公开数据".into(), ..Default::default() },
+        columns: ExportColumns::default(),
+    };
+    let queued = queue.enqueue(user.id, Some(workspace.id), ExportFormat::Csv, &request).await.unwrap();
+
+    let claimed = queue.claim_next_job().await.unwrap().unwrap();
+    assert_eq!(claimed.id, queued.id);
+    assert_eq!((claimed.status.as_str(), claimed.attempts), ("running", 1));
+    assert!(queue.claim_next_job().await.unwrap().is_none());
+
+    // 租约未过期：其他实例仍在执行，不重新排队
+    queue.recover_interrupted_jobs().await.unwrap();
+    let current = export_jobs::Entity::find_by_id(queued.id).one(&db).await.unwrap().unwrap();
+    assert_eq!(current.status, "running");
+
+    // 心跳停止超过租约时间后重新排队，可以再次领取
+    expire_lease(&db, queued.id, None).await;
+    queue.recover_interrupted_jobs().await.unwrap();
+    let current = export_jobs::Entity::find_by_id(queued.id).one(&db).await.unwrap().unwrap();
+    assert_eq!(current.status, "queued");
+
+    let reclaimed = queue.claim_next_job().await.unwrap().unwrap();
+    assert_eq!((reclaimed.id, reclaimed.attempts), (queued.id, 2));
+
+    // 达到最大执行次数后中断的任务标记为失败
+    expire_lease(&db, queued.id, Some(3)).await;
+    queue.recover_interrupted_jobs().await.unwrap();
+    let current = export_jobs::Entity::find_by_id(queued.id).one(&db).await.unwrap().unwrap();
+    assert_eq!(current.status, "failed");
+    assert_eq!(
+        download_state(&current, Utc::now()),
+        DownloadState::NotReady("导出任务失败: 任务执行中断，且已达到最大重试次数".into())
+    );
+
+    common::teardown(&db, &user).await;
+}
@@ -0,0 +1,743 @@
+use excel_data_hub::excel_processor_sea::{ExcelProcessor, ImportMode, ImportOptions, SheetSkipReason, SheetVisibility};
+use excel_data_hub::column_profile::ColumnMapping;
+use excel_data_hub::models::entity::import_rejections;
+use excel_data_hub::row_validation::{RowValidator, ValidationRules};
+use excel_data_hub::sheet_header::disambiguate_headers;
+use excel_data_hub::file_format::{detect_file_format, FileFormat};
+use excel_data_hub::web_server::{DuplicateAction, DuplicatePolicy};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+/// 将夹具复制到临时目录并改用指定文件名，模拟扩展名与内容不符的上传
+fn copy_fixture_as(name: &str, target_name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join(target_name);
+    std::fs::copy(fixture(name), &target).unwrap();
+    target
+}
+
+fn assert_sample_rows(path: &Path, expected_sheet: &str) {
+    let (sheets, field_order) = ExcelProcessor::read_excel_file(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(field_order, vec!["姓名", "城市", "金额"]);
+    assert_eq!(sheets.len(), 1);
+
+    let (sheet_name, rows) = &sheets[0];
+    assert_eq!(sheet_name, expected_sheet);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].data["姓名"], Value::String("阿里".into()));
+    assert_eq!(rows[0].data["城市"], Value::String("乌鲁木齐".into()));
+    assert_eq!(rows[0].data["金额"], Value::String("1000".into()));
+    assert_eq!(rows[1].data["姓名"], Value::String("Bob".into()));
+    assert_eq!(rows[1].data["金额"], Value::String("2500.5".into()));
+}
+
+#[test]
+fn detects_formats_by_content() {
+    let cases = [
+        ("sample.xlsx", FileFormat::Xlsx),
+        ("sample.xlsb", FileFormat::Xlsb),
+        ("sample.ods", FileFormat::Ods),
+        ("sample.csv", FileFormat::Csv),
+    ];
+    for (name, expected) in cases {
+        assert_eq!(detect_file_format(&fixture(name), name).unwrap(), Some(expected), "{}", name);
+    }
+}
+
+#[test]
+fn detection_ignores_misleading_file_names() {
+    let ods = copy_fixture_as("sample.ods", "report.xlsx");
+    assert_eq!(detect_file_format(&ods, "report.xlsx").unwrap(), Some(FileFormat::Ods));
+
+    let xlsb = copy_fixture_as("sample.xlsb", "report.bin");
+    assert_eq!(detect_file_format(&xlsb, "report.bin").unwrap(), Some(FileFormat::Xlsb));
+}
+
+#[test]
+fn rejects_unknown_binary_content() {
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("image.xlsx");
+    std::fs::write(&path, [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D, 1, 2, 3]).unwrap();
+    assert_eq!(detect_file_format(&path, "image.xlsx").unwrap(), None);
+}
+
+#[test]
+fn rejects_text_without_a_delimited_table() {
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let detect = |name: &str, contents: &str| {
+        let path = dir.join(format!("text_{}", name));
+        std::fs::write(&path, contents).unwrap();
+        detect_file_format(&path, name).unwrap()
+    };
+
+    let html = "<!DOCTYPE html>\n<html>\n<head><title>报表</title></head>\n<body><p>你好, 世界</p></body>\n</html>\n";
+    let json = "{\n  \"name\": \"阿里\",\n  \"city\": \"喀什\"\n}\n";
+    let text = "这是一段普通文本。\n第二行内容。\n";
+    for (name, contents) in [("page.html", html), ("data.json", json), ("notes.txt", text), ("report.xlsx", html)] {
+        assert_eq!(detect(name, contents), None, "{}", name);
+    }
+    // 原始扩展名为CSV/TSV时允许单列表格，否则必须识别出分隔符
+    assert_eq!(detect("names.csv", text), Some(FileFormat::Csv));
+    assert_eq!(detect("names.tsv", text), Some(FileFormat::Tsv));
+    assert_eq!(detect("people.txt", "姓名\t城市\n阿里\t喀什\n"), Some(FileFormat::Tsv));
+}
+
+#[test]
+fn imports_xlsx_fixture() {
+    assert_sample_rows(&fixture("sample.xlsx"), "Data");
+}
+
+#[test]
+fn imports_ods_fixture() {
+    assert_sample_rows(&fixture("sample.ods"), "Data");
+}
+
+#[test]
+fn imports_xlsb_fixture() {
+    assert_sample_rows(&fixture("sample.xlsb"), "Data");
+}
+
+#[test]
+fn imports_gbk_csv_fixture() {
+    assert_sample_rows(&fixture("sample.csv"), "Sheet1");
+}
+
+#[test]
+fn keeps_native_types_for_numeric_cells() {
+    for name in ["sample.xlsx", "sample.ods", "sample.xlsb"] {
+        let (sheets, _) = ExcelProcessor::read_excel_file(fixture(name).to_str().unwrap()).unwrap();
+        let rows = &sheets[0].1;
+        assert_eq!(rows[0].typed["金额"], json!({"type": "number", "value": 1000.0}), "{}", name);
+        assert_eq!(rows[1].typed["金额"], json!({"type": "number", "value": 2500.5}), "{}", name);
+        assert!(!rows[0].typed.contains_key("姓名"), "{}", name);
+    }
+}
+
+#[test]
+fn csv_cells_have_no_native_types() {
+    let (sheets, _) = ExcelProcessor::read_excel_file(fixture("sample.csv").to_str().unwrap()).unwrap();
+    assert!(sheets[0].1.iter().all(|row| row.typed.is_empty()));
+}
+
+#[test]
+fn converts_date_formatted_cells_to_iso() {
+    let (sheets, _) = ExcelProcessor::read_excel_file(fixture("dates.xlsx").to_str().unwrap()).unwrap();
+    let row = &sheets[0].1[0];
+
+    assert_eq!(row.data["日期"], Value::String("2024-01-15".into()));
+    assert_eq!(row.data["时间戳"], Value::String("2024-01-15 13:30:00".into()));
+    assert_eq!(row.data["时刻"], Value::String("13:30:00".into()));
+    assert_eq!(row.data["用时"], Value::String("36:30:00".into()));
+    // 未设置日期格式的数字保持原样
+    assert_eq!(row.data["数量"], Value::String("45306".into()));
+
+    assert_eq!(row.typed["日期"], json!({"type": "date", "value": "2024-01-15", "serial": 45306.0}));
+    assert_eq!(
+        row.typed["时间戳"],
+        json!({"type": "datetime", "value": "2024-01-15T13:30:00", "serial": 45306.5625})
+    );
+    assert_eq!(row.typed["时刻"], json!({"type": "time", "value": "13:30:00", "serial": 0.5625}));
+    assert_eq!(row.typed["用时"]["value"], json!("PT36H30M0S"));
+}
+
+#[test]
+fn keeps_midnight_times_and_datetimes() {
+    let (sheets, _) = ExcelProcessor::read_excel_file(fixture("midnight_dates.xlsx").to_str().unwrap()).unwrap();
+    let rows = &sheets[0].1;
+
+    // 00:00的时间单元格序列号为0，不能当作1899-12-31
+    assert_eq!(rows[1].data["时刻"], Value::String("00:00:00".into()));
+    assert_eq!(rows[1].typed["时刻"], json!({"type": "time", "value": "00:00:00", "serial": 0.0}));
+
+    // 同一列有带时刻的值时，零点仍是日期时间
+    assert_eq!(rows[1].data["时间点"], Value::String("2024-01-16 00:00:00".into()));
+    assert_eq!(
+        rows[1].typed["时间点"],
+        json!({"type": "datetime", "value": "2024-01-16T00:00:00", "serial": 45307.0})
+    );
+    assert_eq!(rows[1].typed["日期"], json!({"type": "date", "value": "2024-01-16", "serial": 45307.0}));
+}
+
+#[test]
+fn honors_1904_date_system() {
+    let (sheets, _) = ExcelProcessor::read_excel_file(fixture("dates_1904.xlsx").to_str().unwrap()).unwrap();
+    let row = &sheets[0].1[0];
+
+    // 1904日期系统的序列号比1900日期系统少1462天
+    assert_eq!(row.data["日期"], Value::String("2028-01-16".into()));
+    assert_eq!(row.typed["日期"]["serial"], json!(45306.0));
+    assert_eq!(row.data["时刻"], Value::String("13:30:00".into()));
+}
+
+const MULTI_HEADER_COLUMNS: [&str; 9] = [
+    "序号",
+    "姓名",
+    "基本信息 / 性别",
+    "基本信息 / 年龄",
+    "基本信息 / 民族",
+    "联系方式 / 电话",
+    "联系方式 / 电话 (2)",
+    "EMPTY_8",
+    "姓名 (2)",
+];
+
+fn assert_multi_header_rows(options: ImportOptions) {
+    let (sheets, field_order) =
+        ExcelProcessor::read_excel_file_with_options(fixture("multi_header.xlsx").to_str().unwrap(), &options).unwrap();
+
+    assert_eq!(field_order, MULTI_HEADER_COLUMNS);
+    let rows = &sheets[0].1;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].data["基本信息 / 民族"], Value::String("维吾尔".into()));
+    assert_eq!(rows[0].data["联系方式 / 电话 (2)"], Value::String("456".into()));
+    assert_eq!(rows[0].data["EMPTY_8"], Value::String("备注".into()));
+    assert_eq!(rows[0].data["姓名 (2)"], Value::String("别名".into()));
+}
+
+#[test]
+fn reads_multi_row_header_at_given_row() {
+    assert_multi_header_rows(ImportOptions {
+        header_row: Some(3),
+        header_rows: 2,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn detects_header_below_title_rows() {
+    assert_multi_header_rows(ImportOptions {
+        header_rows: 2,
+        auto_detect_header: true,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn rejects_header_row_outside_sheet() {
+    let options = ImportOptions {
+        header_row: Some(100),
+        ..Default::default()
+    };
+    let result =
+        ExcelProcessor::read_excel_file_with_options(fixture("multi_header.xlsx").to_str().unwrap(), &options);
+    assert!(result.is_err());
+}
+
+#[test]
+fn disambiguates_blank_and_duplicate_headers() {
+    let headers = ["Name", "", "Name", "Name (2)", "", "Name"].map(String::from).to_vec();
+    assert_eq!(
+        disambiguate_headers(headers),
+        vec!["Name", "EMPTY_2", "Name (2)", "Name (2) (2)", "EMPTY_5", "Name (3)"]
+    );
+}
+
+async fn read_merged_data(fill_merged_cells: bool) -> Vec<excel_data_hub::excel_processor_sea::SheetRow> {
+    let options = ImportOptions {
+        fill_merged_cells,
+        ..Default::default()
+    };
+    let (mut sheets, _) =
+        ExcelProcessor::read_excel_file_with_options(fixture("merged_data.xlsx").to_str().unwrap(), &options).unwrap();
+    sheets.remove(0).1
+}
+
+#[tokio::test]
+async fn fills_merged_data_cells_when_requested() {
+    let rows = read_merged_data(true).await;
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1].data["地区"], Value::String("北京".into()));
+    assert_eq!(rows[1].data["门店"], Value::String("乙".into()));
+    assert_eq!(rows[1].merged_fills.len(), 1);
+    assert_eq!(rows[1].merged_fills[0].field, "地区");
+    assert_eq!(rows[1].merged_fills[0].cell, "A3");
+    assert_eq!(rows[1].merged_fills[0].source, "A2");
+    assert!(rows[0].merged_fills.is_empty());
+    assert!(rows[2].merged_fills.is_empty());
+}
+
+#[tokio::test]
+async fn leaves_merged_data_cells_empty_by_default() {
+    let rows = read_merged_data(false).await;
+    assert_eq!(rows[1].data["地区"], Value::Null);
+    assert!(rows.iter().all(|row| row.merged_fills.is_empty()));
+}
+
+#[test]
+fn summarizes_merged_fills_per_range_under_mapped_names() {
+    use excel_data_hub::excel_processor_sea::{MergedFill, SheetRow};
+
+    let fill = |field: &str, cell: &str, source: &str| MergedFill {
+        field: field.to_string(),
+        cell: cell.to_string(),
+        source: source.to_string(),
+    };
+    let row = |fills: Vec<MergedFill>| SheetRow { merged_fills: fills, ..Default::default() };
+    let rows = vec![
+        row(vec![]),
+        row(vec![fill("电话", "A3", "A2"), fill("备注", "B3", "B2")]),
+        row(vec![fill("电话", "A4", "A2"), fill("备注", "B4", "B2")]),
+        row(vec![fill("部门", "C5", "C4")]),
+    ];
+    let mapping = (3, branch_mapping());
+
+    let metadata = ExcelProcessor::build_import_metadata(&[("销量".to_string(), rows)], &ImportOptions::default(), Some(&mapping));
+
+    assert_eq!(metadata["column_profile_version"], 3);
+    // 被规则删除的“备注”列不记录
+    assert_eq!(
+        metadata["merged_fills"],
+        json!([
+            {"sheet": "销量", "field": "手机号", "source": "A2", "first_row": 2, "last_row": 3, "cells": 2},
+            {"sheet": "销量", "field": "部门", "source": "C4", "first_row": 4, "last_row": 4, "cells": 1},
+        ])
+    );
+    assert_eq!(metadata["merged_fill_cells"], 3);
+    assert_eq!(metadata["merged_fills_truncated"], false);
+}
+
+#[test]
+fn caps_recorded_merged_fill_ranges() {
+    use excel_data_hub::excel_processor_sea::{MergedFill, SheetRow};
+
+    let rows: Vec<SheetRow> = (0..1500)
+        .map(|index| SheetRow {
+            merged_fills: vec![MergedFill {
+                field: "地区".to_string(),
+                cell: format!("B{}", index + 2),
+                source: format!("A{}", index + 2),
+            }],
+            ..Default::default()
+        })
+        .collect();
+
+    let metadata = ExcelProcessor::build_import_metadata(&[("Sheet1".to_string(), rows)], &ImportOptions::default(), None);
+
+    assert_eq!(metadata["merged_fills"].as_array().unwrap().len(), 1000);
+    assert_eq!(metadata["merged_fill_cells"], 1500);
+    assert_eq!(metadata["merged_fills_truncated"], true);
+}
+
+async fn imported_sheet_names(options: ImportOptions) -> Vec<String> {
+    let (sheets, _) = ExcelProcessor::read_excel_file_with_options(fixture("sheets.xlsx").to_str().unwrap(), &options).unwrap();
+    sheets.into_iter().map(|(name, _)| name).collect()
+}
+
+#[tokio::test]
+async fn selects_sheets_by_visibility_and_name() {
+    assert_eq!(
+        imported_sheet_names(ImportOptions::default()).await,
+        ["订单", "退货", "草稿", "Lookup"]
+    );
+    assert_eq!(
+        imported_sheet_names(ImportOptions {
+            skip_hidden_sheets: true,
+            ..Default::default()
+        })
+        .await,
+        ["订单", "退货"]
+    );
+    // 明确列出的隐藏工作表仍会导入，exclude_sheets优先
+    let options: ImportOptions = serde_json::from_value(json!({
+        "include_sheets": "订单, lookup,退货",
+        "exclude_sheets": ["退货"],
+        "skip_hidden_sheets": true,
+    }))
+    .unwrap();
+    assert_eq!(imported_sheet_names(options).await, ["订单", "Lookup"]);
+}
+
+#[test]
+fn parses_import_mode_and_key_columns() {
+    assert_eq!(ImportOptions::default().mode, ImportMode::Append);
+
+    let options: ImportOptions = serde_json::from_value(json!({"mode": "upsert", "key_columns": "订单号, 日期,"})).unwrap();
+    assert_eq!(options.mode, ImportMode::Upsert);
+    assert_eq!(options.key_columns, ["订单号", "日期"]);
+    assert!(serde_json::from_value::<ImportOptions>(json!({"mode": "merge"})).is_err());
+
+    let mapping: ColumnMapping = serde_json::from_value(json!({"key_columns": ["订单号", " "]})).unwrap();
+    assert!(mapping.validate().is_err());
+}
+
+#[test]
+fn parses_duplicate_upload_policy() {
+    assert_eq!(DuplicatePolicy::default(), DuplicatePolicy::Warn);
+    assert_eq!(serde_json::from_value::<DuplicatePolicy>(json!("skip")).unwrap(), DuplicatePolicy::Skip);
+    assert_eq!(serde_json::from_value::<DuplicatePolicy>(json!("link")).unwrap(), DuplicatePolicy::Link);
+    assert!(serde_json::from_value::<DuplicatePolicy>(json!("ignore")).is_err());
+}
+
+#[test]
+fn decides_duplicate_upload_action() {
+    for policy in [DuplicatePolicy::Warn, DuplicatePolicy::Skip, DuplicatePolicy::Link] {
+        assert_eq!(policy.action(false), None);
+    }
+    assert_eq!(DuplicatePolicy::Warn.action(true), Some(DuplicateAction::Warned));
+    assert_eq!(DuplicatePolicy::Skip.action(true), Some(DuplicateAction::Skipped));
+    assert_eq!(DuplicatePolicy::Link.action(true), Some(DuplicateAction::Linked));
+    assert_eq!(serde_json::to_value(DuplicateAction::Linked).unwrap(), json!("linked"));
+}
+
+#[test]
+fn rejects_selection_without_matching_sheets() {
+    let options = ImportOptions {
+        include_sheets: vec!["汇总".to_string()],
+        ..Default::default()
+    };
+    let result = ExcelProcessor::read_excel_file_with_options(fixture("sheets.xlsx").to_str().unwrap(), &options);
+    assert!(result.is_err());
+}
+
+#[test]
+fn previews_all_sheets_with_selection() {
+    let options = ImportOptions {
+        include_sheets: vec!["订单".to_string(), "汇总".to_string()],
+        ..Default::default()
+    };
+    let preview = ExcelProcessor::preview_excel_file(fixture("sheets.xlsx").to_str().unwrap(), &options).unwrap();
+
+    let summary: Vec<_> = preview
+        .sheets
+        .iter()
+        .map(|sheet| (sheet.name.as_str(), sheet.visibility, sheet.skip_reason, sheet.row_count))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("订单", SheetVisibility::Visible, None, 2),
+            ("退货", SheetVisibility::Visible, Some(SheetSkipReason::NotIncluded), 1),
+            ("草稿", SheetVisibility::Hidden, Some(SheetSkipReason::NotIncluded), 1),
+            ("Lookup", SheetVisibility::VeryHidden, Some(SheetSkipReason::NotIncluded), 2),
+        ]
+    );
+    assert_eq!(preview.sheets[0].headers, ["单号", "金额"]);
+    assert!(preview.sheets[0].selected);
+    assert_eq!(preview.missing_sheets, ["汇总"]);
+}
+
+#[test]
+fn preview_row_counts_match_imported_rows() {
+    // 行数按数据区域计算，与实际读取的数据行数一致（含多行表头、表头前的标题行）
+    for (name, options) in [
+        ("sample.csv", ImportOptions::default()),
+        ("sample.xlsx", ImportOptions::default()),
+        ("merged_data.xlsx", ImportOptions::default()),
+        ("multi_header.xlsx", ImportOptions { header_row: Some(3), header_rows: 2, ..Default::default() }),
+        ("multi_header.xlsx", ImportOptions { header_rows: 2, auto_detect_header: true, ..Default::default() }),
+    ] {
+        let path = fixture(name);
+        let preview = ExcelProcessor::preview_excel_file(path.to_str().unwrap(), &options).unwrap();
+        let (sheets, field_order) = ExcelProcessor::read_excel_file_with_options(path.to_str().unwrap(), &options).unwrap();
+        assert_eq!(preview.sheets[0].row_count, sheets[0].1.len(), "{}", name);
+        assert_eq!(preview.sheets[0].headers, field_order, "{}", name);
+    }
+
+    let options = ImportOptions { header_row: Some(100), ..Default::default() };
+    let preview = ExcelProcessor::preview_excel_file(fixture("multi_header.xlsx").to_str().unwrap(), &options).unwrap();
+    assert_eq!(preview.sheets[0].row_count, 0);
+    assert!(preview.sheets[0].error.as_deref().unwrap().contains("第 100 行"));
+}
+
+#[test]
+fn dry_run_only_reads_selected_sheets() {
+    let options = ImportOptions {
+        include_sheets: vec!["退货".to_string()],
+        ..Default::default()
+    };
+    let report = ExcelProcessor::dry_run_excel_file(fixture("sheets.xlsx").to_str().unwrap(), &options, None, 10).unwrap();
+
+    let summary: Vec<_> = report
+        .sheets
+        .iter()
+        .map(|sheet| (sheet.sheet.name.as_str(), sheet.sheet.skip_reason, sheet.sheet.row_count, sheet.sample_rows.len()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("订单", Some(SheetSkipReason::NotIncluded), 0, 0),
+            ("退货", None, 1, 1),
+            ("草稿", Some(SheetSkipReason::NotIncluded), 0, 0),
+            ("Lookup", Some(SheetSkipReason::NotIncluded), 0, 0),
+        ]
+    );
+    assert_eq!(report.total_rows, 1);
+}
+
+/// 在临时目录中写入测试文件
+fn write_temp_file(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join(name);
+    std::fs::write(&target, contents).unwrap();
+    target
+}
+
+#[test]
+fn dry_run_reports_types_samples_and_rejections() {
+    // 第4行的词条总长度超过tsvector上限，导入时会被数据库拒绝
+    let oversized: Vec<String> = (0..150_000).map(|i| format!("word{}", i)).collect();
+    let csv = format!(
+        "名称,数量,日期\n甲,10,2024-01-15\n乙\\u4e2d,abc,2024-02-01\n丙,30,\n{},40,2024-03-01\n",
+        oversized.join(" ")
+    );
+    let path = write_temp_file("dry_run.csv", &csv);
+
+    let report = ExcelProcessor::dry_run_excel_file(path.to_str().unwrap(), &ImportOptions::default(), None, 2).unwrap();
+
+    assert_eq!(report.total_rows, 4);
+    assert_eq!(report.rejected_count, 1);
+    assert_eq!(report.suspicious_count, 1);
+
+    let sheet = &report.sheets[0];
+    let types: Vec<_> = sheet.columns.iter().map(|column| (column.name.as_str(), column.inferred_type, column.filled)).collect();
+    assert_eq!(types, [("名称", "text", 4), ("数量", "mixed", 4), ("日期", "date", 3)]);
+
+    assert_eq!(sheet.sample_rows.len(), 2);
+    assert_eq!(sheet.sample_rows[1].row_number, 2);
+    assert_eq!(sheet.sample_rows[1].data["名称"], Value::String("乙\\u4e2d".into()));
+    assert_eq!(sheet.rejected_rows[0].row_number, 4);
+    assert_eq!(sheet.suspicious_escapes[0].row_number, 2);
+    assert_eq!(sheet.suspicious_escapes[0].field, "名称");
+}
+
+fn branch_mapping() -> ColumnMapping {
+    serde_json::from_value(json!({
+        "columns": [
+            {"target": "手机号", "sources": ["电话", "Phone"], "transforms": [{"type": "phone", "country_code": "86"}]},
+            {"target": "姓名", "sources": ["名字"], "transforms": ["trim", "upper"]},
+            {"target": "地址", "sources": ["省", "市"], "merge": "join", "separator": ""},
+            {"target": "入职日期", "transforms": ["date"]}
+        ],
+        "drop": ["备注"]
+    }))
+    .unwrap()
+}
+
+fn text_row(cells: &[(&str, &str)]) -> std::collections::HashMap<String, Value> {
+    cells.iter().map(|(key, value)| (key.to_string(), Value::String(value.to_string()))).collect()
+}
+
+#[test]
+fn applies_column_mapping_rules() {
+    let mapping = branch_mapping();
+    mapping.validate().unwrap();
+
+    let mut data = text_row(&[
+        ("PHONE", "+86 138-0013-8000"),
+        ("名字", " bob "),
+        ("省", "新疆"),
+        ("市", "乌鲁木齐"),
+        ("入职日期", "2024年1月5日"),
+        ("备注", "内部"),
+        ("部门", "销售"),
+    ]);
+    let mut typed = std::collections::HashMap::from([("PHONE".to_string(), json!({"type": "number", "value": 1}))]);
+    mapping.apply(&mut data, &mut typed);
+
+    assert_eq!(
+        data,
+        text_row(&[
+            ("手机号", "13800138000"),
+            ("姓名", "BOB"),
+            ("地址", "新疆乌鲁木齐"),
+            ("入职日期", "2024-01-05"),
+            ("部门", "销售"),
+        ])
+    );
+    assert_eq!(typed, std::collections::HashMap::from([("入职日期".to_string(), json!({"type": "date", "value": "2024-01-05"}))]));
+
+    let field_order = ["名字", "电话", "省", "市", "备注", "Phone"].map(String::from);
+    assert_eq!(mapping.map_field_order(&field_order), ["姓名", "手机号", "地址"]);
+}
+
+#[test]
+fn maps_single_fields_like_rows() {
+    let mapping = branch_mapping();
+    assert_eq!(mapping.map_field("phone").as_deref(), Some("手机号"));
+    assert_eq!(mapping.map_field("市").as_deref(), Some("地址"));
+    assert_eq!(mapping.map_field("部门").as_deref(), Some("部门"));
+    assert_eq!(mapping.map_field("备注"), None);
+}
+
+#[test]
+fn rejects_conflicting_column_mapping() {
+    let mapping: ColumnMapping = serde_json::from_value(json!({
+        "columns": [
+            {"target": "电话", "sources": ["手机"]},
+            {"target": "联系方式", "sources": ["手机"]}
+        ]
+    }))
+    .unwrap();
+    assert!(mapping.validate().is_err());
+
+    let unknown_transform = serde_json::from_value::<ColumnMapping>(json!({
+        "columns": [{"target": "电话", "transforms": ["reverse"]}]
+    }));
+    assert!(unknown_transform.is_err());
+}
+
+fn order_rules() -> ValidationRules {
+    serde_json::from_value(json!({
+        "rules": [
+            {"column": "订单号", "required": true, "pattern": "^SO\\d{4}$", "unique": true},
+            {"column": "数量", "min": 1, "max": 100},
+            {"column": "状态", "allowed": ["已发货", "待发货"]}
+        ]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn checks_row_validation_rules() {
+    let mut validator = RowValidator::new(&order_rules()).unwrap();
+    let mut check = |cells: &[(&str, &str)]| -> Vec<String> {
+        validator
+            .check_row(&text_row(cells), &std::collections::HashMap::new(), "Sheet1 第1行")
+            .into_iter()
+            .map(|violation| format!("{}:{}", violation.column, violation.rule))
+            .collect()
+    };
+
+    assert!(check(&[("订单号", "SO0001"), ("数量", "5"), ("状态", "已发货")]).is_empty());
+    assert_eq!(check(&[("数量", "0"), ("状态", "取消")]), ["订单号:required", "数量:range", "状态:allowed"]);
+    assert_eq!(check(&[("订单号", "SO0001"), ("数量", "abc")]), ["订单号:unique", "数量:range"]);
+    assert_eq!(check(&[("订单号", "X1"), ("数量", "100")]), ["订单号:pattern"]);
+    // 空白值按未填写处理
+    assert_eq!(check(&[("订单号", " "), ("数量", "1")]), ["订单号:required"]);
+
+    // 数字单元格按原生数值检查范围
+    let mut validator = RowValidator::new(&order_rules()).unwrap();
+    let typed = std::collections::HashMap::from([("数量".to_string(), json!({"type": "number", "value": 150.5}))]);
+    let violations = validator.check_row(&text_row(&[("订单号", "SO0003"), ("数量", "150.5")]), &typed, "Sheet1 第1行");
+    assert_eq!(violations[0].message, "数量 的值 150.5 超出范围 [1, 100]");
+}
+
+#[test]
+fn rejected_rows_do_not_claim_unique_values() {
+    let mut validator = RowValidator::new(&order_rules()).unwrap();
+    let mut check = |cells: &[(&str, &str)], location: &str| -> Vec<String> {
+        validator
+            .check_row(&text_row(cells), &std::collections::HashMap::new(), location)
+            .into_iter()
+            .map(|violation| violation.message)
+            .collect()
+    };
+
+    // 第1行因数量超出范围被拒绝，之后同一订单号的合格行不算重复
+    assert_eq!(check(&[("订单号", "SO0001"), ("数量", "500")], "Sheet1 第1行").len(), 1);
+    assert!(check(&[("订单号", "SO0001"), ("数量", "5")], "Sheet1 第2行").is_empty());
+    assert_eq!(
+        check(&[("订单号", "SO0001"), ("数量", "6")], "Sheet1 第3行"),
+        ["订单号 的值 SO0001 与Sheet1 第2行重复"]
+    );
+}
+
+#[test]
+fn rejects_invalid_validation_rules() {
+    let invalid_pattern: ColumnMapping = serde_json::from_value(json!({
+        "validation": {"rules": [{"column": "编号", "pattern": "("}]}
+    }))
+    .unwrap();
+    assert!(invalid_pattern.validate().is_err());
+
+    let inverted_range: ValidationRules = serde_json::from_value(json!({
+        "rules": [{"column": "数量", "min": 10, "max": 1}]
+    }))
+    .unwrap();
+    assert!(inverted_range.validate().is_err());
+}
+
+#[test]
+fn dry_run_reports_validation_rejections() {
+    let csv = "订单号,数量,状态\nSO0001,5,已发货\nSO0002,500,待发货\nSO0001,3,取消\n,2,已发货\n";
+    let path = write_temp_file("dry_run_validation.csv", csv);
+    let mapping: ColumnMapping = serde_json::from_value(json!({ "validation": order_rules() })).unwrap();
+
+    let report = ExcelProcessor::dry_run_excel_file(path.to_str().unwrap(), &ImportOptions::default(), Some(&mapping), 10).unwrap();
+
+    assert_eq!(report.total_rows, 4);
+    assert_eq!(report.rejected_count, 3);
+    let sheet = &report.sheets[0];
+    assert_eq!(sheet.sample_rows.len(), 1);
+    let rejected: Vec<_> = sheet
+        .rejected_rows
+        .iter()
+        .map(|row| (row.row_number, row.reasons.iter().map(|reason| reason.rule.as_str()).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(
+        rejected,
+        [(2, vec!["range"]), (3, vec!["unique", "allowed"]), (4, vec!["required"])]
+    );
+    assert_eq!(sheet.rejected_rows[1].reason, "订单号 的值 SO0001 与工作表 Sheet1 第 1 行重复; 状态 的值 取消 不在允许的取值中");
+}
+
+#[test]
+fn dry_run_reports_upsert_key_rejections() {
+    let csv = "订单号,数量\nSO0001,5\n,2\nso0002,3\nSO0001,4\n";
+    let path = write_temp_file("dry_run_keys.csv", csv);
+    let options = ImportOptions {
+        mode: ImportMode::Upsert,
+        key_columns: vec!["订单号".to_string()],
+        ..Default::default()
+    };
+
+    let report = ExcelProcessor::dry_run_excel_file(path.to_str().unwrap(), &options, None, 10).unwrap();
+
+    assert_eq!(report.rejected_count, 2);
+    let rejected: Vec<_> = report.sheets[0]
+        .rejected_rows
+        .iter()
+        .map(|row| (row.row_number, row.reason.as_str()))
+        .collect();
+    assert_eq!(
+        rejected,
+        [(2, "键列 订单号 不能为空"), (4, "键 (SO0001) 与工作表 Sheet1 第 1 行重复")]
+    );
+}
+
+#[test]
+fn builds_annotated_rejection_workbook() {
+    use calamine::{Data, Reader};
+
+    let rejection = |sheet: &str, row_number: i32, data: Value, reasons: Value| import_rejections::Model {
+        id: 0,
+        workspace_id: Some(1),
+        file_id: Some(1),
+        job_id: None,
+        sheet_name: sheet.to_string(),
+        row_number,
+        reasons,
+        data_json: data,
+        created_at: chrono::Utc::now(),
+    };
+    let rejections = [
+        rejection(
+            "订单",
+            3,
+            json!({"订单号": "SO0002", "数量": "500", "渠道": "线下"}),
+            json!([{"column": "数量", "rule": "range", "message": "数量 的值 500 超出范围 [1, 100]"}]),
+        ),
+        rejection("退货", 2, json!({"订单号": ""}), json!([{"column": "订单号", "rule": "required", "message": "订单号 不能为空"}])),
+    ];
+    let field_order = ["订单号", "数量"].map(String::from);
+
+    let buffer = ExcelProcessor::build_rejection_workbook(&field_order, &rejections).unwrap();
+    let mut workbook = calamine::Xlsx::new(std::io::Cursor::new(buffer)).unwrap();
+    assert_eq!(workbook.sheet_names(), ["订单", "退货"]);
+
+    let range = workbook.worksheet_range("订单").unwrap();
+    let rows: Vec<Vec<Data>> = range.rows().map(|row| row.to_vec()).collect();
+    let text = |cells: &[&str]| cells.iter().map(|cell| Data::String(cell.to_string())).collect::<Vec<_>>();
+    assert_eq!(rows[0], text(&["行号", "订单号", "数量", "渠道", "错误原因"]));
+    assert_eq!(rows[1][0], Data::Float(3.0));
+    assert_eq!(rows[1][1..], text(&["SO0002", "500", "线下", "数量 的值 500 超出范围 [1, 100]"]));
+}
@@ -0,0 +1,249 @@
+mod common;
+
+use excel_data_hub::column_profile::{self, ColumnMapping};
+use excel_data_hub::excel_processor_sea::{
+    ExcelProcessor, ImportEvent, ImportMode, ImportOptions, ImportProgress, ImportSummary,
+};
+use excel_data_hub::import_jobs::{ImportJobQueue, JobEvent};
+use excel_data_hub::models::entity::{excel_data, files, import_jobs};
+use excel_data_hub::row_validation::{self, RejectionOwner};
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, Statement};
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+async fn find(queue: &ImportJobQueue, job_id: i32) -> import_jobs::Model {
+    queue.find_job(job_id).await.unwrap().unwrap()
+}
+
+async fn wait_until_finished(queue: &ImportJobQueue, job_id: i32) -> import_jobs::Model {
+    let mut job = find(queue, job_id).await;
+    for _ in 0..100 {
+        if job.status != "queued" && job.status != "running" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        job = find(queue, job_id).await;
+    }
+    job
+}
+
+/// 模拟执行任务的实例已停止：将任务的心跳时间改到租约之前
+async fn expire_lease(db: &DatabaseConnection, job_id: i32) {
+    db.execute(Statement::from_sql_and_values(
+        sea_orm::DatabaseBackend::Postgres,
+        "UPDATE import_jobs SET updated_at = NOW() - INTERVAL '10 minutes' WHERE id = $1",
+        [job_id.into()],
+    ))
+    .await
+    .unwrap();
+}
+
+/// 领取、中断恢复与后台执行需要PostgreSQL，设置TEST_DATABASE_URL（专用的测试库）后运行
+#[tokio::test]
+async fn runs_queued_jobs_and_recovers_only_expired_leases() {
+    let Some((db, user, workspace)) = common::setup("import_jobs_lease_test").await else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_jobs_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file_path = dir.join("sample.csv");
+    std::fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sample.csv"), &file_path).unwrap();
+
+    let queue = ImportJobQueue::new(db.clone(), 1);
+    let queued = queue
+        .enqueue(
+            workspace.id,
+            user.id,
+            file_path.to_str().unwrap(),
+            "sample.csv",
+            "import_jobs_lease_test",
+            &ImportOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(queued.status, "queued");
+
+    let claimed = queue.claim_next_job().await.unwrap().unwrap();
+    assert_eq!(claimed.id, queued.id);
+    assert_eq!((claimed.status.as_str(), claimed.attempts), ("running", 1));
+    assert!(queue.claim_next_job().await.unwrap().is_none());
+
+    // 租约未过期：其他实例仍在执行，不重新排队
+    queue.recover_interrupted_jobs().await.unwrap();
+    assert_eq!(find(&queue, queued.id).await.status, "running");
+
+    // 心跳停止超过租约时间后重新排队，由工作线程再次执行
+    expire_lease(&db, queued.id).await;
+    queue.recover_interrupted_jobs().await.unwrap();
+    let requeued = find(&queue, queued.id).await;
+    assert_eq!((requeued.status.as_str(), requeued.rows_processed), ("queued", 0));
+
+    queue.start_workers(1);
+    let finished = wait_until_finished(&queue, queued.id).await;
+    assert_eq!(finished.status, "completed", "{:?}", finished.error);
+    assert_eq!((finished.attempts, finished.rows_inserted, finished.rows_rejected), (2, 2, 0));
+    assert!(finished.file_id.is_some());
+    assert!(finished.finished_at.is_some());
+
+    // 已结束的任务不受恢复影响
+    expire_lease(&db, queued.id).await;
+    queue.recover_interrupted_jobs().await.unwrap();
+    let current = import_jobs::Entity::find_by_id(queued.id).one(&db).await.unwrap().unwrap();
+    assert_eq!(current.status, "completed");
+
+    common::teardown(&db, &user).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn job_event(event: ImportEvent) -> JobEvent {
+    JobEvent { job_id: 7, workspace_id: 2, file_name: "sample.csv".into(), event }
+}
+
+#[test]
+fn job_events_are_named_like_their_payload() {
+    let events = [
+        ImportEvent::SheetStarted { sheet: "Sheet1".into(), total_rows: 5 },
+        ImportEvent::RowsInserted { sheet: "Sheet1".into(), sheet_rows: 2, file_rows: 2 },
+        ImportEvent::SheetFinished { sheet: "Sheet1".into(), rows: 5 },
+        ImportEvent::FileDone { total_rows: 5, summary: ImportSummary { inserted_rows: 5, ..Default::default() } },
+        ImportEvent::Failed { error: "文件格式不受支持".into() },
+    ];
+    let names: Vec<&str> = events.iter().cloned().map(|event| job_event(event).name()).collect();
+    assert_eq!(names, ["sheet_started", "rows_inserted", "sheet_finished", "file_done", "failed"]);
+
+    for event in events {
+        let event = job_event(event);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["event"], event.name());
+        assert_eq!((json["job_id"].clone(), json["workspace_id"].clone()), (json!(7), json!(2)));
+        // 只有文件完成或失败才结束单任务的事件流
+        assert_eq!(event.is_terminal(), matches!(event.name(), "file_done" | "failed"));
+    }
+
+    let done = job_event(ImportEvent::FileDone {
+        total_rows: 5,
+        summary: ImportSummary { file_id: Some(3), inserted_rows: 4, rejected_rows: 1, ..Default::default() },
+    });
+    let json = serde_json::to_value(&done).unwrap();
+    assert_eq!((json["file_id"].clone(), json["inserted_rows"].clone()), (json!(3), json!(4)));
+    assert_eq!((json["rejected_rows"].clone(), json["total_rows"].clone()), (json!(1), json!(5)));
+}
+
+/// 导入过程中按顺序上报进度事件，需要PostgreSQL
+#[tokio::test]
+async fn reports_progress_while_importing() {
+    let Some((db, user, workspace)) = common::setup("import_jobs_progress_test").await else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_progress_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file_path = dir.join("cities.csv");
+    std::fs::write(&file_path, "城市,人口\n乌鲁木齐,1\n喀什,2\n和田,3\n").unwrap();
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let progress = ImportProgress::new(sender, 2);
+    let summary = ExcelProcessor::new(db.clone())
+        .import_uploaded_file(
+            workspace.id,
+            file_path.to_str().unwrap(),
+            Some(user.id),
+            "cities.csv",
+            None,
+            &ImportOptions::default(),
+            Some(&progress),
+        )
+        .await
+        .unwrap();
+    drop(progress);
+    assert_eq!(summary.inserted_rows, 3);
+
+    let mut events = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        events.push(serde_json::to_value(job_event(event)).unwrap());
+    }
+    let names: Vec<&str> = events.iter().map(|event| event["event"].as_str().unwrap()).collect();
+    assert_eq!(names, ["sheet_started", "rows_inserted", "sheet_finished", "file_done"]);
+    assert_eq!(events[0]["total_rows"], 3);
+    // 最后一批插入完成时总会上报，即使未跨过上报间隔
+    assert_eq!((events[1]["sheet_rows"].clone(), events[1]["file_rows"].clone()), (json!(3), json!(3)));
+    assert_eq!(events[2]["rows"], 3);
+    assert_eq!((events[3]["total_rows"].clone(), events[3]["file_id"].clone()), (json!(3), json!(summary.file_id)));
+
+    common::teardown(&db, &user).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// 严格模式下整个文件被拒绝时不创建或改动文件记录，拒绝的行记录在任务下，需要PostgreSQL
+#[tokio::test]
+async fn strict_rejections_belong_to_the_job() {
+    let Some((db, user, workspace)) = common::setup("import_jobs_strict_test").await else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_strict_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut mapping: ColumnMapping =
+        serde_json::from_value(json!({"validation": {"rules": [{"column": "人口", "required": true}]}})).unwrap();
+    column_profile::save_profile(&db, workspace.id, user.id, &mapping).await.unwrap();
+
+    // 非严格模式导入的上一版本：一行被拒绝
+    let replace = ImportOptions { mode: ImportMode::Replace, ..Default::default() };
+    let first_path = dir.join("cities_v1.csv");
+    std::fs::write(&first_path, "城市,人口\n乌鲁木齐,1\n喀什,\n和田,3\n").unwrap();
+    let first = ExcelProcessor::new(db.clone())
+        .import_uploaded_file(workspace.id, first_path.to_str().unwrap(), Some(user.id), "cities.csv", None, &replace, None)
+        .await
+        .unwrap();
+    let file_id = first.file_id.unwrap();
+    assert_eq!((first.inserted_rows, first.rejected_rows), (2, 1));
+
+    mapping.validation.strict = true;
+    column_profile::save_profile(&db, workspace.id, user.id, &mapping).await.unwrap();
+
+    let queue = ImportJobQueue::new(db.clone(), 1);
+    let second_path = dir.join("cities_v2.csv");
+    std::fs::write(&second_path, "城市,人口\n乌鲁木齐,\n喀什,\n和田,3\n").unwrap();
+    let replaced = queue
+        .enqueue(workspace.id, user.id, second_path.to_str().unwrap(), "cities.csv", "strict_v2", &replace)
+        .await
+        .unwrap();
+    let new_path = dir.join("towns.csv");
+    std::fs::write(&new_path, "城市,人口\n伊宁,\n").unwrap();
+    let new_file = queue
+        .enqueue(workspace.id, user.id, new_path.to_str().unwrap(), "towns.csv", "strict_new", &ImportOptions::default())
+        .await
+        .unwrap();
+    queue.start_workers(1);
+
+    let replaced = wait_until_finished(&queue, replaced.id).await;
+    let new_file = wait_until_finished(&queue, new_file.id).await;
+    for (job, rejected) in [(&replaced, 2), (&new_file, 1)] {
+        assert_eq!(job.status, "failed");
+        assert_eq!((job.file_id, job.rows_rejected), (None, rejected));
+        let rejections = row_validation::list_rejections(&db, RejectionOwner::Job(job.id), None, 0).await.unwrap();
+        assert_eq!(rejections.len(), rejected as usize);
+        assert!(rejections.iter().all(|rejection| rejection.file_id.is_none()));
+    }
+
+    // 上一版本的数据与拒绝记录保持不变，新文件没有留下文件记录
+    let files = files::Entity::find()
+        .filter(files::Column::WorkspaceId.eq(workspace.id))
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(files.iter().map(|file| file.id).collect::<Vec<_>>(), [file_id]);
+    assert_eq!(files[0].file_path, first_path.to_str().unwrap());
+    let rows = excel_data::Entity::find()
+        .filter(excel_data::Column::FileId.eq(file_id))
+        .count(&db)
+        .await
+        .unwrap();
+    assert_eq!(rows, 2);
+    assert_eq!(row_validation::count_rejections(&db, RejectionOwner::File(file_id)).await.unwrap(), 1);
+
+    common::teardown(&db, &user).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
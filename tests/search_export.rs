@@ -0,0 +1,369 @@
+use excel_data_hub::excel_processor_sea::SearchMode;
+use excel_data_hub::export_jobs::ExportRequest;
+use excel_data_hub::models::entity::{excel_data, files};
+use excel_data_hub::search_export::{
+    ExportColumn, ExportColumns, ExportContext, ExportFormat, ExportInfo, ExportMetadataLabels, ExportOutput, ExportSheet,
+    SystemColumnLabels,
+};
+use serde_json::{json, Value};
+
+/// 导出测试用的分组（已按导出顺序排列）：两个同名文件、一个多工作表文件和一个文件名很长的CSV
+fn sample_export_groups(now: chrono::DateTime<chrono::Utc>) -> Vec<(ExportSheet, Vec<excel_data::Model>)> {
+    let file = |id: i32, name: &str, path: &str, field_order: Value| files::Model {
+        id,
+        workspace_id: Some(1),
+        uploaded_by: None,
+        file_path: path.to_string(),
+        file_name: name.to_string(),
+        file_size: 0,
+        file_hash: String::new(),
+        field_order: Some(field_order),
+        import_metadata: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let row = |id: i32, file_id: i32, sheet: &str, row_number: i32, data: Value| excel_data::Model {
+        id,
+        workspace_id: Some(1),
+        file_id,
+        import_time: now,
+        row_number,
+        sheet_name: sheet.to_string(),
+        data_json: data,
+        typed_json: json!({}),
+        search_text: String::new(),
+    };
+    let group = |file: &files::Model, sheet: &str, rows: Vec<excel_data::Model>| {
+        let fields: Vec<String> = rows
+            .iter()
+            .flat_map(|row| row.data_json.as_object().unwrap().keys().cloned())
+            .collect();
+        (ExportSheet::new(file.clone(), sheet.to_string(), fields, rows.len() as u64), rows)
+    };
+    let order = json!(["姓名", "城市", "金额"]);
+    let sales = file(1, "销售.xlsx", "uploads/1_a.xlsx", order.clone());
+    let sales_copy = file(2, "销售.xlsx", "uploads/1_b.xlsx", order);
+    let long_name = file(3, "二〇二六年第三季度华东区域各门店销售明细汇总表（含退货与调拨记录）.csv", "uploads/1_c.csv", json!(["编号"]));
+
+    vec![
+        group(&sales, "上海", vec![
+            row(4, 1, "上海", 2, json!({"姓名": "赵六", "城市": "上海", "金额": "40"})),
+            row(1, 1, "上海", 5, json!({"金额": "30", "姓名": "张三", "城市": "上海"})),
+        ]),
+        group(&sales_copy, "上海", vec![row(2, 2, "上海", 2, json!({"姓名": "李四", "金额": "10", "备注": "x"}))]),
+        group(&sales, "北京", vec![row(3, 1, "北京", 3, json!({"姓名": "王五", "城市": "北京", "金额": "20"}))]),
+        group(&long_name, "Sheet1", vec![row(5, 3, "Sheet1", 2, json!({"编号": "A1"}))]),
+    ]
+}
+
+/// 按分组逐行写入导出器（模拟分页读取），收集全部输出
+fn run_export(format: ExportFormat, groups: &[(ExportSheet, Vec<excel_data::Model>)], info: &ExportInfo) -> Vec<u8> {
+    use std::io::Read;
+
+    let sheets: Vec<ExportSheet> = groups.iter().map(|(sheet, _)| sheet.clone()).collect();
+    let mut exporter = format.exporter(&sheets, info).unwrap();
+    let mut output = exporter.take_output();
+    for (sheet_index, (_, rows)) in groups.iter().enumerate() {
+        for row in rows {
+            exporter.write_rows(sheet_index, std::slice::from_ref(row)).unwrap();
+            output.extend(exporter.take_output());
+        }
+    }
+    match exporter.finish().unwrap() {
+        ExportOutput::Bytes(rest) => output.extend(rest),
+        ExportOutput::File(mut file) => {
+            assert!(output.is_empty());
+            file.read_to_end(&mut output).unwrap();
+        }
+    }
+    output
+}
+
+#[test]
+fn exports_search_results_by_file_and_sheet_in_field_order() {
+    use calamine::{Data, Reader};
+
+    let now = chrono::Utc::now();
+    let groups = sample_export_groups(now);
+    assert_eq!(
+        groups[1].0.columns,
+        [
+            ExportColumn::RowNumber,
+            ExportColumn::Field("姓名".to_string()),
+            ExportColumn::Field("金额".to_string()),
+            ExportColumn::Field("备注".to_string()),
+            ExportColumn::ImportTime,
+        ]
+    );
+
+    let info = ExportInfo {
+        context: ExportContext {
+            query: "城市:上海 filter[金额]=10..40".to_string(),
+            scope: "Workspace 测试 (ID 1)".to_string(),
+            ..Default::default()
+        },
+        mode: SearchMode::FullText,
+        exported_at: now,
+        columns: ExportColumns::default(),
+    };
+    let buffer = run_export(ExportFormat::Xlsx, &groups, &info);
+    let mut workbook = calamine::Xlsx::new(std::io::Cursor::new(buffer)).unwrap();
+    assert_eq!(
+        workbook.sheet_names(),
+        ["销售 - 上海", "销售 - 上海 (2)", "销售 - 北京", "二〇二六年第三季度华东区域各门店销售明细汇总表（含退货与...", "导出信息"]
+    );
+
+    let range = workbook.worksheet_range("销售 - 上海").unwrap();
+    let rows: Vec<Vec<Data>> = range.rows().map(|row| row.to_vec()).collect();
+    let text = |cells: &[&str]| cells.iter().map(|cell| Data::String(cell.to_string())).collect::<Vec<_>>();
+    assert_eq!(rows[0], text(&["行号", "姓名", "城市", "金额", "导入时间"]));
+    assert_eq!(rows[1][..4], [Data::Float(2.0), Data::String("赵六".into()), Data::String("上海".into()), Data::String("40".into())]);
+    assert_eq!(rows.len(), 3);
+
+    let range = workbook.worksheet_range("导出信息").unwrap();
+    let rows: Vec<Vec<Data>> = range.rows().map(|row| row.to_vec()).collect();
+    assert_eq!(rows[0][..2], text(&["查询", "城市:上海 filter[金额]=10..40"]));
+    assert_eq!(rows[4][..2], text(&["导出行数", "5"]));
+    assert_eq!(rows[6][..2], text(&["工作表", "源文件"]));
+    assert_eq!(rows[8][..5], [
+        Data::String("销售 - 上海 (2)".into()),
+        Data::String("销售.xlsx".into()),
+        Data::Float(2.0),
+        Data::String("上海".into()),
+        Data::Float(1.0),
+    ]);
+}
+
+#[test]
+fn exports_search_results_as_csv_json_and_ndjson() {
+    let now = chrono::Utc::now();
+    let mut groups = sample_export_groups(now);
+    groups[0].1[1].typed_json = json!({"金额": {"type": "number", "value": 30}});
+    let info = ExportInfo {
+        context: ExportContext {
+            query: "上海".to_string(),
+            scope: "公开数据".to_string(),
+            ..Default::default()
+        },
+        mode: SearchMode::Fuzzy { threshold: 0.5 },
+        exported_at: now,
+        columns: ExportColumns::default(),
+    };
+    let time = now.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let csv = run_export(ExportFormat::Csv, &groups, &info);
+    assert!(csv.starts_with(b"\xEF\xBB\xBF"));
+    let csv = String::from_utf8(csv[3..].to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "源文件,工作表,行号,姓名,城市,金额,备注,编号,导入时间");
+    assert_eq!(lines[2], format!("销售.xlsx,上海,5,张三,上海,30,,,{}", time));
+    assert_eq!(lines.len(), 6);
+
+    let json = run_export(ExportFormat::Json, &groups, &info);
+    assert!(String::from_utf8_lossy(&json).contains(r#"{"行号":5,"姓名":"张三","城市":"上海","金额":30,"导入时间""#));
+    let json: Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["search"], json!({"mode": "fuzzy", "threshold": 0.5}));
+    assert_eq!(json["total_rows"], 5);
+    assert_eq!(json["sheets"].as_array().unwrap().len(), 4);
+    assert_eq!(json["sheets"][0]["columns"], json!(["行号", "姓名", "城市", "金额", "导入时间"]));
+    assert_eq!(json["sheets"][0]["rows"][1]["金额"], json!(30));
+    assert_eq!(json["sheets"][3]["rows"][0]["编号"], json!("A1"));
+
+    // 统计行数之后数据被删除时，total_rows按实际写出的行数
+    let mut shrunk = groups.clone();
+    shrunk[0].1.truncate(1);
+    let json: Value = serde_json::from_slice(&run_export(ExportFormat::Json, &shrunk, &info)).unwrap();
+    assert_eq!(shrunk[0].0.row_count, 2);
+    assert_eq!(json["total_rows"], 4);
+
+    let ndjson = String::from_utf8(run_export(ExportFormat::Ndjson, &groups, &info)).unwrap();
+    let first = ndjson.lines().next().unwrap();
+    assert!(first.starts_with(r#"{"源文件":"销售.xlsx","工作表":"上海","行号":2,"姓名":"赵六","城市":"上海","金额":"40""#));
+    assert_eq!(ndjson.lines().count(), 5);
+}
+
+#[test]
+fn export_job_request_round_trips_search_conditions() {
+    let request = ExportRequest {
+        query: "城市:上海".to_string(),
+        filters: vec![("filter[金额]".to_string(), "10..40".to_string())],
+        search: SearchMode::Fuzzy { threshold: 0.3 },
+        context: ExportContext {
+            query: "城市:上海 filter[金额]=10..40".to_string(),
+            scope: "公开数据".to_string(),
+            ..Default::default()
+        },
+        columns: ExportColumns::default(),
+    };
+    let stored = serde_json::to_value(&request).unwrap();
+    assert_eq!(stored["search"], json!({"mode": "fuzzy", "threshold": 0.3}));
+    assert_eq!(stored["filters"], json!([["filter[金额]", "10..40"]]));
+
+    let restored: ExportRequest = serde_json::from_value(stored).unwrap();
+    assert_eq!(restored, request);
+    let criteria = restored.criteria().unwrap();
+    assert!(criteria.query.is_some());
+    assert_eq!(criteria.filters.len(), 1);
+    assert_eq!(criteria.filters[0].field, "金额");
+
+    let fulltext: SearchMode = serde_json::from_value(json!({"mode": "fulltext"})).unwrap();
+    assert_eq!(fulltext, SearchMode::FullText);
+    let empty = ExportRequest {
+        query: " ".to_string(),
+        filters: Vec::new(),
+        search: fulltext,
+        context: request.context.clone(),
+        columns: ExportColumns::default(),
+    };
+    assert!(empty.criteria().is_err());
+    assert_eq!(ExportFormat::from_extension("ndjson"), Some(ExportFormat::Ndjson));
+    assert_eq!(ExportFormat::from_extension("pdf"), None);
+}
+
+#[test]
+fn exports_selected_columns_with_display_names_and_localized_system_columns() {
+    let labels = SystemColumnLabels {
+        file_name: "Source File".to_string(),
+        sheet_name: "Sheet".to_string(),
+        row_number: "Row Number".to_string(),
+        import_time: "Import Time".to_string(),
+    };
+    let columns = ExportColumns::parse(["金额=Amount", "@row_number", "姓名", "@file_name=文件"], labels.clone()).unwrap();
+    assert_eq!(columns.selected[0].column, ExportColumn::Field("金额".to_string()));
+    assert_eq!(columns.selected[1].column, ExportColumn::RowNumber);
+    assert_eq!(columns.header(&ExportColumn::RowNumber), "Row Number");
+    assert_eq!(columns.header(&ExportColumn::FileName), "文件");
+
+    let now = chrono::Utc::now();
+    let groups = sample_export_groups(now);
+    let info = ExportInfo {
+        context: ExportContext {
+            query: "上海".to_string(),
+            scope: "公开数据".to_string(),
+            ..Default::default()
+        },
+        mode: SearchMode::FullText,
+        exported_at: now,
+        columns,
+    };
+
+    let csv = String::from_utf8(run_export(ExportFormat::Csv, &groups, &info)[3..].to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "Amount,Row Number,姓名,文件");
+    assert_eq!(lines[1], "40,2,赵六,销售.xlsx");
+    assert_eq!(lines[5], ",2,,二〇二六年第三季度华东区域各门店销售明细汇总表（含退货与调拨记录）.csv");
+
+    let ndjson = String::from_utf8(run_export(ExportFormat::Ndjson, &groups, &info)).unwrap();
+    assert_eq!(ndjson.lines().next().unwrap(), r#"{"Amount":"40","Row Number":2,"姓名":"赵六","文件":"销售.xlsx"}"#);
+
+    let json: Value = serde_json::from_slice(&run_export(ExportFormat::Json, &groups, &info)).unwrap();
+    assert_eq!(json["sheets"][1]["columns"], json!(["Amount", "Row Number", "姓名", "文件"]));
+
+    // 未选择列时使用各格式默认的列，系统列表头同样本地化
+    let default_info = ExportInfo {
+        columns: ExportColumns::parse([], labels.clone()).unwrap(),
+        ..info
+    };
+    let csv = String::from_utf8(run_export(ExportFormat::Csv, &groups, &default_info)[3..].to_vec()).unwrap();
+    assert_eq!(csv.lines().next().unwrap(), "Source File,Sheet,Row Number,姓名,城市,金额,备注,编号,Import Time");
+
+    assert!(ExportColumns::parse(["@row"], labels.clone()).unwrap_err().contains("@row"));
+    assert!(ExportColumns::parse(["姓名", "姓名=名字"], labels.clone()).unwrap_err().contains("重复"));
+    assert!(ExportColumns::parse(["姓名", "金额=姓名"], labels.clone()).unwrap_err().contains("名称重复"));
+    assert!(ExportColumns::parse([" =名称"], labels).is_err());
+}
+
+#[test]
+fn export_column_specs_escape_equals_and_at_signs() {
+    let labels = SystemColumnLabels::default();
+    let columns = ExportColumns::parse([r"a\=b", r"\@tag=标签", r"c:\\=路径", r"x=y=z"], labels.clone()).unwrap();
+    let selected: Vec<(ExportColumn, Option<&str>)> = columns
+        .selected
+        .iter()
+        .map(|selected| (selected.column.clone(), selected.label.as_deref()))
+        .collect();
+    assert_eq!(
+        selected,
+        [
+            (ExportColumn::Field("a=b".to_string()), None),
+            // 转义的 @ 开头是字段而不是系统列
+            (ExportColumn::Field("@tag".to_string()), Some("标签")),
+            (ExportColumn::Field(r"c:\".to_string()), Some("路径")),
+            (ExportColumn::Field("x".to_string()), Some("y=z")),
+        ]
+    );
+    assert_eq!(
+        ExportColumns::parse([r"\@row_number"], labels.clone()).unwrap().selected[0].column,
+        ExportColumn::Field("@row_number".to_string())
+    );
+    assert!(ExportColumns::parse(["@tag"], labels).is_err());
+}
+
+#[test]
+fn default_export_columns_keep_headers_unique() {
+    let now = chrono::Utc::now();
+    let (sheet, rows) = sample_export_groups(now).remove(3);
+    // 字段名与系统列的表头相同
+    let sheet = ExportSheet::new(sheet.file, sheet.sheet_name, ["行号".to_string(), "编号".to_string()], 1);
+    let mut row = rows[0].clone();
+    row.data_json = json!({"行号": "R7", "编号": "A1"});
+    let groups = vec![(sheet, vec![row])];
+    let info = ExportInfo {
+        context: ExportContext::default(),
+        mode: SearchMode::FullText,
+        exported_at: now,
+        columns: ExportColumns::default(),
+    };
+
+    let csv = String::from_utf8(run_export(ExportFormat::Csv, &groups, &info)[3..].to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "源文件,工作表,行号 (2),编号,行号,导入时间");
+    assert!(lines[1].contains(",2,A1,R7,"), "{}", lines[1]);
+
+    let ndjson = String::from_utf8(run_export(ExportFormat::Ndjson, &groups, &info)).unwrap();
+    let object: Value = serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+    assert_eq!((&object["行号 (2)"], &object["行号"]), (&json!(2), &json!("R7")));
+
+    let json: Value = serde_json::from_slice(&run_export(ExportFormat::Json, &groups, &info)).unwrap();
+    assert_eq!(json["sheets"][0]["columns"], json!(["行号 (2)", "编号", "行号", "导入时间"]));
+}
+
+#[test]
+fn export_metadata_sheet_uses_requested_language() {
+    use calamine::{Data, Reader};
+
+    let now = chrono::Utc::now();
+    let groups = sample_export_groups(now);
+    let labels = ExportMetadataLabels {
+        sheet_name: "Export Info".to_string(),
+        query: "Query".to_string(),
+        search_mode: "Search Mode".to_string(),
+        sheet: "Sheet".to_string(),
+        fuzzy: "Fuzzy search (similarity threshold {threshold})".to_string(),
+        ..Default::default()
+    };
+    let info = ExportInfo {
+        context: ExportContext {
+            query: "上海".to_string(),
+            scope: "公开数据".to_string(),
+            labels,
+        },
+        mode: SearchMode::Fuzzy { threshold: 0.4 },
+        exported_at: now,
+        columns: ExportColumns::default(),
+    };
+    let buffer = run_export(ExportFormat::Xlsx, &groups, &info);
+    let mut workbook = calamine::Xlsx::new(std::io::Cursor::new(buffer)).unwrap();
+    assert_eq!(workbook.sheet_names().last().unwrap(), "Export Info");
+
+    let range = workbook.worksheet_range("Export Info").unwrap();
+    let rows: Vec<Vec<Data>> = range.rows().map(|row| row.to_vec()).collect();
+    let text = |cells: &[&str]| cells.iter().map(|cell| Data::String(cell.to_string())).collect::<Vec<_>>();
+    assert_eq!(rows[0][..2], text(&["Query", "上海"]));
+    assert_eq!(rows[1][..2], text(&["Search Mode", "Fuzzy search (similarity threshold 0.4)"]));
+    assert_eq!(rows[6][..2], text(&["Sheet", "源文件"]));
+
+    // 保存的任务请求中没有标签时使用默认的中文标签
+    let stored: ExportContext = serde_json::from_value(json!({"query": "上海", "scope": "公开数据"})).unwrap();
+    assert_eq!(stored.labels, ExportMetadataLabels::default());
+}
@@ -0,0 +1,256 @@
+use chrono::NaiveDateTime;
+use excel_data_hub::search_query::{
+    parse_range_filters, parse_search_query, QueryExpr, QuerySyntaxError, RangeBound, RangeFilter, RangeValue, SearchTerm,
+};
+
+fn word(value: &str) -> QueryExpr {
+    QueryExpr::Term(SearchTerm { field: None, value: value.into(), phrase: false })
+}
+
+fn phrase(value: &str) -> QueryExpr {
+    QueryExpr::Term(SearchTerm { field: None, value: value.into(), phrase: true })
+}
+
+fn field(name: &str, value: &str, phrase: bool) -> QueryExpr {
+    QueryExpr::Term(SearchTerm { field: Some(name.into()), value: value.into(), phrase })
+}
+
+fn not(expr: QueryExpr) -> QueryExpr {
+    QueryExpr::Not(Box::new(expr))
+}
+
+fn parse(input: &str) -> QueryExpr {
+    parse_search_query(input).unwrap().expr
+}
+
+fn parse_error(input: &str) -> QuerySyntaxError {
+    parse_search_query(input).unwrap_err()
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(
+        parse("a OR b AND c"),
+        QueryExpr::Or(vec![word("a"), QueryExpr::And(vec![word("b"), word("c")])])
+    );
+    // 省略的运算符与显式AND优先级相同
+    assert_eq!(
+        parse("a OR b -c"),
+        QueryExpr::Or(vec![word("a"), QueryExpr::And(vec![word("b"), not(word("c"))])])
+    );
+}
+
+#[test]
+fn not_applies_to_the_next_condition_only() {
+    assert_eq!(parse("NOT a b"), QueryExpr::And(vec![not(word("a")), word("b")]));
+    assert_eq!(
+        parse("NOT (a OR b) c"),
+        QueryExpr::And(vec![not(QueryExpr::Or(vec![word("a"), word("b")])), word("c")])
+    );
+}
+
+#[test]
+fn parentheses_override_precedence() {
+    assert_eq!(
+        parse("(a OR b) c"),
+        QueryExpr::And(vec![QueryExpr::Or(vec![word("a"), word("b")]), word("c")])
+    );
+}
+
+#[test]
+fn lowercase_operators_are_keywords() {
+    let parsed = parse_search_query("a or b").unwrap();
+    assert_eq!(parsed.plain_keywords(), Some(vec!["a", "or", "b"]));
+}
+
+#[test]
+fn plain_keywords_only_without_query_syntax() {
+    assert_eq!(parse_search_query("乌鲁木齐 喀什").unwrap().plain_keywords(), Some(vec!["乌鲁木齐", "喀什"]));
+    for query in ["a AND b", "a -b", "\"a b\"", "city:a b", "(a b)"] {
+        assert_eq!(parse_search_query(query).unwrap().plain_keywords(), None, "{}", query);
+    }
+}
+
+#[test]
+fn parses_quoted_phrases_with_escapes() {
+    assert_eq!(parse("\"New York\""), phrase("New York"));
+    assert_eq!(parse(r#""say \"hi\"""#), phrase("say \"hi\""));
+}
+
+#[test]
+fn parses_field_terms() {
+    assert_eq!(parse("城市:乌鲁木齐"), field("城市", "乌鲁木齐", false));
+    assert_eq!(parse("city:\"New York\""), field("city", "New York", true));
+    assert_eq!(parse("\"Full Name\":Ali"), field("Full Name", "Ali", false));
+    assert_eq!(
+        parse("-city:Kashgar amount:100"),
+        QueryExpr::And(vec![not(field("city", "Kashgar", false)), field("amount", "100", false)])
+    );
+}
+
+#[test]
+fn numeric_prefix_is_not_a_field() {
+    assert_eq!(parse("10:30"), word("10:30"));
+}
+
+#[test]
+fn minus_before_a_number_is_a_negative_value() {
+    assert_eq!(parse("-5"), word("-5"));
+    assert_eq!(parse("-.5"), word("-.5"));
+    assert_eq!(parse_search_query("a -5").unwrap().plain_keywords(), Some(vec!["a", "-5"]));
+    assert_eq!(parse("-a"), not(word("a")));
+    assert_eq!(parse("amount:-5"), field("amount", "-5", false));
+}
+
+#[test]
+fn reports_unbalanced_parentheses() {
+    let error = parse_error("a (b OR c");
+    assert_eq!(error.message, "括号未闭合");
+    assert_eq!(error.position, 2);
+
+    let error = parse_error("a b)");
+    assert_eq!(error.message, "多余的右括号");
+    assert_eq!(error.position, 3);
+
+    let error = parse_error("()");
+    assert_eq!(error.message, "右括号前缺少搜索条件");
+    assert_eq!(error.position, 1);
+}
+
+#[test]
+fn reports_unclosed_quotes_at_the_opening_quote() {
+    let error = parse_error("city:\"New York");
+    assert_eq!(error.message, "引号未闭合");
+    assert_eq!(error.position, 5);
+
+    let error = parse_error("乌鲁木齐 \"abc");
+    assert_eq!(error.message, "引号未闭合");
+    assert_eq!(error.position, 5);
+}
+
+#[test]
+fn reports_incomplete_expressions() {
+    let error = parse_error("OR a");
+    assert_eq!(error.message, "运算符前缺少搜索条件");
+    assert_eq!(error.position, 0);
+
+    let error = parse_error("a AND");
+    assert_eq!(error.message, "查询不完整，缺少搜索条件");
+    assert_eq!(error.position, 5);
+
+    let error = parse_error("a city: b");
+    assert_eq!(error.message, "字段缺少搜索值");
+    assert_eq!(error.position, 2);
+
+    assert_eq!(parse_error("   ").message, "查询内容为空");
+    assert_eq!(parse_error("\"  \"").message, "引号内容为空");
+}
+
+#[test]
+fn limits_nesting_and_term_count() {
+    let nested = format!("{}a{}", "(".repeat(20), ")".repeat(20));
+    assert!(parse_error(&nested).message.starts_with("嵌套层数过多"));
+
+    let many = vec!["a"; 40].join(" AND ");
+    assert!(parse_error(&many).message.starts_with("搜索条件过多"));
+}
+
+fn filters(params: &[(&str, &str)]) -> Result<Vec<RangeFilter>, String> {
+    let params: Vec<(String, String)> = params.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+    parse_range_filters(&params)
+}
+
+fn filter(key: &str, value: &str) -> RangeFilter {
+    filters(&[(key, value)]).unwrap().remove(0)
+}
+
+fn number(value: &str, inclusive: bool) -> Option<RangeBound> {
+    Some(RangeBound { value: RangeValue::Number(value.into()), inclusive })
+}
+
+fn datetime(value: &str, inclusive: bool) -> Option<RangeBound> {
+    let value = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap();
+    Some(RangeBound { value: RangeValue::DateTime(value), inclusive })
+}
+
+#[test]
+fn parses_closed_and_open_number_ranges() {
+    let range = filter("filter[amount]", "1000..5000");
+    assert_eq!(range.field, "amount");
+    assert_eq!((range.lower, range.upper), (number("1000", true), number("5000", true)));
+
+    let range = filter("filter[amount]", "1000..");
+    assert_eq!((range.lower, range.upper), (number("1000", true), None));
+
+    let range = filter("filter[amount]", "..-2.5");
+    assert_eq!((range.lower, range.upper), (None, number("-2.5", true)));
+
+    let range = filter("filter[amount]", "100");
+    assert_eq!((range.lower, range.upper), (number("100", true), number("100", true)));
+}
+
+#[test]
+fn rejoins_comparison_operators_split_by_url_parsing() {
+    // filter[amount]>=1000 在URL解析时被拆成键 filter[amount]> 和值 1000
+    let range = filter("filter[amount]>", "1000");
+    assert_eq!((range.lower, range.upper), (number("1000", true), None));
+
+    let range = filter("filter[amount]<", "1000");
+    assert_eq!((range.lower, range.upper), (None, number("1000", true)));
+
+    // filter[amount]>1000 没有等号，整体作为键
+    let range = filter("filter[amount]>1000", "");
+    assert_eq!((range.lower, range.upper), (number("1000", false), None));
+
+    let range = filter("filter[amount]<1000", "");
+    assert_eq!((range.lower, range.upper), (None, number("1000", false)));
+}
+
+#[test]
+fn date_only_bounds_cover_whole_days() {
+    let range = filter("filter[date]", "2024-01-01..2024-01-31");
+    assert_eq!(range.lower, datetime("2024-01-01 00:00:00", true));
+    assert_eq!(range.upper, datetime("2024-02-01 00:00:00", false));
+
+    let range = filter("filter[date]>2024-01-15", "");
+    assert_eq!(range.lower, datetime("2024-01-16 00:00:00", true));
+
+    let range = filter("filter[date]<2024-01-15", "");
+    assert_eq!(range.upper, datetime("2024-01-15 00:00:00", false));
+
+    // 带时间的边界保持原样
+    let range = filter("filter[date]", "2024-01-01 08:30..2024-01-01T18:00:00");
+    assert_eq!(range.lower, datetime("2024-01-01 08:30:00", true));
+    assert_eq!(range.upper, datetime("2024-01-01 18:00:00", true));
+}
+
+#[test]
+fn rejects_reversed_and_mixed_bounds() {
+    assert_eq!(filters(&[("filter[amount]", "5000..1000")]).unwrap_err(), "筛选字段 amount 的下限大于上限");
+    assert_eq!(filters(&[("filter[date]", "2024-02-01..2024-01-01")]).unwrap_err(), "筛选字段 date 的下限大于上限");
+    assert!(filters(&[("filter[date]", "2024-01-01..2024-01-01")]).is_ok());
+    assert_eq!(filters(&[("filter[x]", "1..2024-01-01")]).unwrap_err(), "筛选字段 x 的上下限类型不一致");
+}
+
+#[test]
+fn rejects_malformed_filters() {
+    for (key, value) in [
+        ("filter[amount]", "abc"),
+        ("filter[amount]", ".."),
+        ("filter[amount]", "1..2..3"),
+        ("filter[amount]", "1e999"),
+        ("filter[date]", "2024-02-30"),
+        ("filter[amount]>", ""),
+        ("filter[]", "1"),
+        ("filter[amount", "1"),
+        ("filter[amount]x", "1"),
+    ] {
+        assert!(filters(&[(key, value)]).is_err(), "{}={}", key, value);
+    }
+}
+
+#[test]
+fn ignores_other_parameters() {
+    let parsed = filters(&[("q", "abc"), ("workspace_id", "1"), ("filter[a]", "1"), ("filter[b]", "2..")]).unwrap();
+    assert_eq!(parsed.iter().map(|filter| filter.field.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+}
@@ -0,0 +1,48 @@
+use excel_data_hub::text_normalize::{contains_cjk, fold_full_width, normalize_for_search};
+
+#[test]
+fn folds_full_width_ascii_and_ideographic_space() {
+    assert_eq!(normalize_for_search("ＡＢＣ１２３"), "ABC123");
+    assert_eq!(normalize_for_search("乌鲁木齐\u{3000}喀什"), "乌鲁木齐 喀什");
+    assert_eq!(normalize_for_search("（１）：Ｅ００１"), "(1):E001");
+}
+
+#[test]
+fn folds_arabic_presentation_forms_to_base_letters() {
+    // 词首形式的س与独立形式一致
+    assert_eq!(normalize_for_search("\u{FEB3}"), "\u{0633}");
+    // lam-alef连写字形分解为两个字母
+    assert_eq!(normalize_for_search("\u{FEFB}"), "\u{0644}\u{0627}");
+}
+
+#[test]
+fn removes_arabic_tatweel() {
+    assert_eq!(normalize_for_search("كـتـاب"), "كتاب");
+    // 表现形式分解后产生的延长符同样被移除
+    assert_eq!(normalize_for_search("\u{FE77}"), "\u{064E}");
+}
+
+#[test]
+fn keeps_cjk_and_uyghur_text_unchanged() {
+    for text in ["乌鲁木齐市", "ئۈرۈمچی شەھىرى", "Kashgar 2024"] {
+        assert_eq!(normalize_for_search(text), text);
+    }
+}
+
+#[test]
+fn fold_full_width_only_changes_width() {
+    assert_eq!("１３８\u{3000}０".chars().map(fold_full_width).collect::<String>(), "138 0");
+    // 其他兼容字符不属于宽度折叠
+    assert_eq!(fold_full_width('①'), '①');
+    assert_eq!(normalize_for_search("①"), "1");
+}
+
+#[test]
+fn detects_cjk_text() {
+    for text in ["木齐", "abc東京", "カタカナ", "서울", "\u{20BB7}"] {
+        assert!(contains_cjk(text), "{}", text);
+    }
+    for text in ["Kashgar", "ئۈرۈمچی", "１２３", "..."] {
+        assert!(!contains_cjk(text), "{}", text);
+    }
+}
@@ -0,0 +1,114 @@
+use excel_data_hub::web_server::upload_storage_test_router;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const BOUNDARY: &str = "excel-data-hub-test-boundary";
+
+fn upload_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_upload_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn dir_entries(dir: &Path) -> Vec<String> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// 启动只保存上传文件的服务，响应为 `哈希 文件名 格式扩展名`，空文件为 `empty`
+async fn serve(upload_dir: PathBuf, limit: usize) -> SocketAddr {
+    let app = upload_storage_test_router(upload_dir.to_string_lossy().into_owned(), limit);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// 以multipart上传一个文件，返回状态码与响应体；chunked为true时不声明请求体长度
+async fn upload(addr: SocketAddr, file_name: &str, contents: &[u8], chunked: bool) -> (u16, String) {
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        BOUNDARY, file_name
+    )
+    .into_bytes();
+    body.extend_from_slice(contents);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+    let length = if chunked {
+        let mut framed = format!("{:x}\r\n", body.len()).into_bytes();
+        framed.extend_from_slice(&body);
+        framed.extend_from_slice(b"\r\n0\r\n\r\n");
+        body = framed;
+        "Transfer-Encoding: chunked".to_string()
+    } else {
+        format!("Content-Length: {}", body.len())
+    };
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let head = format!(
+        "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: multipart/form-data; boundary={}\r\n{}\r\nConnection: close\r\n\r\n",
+        addr, BOUNDARY, length
+    );
+    // 超过上限时服务端可能在请求体发送完之前就返回并关闭连接
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&body).await;
+
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response).await;
+    let response = String::from_utf8_lossy(&response).into_owned();
+    let status = response.split(' ').nth(1).and_then(|code| code.parse().ok()).unwrap_or(0);
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or_default().to_string();
+    (status, body)
+}
+
+#[tokio::test]
+async fn streams_upload_to_disk_with_hash_and_detected_extension() {
+    let dir = upload_dir("stream");
+    let addr = serve(dir.clone(), 1024 * 1024).await;
+    let contents = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sample.csv")).unwrap();
+
+    // 扩展名与内容不符时按内容保存
+    let (status, body) = upload(addr, "sample.xlsx", &contents, false).await;
+    assert_eq!(status, 200, "{}", body);
+    let parts: Vec<&str> = body.split(' ').collect();
+    assert_eq!(parts[0], format!("{:x}", md5::compute(&contents)));
+    assert!(parts[1].starts_with("1_") && parts[1].ends_with(".csv"), "{}", parts[1]);
+    assert_eq!(parts[2], "csv");
+
+    // 临时文件已改名，上传目录中只有保存的文件且内容不变
+    assert_eq!(dir_entries(&dir), vec![parts[1].to_string()]);
+    assert_eq!(std::fs::read(dir.join(parts[1])).unwrap(), contents);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn skips_empty_uploads() {
+    let dir = upload_dir("empty");
+    let addr = serve(dir.clone(), 1024 * 1024).await;
+
+    assert_eq!(upload(addr, "empty.csv", b"", false).await, (200, "empty".to_string()));
+    assert!(dir_entries(&dir).is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn rejects_uploads_over_the_limit_and_removes_partial_files() {
+    let dir = upload_dir("limit");
+    let addr = serve(dir.clone(), 4 * 1024).await;
+
+    let contents = "编号,城市\n".to_string() + &"1,乌鲁木齐\n".repeat(2000);
+    // 声明了长度的请求在读取前拒绝；分块传输的请求在写入临时文件的过程中超过上限，临时文件随之删除
+    for chunked in [false, true] {
+        let (status, body) = upload(addr, "cities.csv", contents.as_bytes(), chunked).await;
+        assert_eq!(status, 413, "chunked={} {}", chunked, body);
+        assert!(dir_entries(&dir).is_empty(), "{:?}", dir_entries(&dir));
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use excel_data_hub::column_profile::{self, ColumnMapping};
+use excel_data_hub::excel_processor_sea::{ExcelProcessor, ImportMode, ImportOptions};
+use excel_data_hub::models::entity::excel_data;
+use excel_data_hub::row_validation::RowViolation;
+use excel_data_hub::upsert_key::{check_keys, key_value, match_existing, row_key, UpsertAction};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+fn row(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn keys(columns: &[&str]) -> Vec<String> {
+    columns.iter().map(|column| column.to_string()).collect()
+}
+
+fn existing_row(id: i32, data_json: Value) -> excel_data::Model {
+    excel_data::Model {
+        id,
+        workspace_id: Some(1),
+        file_id: 1,
+        import_time: chrono::Utc::now(),
+        row_number: id,
+        sheet_name: "Sheet1".into(),
+        data_json,
+        typed_json: json!({}),
+        search_text: String::new(),
+    }
+}
+
+/// 按 (工作表, 数据) 依次检查键列，返回每行的违规规则与说明
+fn check(key_columns: &[&str], rows: &[(&str, Value)]) -> Vec<Vec<(String, String)>> {
+    let data: Vec<HashMap<String, Value>> = rows.iter().map(|(_, value)| row(value.clone())).collect();
+    let mut violations: Vec<Vec<RowViolation>> = vec![Vec::new(); rows.len()];
+    check_keys(
+        &keys(key_columns),
+        rows.iter()
+            .zip(&data)
+            .zip(violations.iter_mut())
+            .enumerate()
+            .map(|(index, (((sheet_name, _), data), violations))| (*sheet_name, index + 1, data, violations)),
+    );
+    violations
+        .into_iter()
+        .map(|row| row.into_iter().map(|violation| (violation.column, violation.message)).collect())
+        .collect()
+}
+
+#[test]
+fn key_value_ignores_case_and_surrounding_whitespace() {
+    let data = row(json!({"Employee ID": "  E001 ", "Amount": 12, "Empty": "  ", "Missing": null}));
+    assert_eq!(key_value(&data, "employee id"), Some("E001".into()));
+    assert_eq!(key_value(&data, " EMPLOYEE ID "), Some("E001".into()));
+    assert_eq!(key_value(&data, "amount"), Some("12".into()));
+    assert_eq!(key_value(&data, "Empty"), None);
+    assert_eq!(key_value(&data, "Missing"), None);
+    assert_eq!(key_value(&data, "Other"), None);
+}
+
+#[test]
+fn row_key_requires_every_key_column() {
+    let data = row(json!({"工号": "E001", "部门": "财务", "备注": ""}));
+    assert_eq!(row_key(&data, &keys(&["工号", "部门"])), Some(vec!["E001".into(), "财务".into()]));
+    assert_eq!(row_key(&data, &keys(&["工号", "备注"])), None);
+    assert_eq!(row_key(&data, &keys(&["工号", "城市"])), None);
+}
+
+#[test]
+fn check_keys_rejects_empty_and_duplicate_keys() {
+    let violations = check(
+        &["工号", "部门"],
+        &[
+            ("Sheet1", json!({"工号": "E001", "部门": "财务"})),
+            ("Sheet1", json!({"工号": "", "部门": "财务"})),
+            ("Sheet1", json!({"部门": ""})),
+            ("Sheet2", json!({"工号": " e001 ", "部门": "财务"})),
+            ("Sheet2", json!({"工号": "E001", "部门": "财务 "})),
+        ],
+    );
+
+    assert!(violations[0].is_empty());
+    assert_eq!(violations[1], vec![("工号".to_string(), "键列 工号 不能为空".to_string())]);
+    assert_eq!(violations[2].len(), 2);
+    // 键值区分大小写，只忽略首尾空白
+    assert!(violations[3].is_empty());
+    assert_eq!(
+        violations[4],
+        vec![("工号, 部门".to_string(), "键 (E001, 财务) 与工作表 Sheet1 第 1 行重复".to_string())]
+    );
+}
+
+#[test]
+fn check_keys_matches_key_columns_case_insensitively() {
+    let violations = check(
+        &["employee id"],
+        &[("Sheet1", json!({"Employee ID": "E001"})), ("Sheet1", json!({"EMPLOYEE ID": "E001"}))],
+    );
+    assert!(violations[0].is_empty());
+    assert_eq!(violations[1].len(), 1);
+}
+
+#[test]
+fn match_existing_counts_inserted_updated_and_unchanged_rows() {
+    let key_columns = keys(&["工号"]);
+    let mut existing: HashMap<Vec<String>, Vec<excel_data::Model>> = HashMap::new();
+    existing.insert(vec!["E001".into()], vec![existing_row(1, json!({"工号": "E001", "姓名": "阿里"}))]);
+    existing.insert(
+        vec!["E002".into()],
+        vec![
+            existing_row(2, json!({"工号": "E002", "姓名": "古丽"})),
+            existing_row(3, json!({"工号": "E002", "姓名": "古丽娜"})),
+        ],
+    );
+
+    let incoming = [
+        json!({"工号": "E001", "姓名": "阿里"}),
+        json!({"工号": "E002", "姓名": "古丽娜"}),
+        json!({"工号": "E003", "姓名": "买买提"}),
+        json!({"姓名": "没有工号"}),
+    ];
+    let actions: Vec<UpsertAction> = incoming
+        .iter()
+        .map(|data| {
+            let key = row_key(&row(data.clone()), &key_columns);
+            match_existing(key.as_ref(), data, &json!({}), &existing)
+        })
+        .collect();
+
+    assert_eq!(
+        actions,
+        vec![
+            UpsertAction::Unchanged,
+            // 同一键的多行中只更新取值不同的行
+            UpsertAction::Update(vec![2]),
+            UpsertAction::Insert,
+            UpsertAction::Insert,
+        ]
+    );
+
+    let count = |wanted: fn(&UpsertAction) -> bool| actions.iter().filter(|action| wanted(action)).count();
+    assert_eq!(count(|action| matches!(action, UpsertAction::Insert)), 2);
+    assert_eq!(count(|action| matches!(action, UpsertAction::Update(_))), 1);
+    assert_eq!(count(|action| matches!(action, UpsertAction::Unchanged)), 1);
+}
+
+#[test]
+fn match_existing_updates_when_only_native_types_change() {
+    let mut existing = HashMap::new();
+    existing.insert(vec!["E001".into()], vec![existing_row(7, json!({"工号": "E001", "金额": "12"}))]);
+    let data = json!({"工号": "E001", "金额": "12"});
+    let typed = json!({"金额": {"type": "number", "value": 12}});
+    assert_eq!(
+        match_existing(Some(&vec!["E001".into()]), &data, &typed, &existing),
+        UpsertAction::Update(vec![7])
+    );
+}
+
+/// 键列与唯一列在工作区其他文件中的查找（列名不区分大小写），需要PostgreSQL
+#[tokio::test]
+async fn finds_existing_rows_by_key_and_unique_values() {
+    let Some((db, user, workspace)) = common::setup("upsert_key_lookup_test").await else {
+        return;
+    };
+
+    let dir = std::env::temp_dir().join(format!("excel_data_hub_upsert_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let processor = ExcelProcessor::new(db.clone());
+    let import = |name: &str, content: &str, options: ImportOptions| {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        let (processor, user_id, name) = (&processor, user.id, name.to_string());
+        async move {
+            processor
+                .import_uploaded_file(workspace.id, path.to_str().unwrap(), Some(user_id), &name, None, &options, None)
+                .await
+                .unwrap()
+        }
+    };
+
+    let first = import("orders.csv", "Code,Name\nA1,甲\nA2,乙\n", ImportOptions::default()).await;
+    assert_eq!(first.inserted_rows, 2);
+
+    let upsert = ImportOptions { mode: ImportMode::Upsert, key_columns: keys(&["code"]), ..Default::default() };
+    let second = import("changes.csv", "Code,Name\nA1,甲甲\nA2,乙\nA3,丙\n", upsert).await;
+    assert_eq!((second.updated_rows, second.unchanged_rows, second.inserted_rows), (1, 1, 1));
+
+    let mapping: ColumnMapping =
+        serde_json::from_value(json!({"validation": {"rules": [{"column": "name", "unique": true}]}})).unwrap();
+    column_profile::save_profile(&db, workspace.id, user.id, &mapping).await.unwrap();
+    let third = import("more.csv", "Code,NAME\nB1,丙\nB2,丁\nB3,甲甲\n", ImportOptions::default()).await;
+    assert_eq!((third.inserted_rows, third.rejected_rows), (1, 2));
+
+    common::teardown(&db, &user).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}